@@ -0,0 +1,10 @@
+// Screen space ambient occlusion, see `render::AmbientOcclusion`.
+
+// Occlusion in the red channel, a white texture when disabled.
+uniform sampler2D t_AmbientOcclusion;
+
+// Fraction of the ambient light reaching the current fragment.
+float ambient_occlusion() {
+    vec2 coord = gl_FragCoord.xy / vec2(textureSize(t_AmbientOcclusion, 0));
+    return texture(t_AmbientOcclusion, coord).r;
+}
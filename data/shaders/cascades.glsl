@@ -0,0 +1,43 @@
+// Matches `render::MAX_CASCADES`.
+#define MAX_CASCADES            4
+// Matches `render::CASCADE_SHADOW_INDEX`.
+#define CASCADE_SHADOW_INDEX    3
+
+struct Cascade {
+    mat4 projection;
+    vec4 split;
+};
+
+layout(std140) uniform b_Cascades {
+    Cascade u_Cascades[MAX_CASCADES];
+};
+
+uniform sampler2DArrayShadow t_ShadowCascades;
+
+// Shadow factor of a world space point from the cascaded shadow map.
+// `split.x` is the view depth where a cascade ends, or zero if it's unused.
+// Requires the `globals` and `shadows` includes.
+float cascaded_shadow(vec3 world, int type, int kernel, float bias) {
+    float depth = -(u_View * vec4(world, 1.0)).z;
+    for (int i = 0; i < MAX_CASCADES; ++i) {
+        if (depth < u_Cascades[i].split.x) {
+            vec4 lit_space = u_Cascades[i].projection * vec4(world, 1.0);
+            vec3 coord = 0.5 * lit_space.xyz / lit_space.w + 0.5;
+            coord.z -= bias;
+            if (type == SHADOW_BASIC) {
+                return texture(t_ShadowCascades, vec4(coord.xy, float(i), coord.z));
+            }
+            // other filters fall back to PCF
+            vec2 spacing = 0.5 * float(kernel) / (1.5 * vec2(textureSize(t_ShadowCascades, 0).xy));
+            float sum = 0.0;
+            for (int y = 0; y < 4; ++y) {
+                for (int x = 0; x < 4; ++x) {
+                    vec2 offset = (vec2(x, y) - 1.5) * spacing;
+                    sum += texture(t_ShadowCascades, vec4(coord.xy + offset, float(i), coord.z));
+                }
+            }
+            return sum / 16.0;
+        }
+    }
+    return 1.0;
+}
@@ -0,0 +1,9 @@
+#version 150 core
+
+in vec3 v_Normal;
+
+out vec4 Target0;
+
+void main() {
+    Target0 = vec4(normalize(v_Normal), gl_FragCoord.z);
+}
@@ -0,0 +1,21 @@
+#version 150 core
+#include <globals>
+
+in vec4 a_Position;
+in vec4 a_Normal;
+in vec4 i_World0;
+in vec4 i_World1;
+in vec4 i_World2;
+// Rows of the inverse transpose of the world matrix, for the normals.
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
+
+out vec3 v_Normal;
+
+void main() {
+    mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    mat3 m_Normal = transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz));
+    v_Normal = mat3(u_View) * m_Normal * a_Normal.xyz;
+    gl_Position = u_ViewProj * m_World * a_Position;
+}
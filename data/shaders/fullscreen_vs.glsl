@@ -0,0 +1,12 @@
+#version 150 core
+
+out vec2 v_TexCoord;
+
+void main() {
+    vec2 pos = gl_VertexID == 0 ? vec2(-1.0, -1.0) :
+               gl_VertexID == 1 ? vec2( 1.0, -1.0) :
+               gl_VertexID == 2 ? vec2(-1.0,  1.0) :
+                                  vec2( 1.0,  1.0) ;
+    v_TexCoord = 0.5 * pos + 0.5;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
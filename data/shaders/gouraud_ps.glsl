@@ -1,27 +1,42 @@
 #version 150 core
+#include <lights>
+#include <shadows>
+#include <ambient_occlusion>
 
 in vec4 v_ResultColor;
 flat in vec4 v_ResultColorFlat;
+in vec4 v_Ambient;
+flat in vec4 v_AmbientFlat;
 flat in float v_Smooth;
-in vec4 v_LightEval[2];
-flat in vec4 v_LightEvalFlat[2];
-in vec4 v_ShadowCoord[2];
+in vec4 v_LightEval[3];
+flat in vec4 v_LightEvalFlat[3];
+in vec4 v_ShadowCoord[3];
+flat in vec2 v_ShadowCubeRange;
+flat in vec4 v_ShadowFilter[3];
 
 out vec4 Target0;
 
 uniform sampler2DShadow t_Shadow0;
 uniform sampler2DShadow t_Shadow1;
+uniform samplerCubeShadow t_ShadowCube0;
 
 void main() {
     Target0 = mix(v_ResultColorFlat, v_ResultColor, v_Smooth);
+    Target0 += ambient_occlusion() * mix(v_AmbientFlat, v_Ambient, v_Smooth);
     if (v_ShadowCoord[0].w != 0.0) {
-        vec3 coord = v_ShadowCoord[0].xyz / v_ShadowCoord[0].w;
-        float shadow = texture(t_Shadow0, 0.5 * coord + 0.5);
+        vec4 f = v_ShadowFilter[0];
+        float shadow = shadow_2d(t_Shadow0, t_ShadowDepth0, v_ShadowCoord[0], int(f.x), int(f.y), f.z, f.w);
         Target0 += shadow * mix(v_LightEvalFlat[0], v_LightEval[0], v_Smooth);
     }
     if (v_ShadowCoord[1].w != 0.0) {
-        vec3 coord = v_ShadowCoord[1].xyz / v_ShadowCoord[1].w;
-        float shadow = texture(t_Shadow1, 0.5 * coord + 0.5);
+        vec4 f = v_ShadowFilter[1];
+        float shadow = shadow_2d(t_Shadow1, t_ShadowDepth1, v_ShadowCoord[1], int(f.x), int(f.y), f.z, f.w);
         Target0 += shadow * mix(v_LightEvalFlat[1], v_LightEval[1], v_Smooth);
     }
+    if (v_ShadowCoord[2].w != 0.0) {
+        vec4 f = v_ShadowFilter[2];
+        float shadow = shadow_cube(t_ShadowCube0, v_ShadowCoord[2].xyz, v_ShadowCubeRange, int(f.x), int(f.y), f.z);
+        Target0 += shadow * mix(v_LightEvalFlat[2], v_LightEval[2], v_Smooth);
+    }
+    Target0.a = v_ResultColorFlat.a;
 }
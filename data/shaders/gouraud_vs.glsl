@@ -1,38 +1,52 @@
 #version 150 core
 #include <lights>
 #include <globals>
+#include <shadows>
 
-#define MAX_SHADOWS 2
+#define MAX_SHADOWS 3
 
 in vec4 a_Position;
 in vec4 a_Normal;
 out vec4 v_ResultColor;
 flat out vec4 v_ResultColorFlat;
+out vec4 v_Ambient;
+flat out vec4 v_AmbientFlat;
 flat out float v_Smooth;
 out vec4 v_LightEval[MAX_SHADOWS];
 flat out vec4 v_LightEvalFlat[MAX_SHADOWS];
 out vec4 v_ShadowCoord[MAX_SHADOWS];
+flat out vec2 v_ShadowCubeRange;
+// x: shadow type, y: kernel size, z: bias, w: light size
+flat out vec4 v_ShadowFilter[MAX_SHADOWS];
 
 in vec4 i_World0;
 in vec4 i_World1;
 in vec4 i_World2;
+// Rows of the inverse transpose of the world matrix, for the normals.
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
 in vec4 i_MatParams;
 in vec4 i_Color;
 in vec4 i_UvRange;
 
 void main() {
     mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    mat3 m_Normal = transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz));
     vec4 world = m_World * a_Position;
-    vec3 normal = normalize(mat3(m_World) * a_Normal.xyz);
+    vec3 normal = normalize(m_Normal * a_Normal.xyz);
     for(int i=0; i<MAX_SHADOWS; ++i) {
         v_ShadowCoord[i] = vec4(0.0);
         v_LightEval[i] = v_LightEvalFlat[i] = vec4(0.0);
+        v_ShadowFilter[i] = vec4(0.0);
     }
+    v_ShadowCubeRange = vec2(0.0);
     v_ResultColor = vec4(0.0);
+    v_Ambient = vec4(0.0);
     v_Smooth = i_MatParams.x;
 
-    for(uint i=0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
-        Light light = u_Lights[i];
+    for(uint i=0U; i < u_NumLights; ++i) {
+        Light light = fetch_light(i);
         vec3 dir = light.pos.xyz - light.pos.w * world.xyz;
         // evaluate light color
         float dot_nl = dot(normal, normalize(dir));
@@ -41,12 +55,21 @@ void main() {
             irradiance = mix(light.color_back, light.color, dot_nl*0.5 + 0.5);
             dot_nl = 0.0;
         }
-        v_ResultColor += light.intensity.x * i_Color * irradiance; //ambient
-        vec4 color = light.intensity.y * max(0.0, dot_nl) * i_Color * light.color;
+        v_Ambient += light.intensity.x * i_Color * irradiance;
+        vec4 color = light.intensity.y * max(0.0, dot_nl) * spot_factor(light, dir) * i_Color * light.color;
         // compute shadow coordinates
         int shadow_index = light.shadow_params[0];
         if (0 <= shadow_index && shadow_index < MAX_SHADOWS) {
             v_ShadowCoord[shadow_index] = light.projection * world;
+            if (shadow_index == CUBE_SHADOW_INDEX) {
+                v_ShadowCubeRange = light.focus.zw;
+            }
+            v_ShadowFilter[shadow_index] = vec4(
+                light.shadow_params[1],
+                light.shadow_params[2],
+                shadow_bias(light.shadow_filter, dot_nl),
+                light.shadow_filter.z
+            );
             v_LightEval[shadow_index] = color;
             v_LightEvalFlat[shadow_index] = color;
         } else {
@@ -54,6 +77,9 @@ void main() {
         }
     }
 
+    v_ResultColor.a = i_Color.a;
     v_ResultColorFlat = v_ResultColor;
+    v_Ambient.a = 0.0;
+    v_AmbientFlat = v_Ambient;
     gl_Position = u_ViewProj * world;
 }
@@ -0,0 +1,132 @@
+#version 150 core
+
+// Matches the `IBL_MODE_*` constants of the renderer.
+#define MODE_EQUIRECT       0
+#define MODE_IRRADIANCE     1
+#define MODE_SPECULAR       2
+#define MODE_BRDF           3
+
+uniform sampler2D t_Equirect;
+uniform samplerCube t_Cube;
+
+layout(std140) uniform b_IblParams {
+    vec4 u_Forward;
+    vec4 u_Right;
+    vec4 u_Up;
+    // x: mode, y: roughness
+    vec4 u_Params;
+};
+
+in vec2 v_Ndc;
+
+out vec4 Target0;
+
+const float PI = 3.141592653589793;
+const uint SAMPLE_COUNT = 512U;
+const float IRRADIANCE_DELTA = 0.025;
+
+// Low discrepancy point set, see http://holger.dammertz.org/stuff/notes_HammersleyOnHemisphere.html
+vec2 hammersley(uint i) {
+    uint bits = (i << 16U) | (i >> 16U);
+    bits = ((bits & 0x55555555U) << 1U) | ((bits & 0xAAAAAAAAU) >> 1U);
+    bits = ((bits & 0x33333333U) << 2U) | ((bits & 0xCCCCCCCCU) >> 2U);
+    bits = ((bits & 0x0F0F0F0FU) << 4U) | ((bits & 0xF0F0F0F0U) >> 4U);
+    bits = ((bits & 0x00FF00FFU) << 8U) | ((bits & 0xFF00FF00U) >> 8U);
+    return vec2(float(i) / float(SAMPLE_COUNT), float(bits) * 2.3283064365386963e-10);
+}
+
+mat3 tangent_frame(vec3 n) {
+    vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 t = normalize(cross(up, n));
+    return mat3(t, cross(n, t), n);
+}
+
+// Half vector around `n` distributed by the GGX lobe of `alpha` roughness.
+vec3 importance_sample_ggx(vec2 xi, float alpha, vec3 n) {
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    return normalize(tangent_frame(n) * h);
+}
+
+vec3 equirect(vec3 dir) {
+    vec2 uv = vec2(
+        atan(dir.z, dir.x) / (2.0 * PI) + 0.5,
+        asin(clamp(dir.y, -1.0, 1.0)) / PI + 0.5
+    );
+    return texture(t_Equirect, uv).rgb;
+}
+
+// Cosine weighted integral of the incoming light over the hemisphere around `n`.
+vec3 irradiance(vec3 n) {
+    mat3 frame = tangent_frame(n);
+    vec3 sum = vec3(0.0);
+    float count = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += IRRADIANCE_DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += IRRADIANCE_DELTA) {
+            vec3 dir = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            sum += texture(t_Cube, frame * dir).rgb * cos(theta) * sin(theta);
+            count += 1.0;
+        }
+    }
+    return PI * sum / count;
+}
+
+// Incoming light convolved with the GGX lobe, assuming the view along the normal.
+vec3 prefilter_specular(vec3 n, float roughness) {
+    if (roughness == 0.0) {
+        return texture(t_Cube, n).rgb;
+    }
+    float alpha = roughness * roughness;
+    vec3 sum = vec3(0.0);
+    float weight = 0.0;
+    for (uint i = 0U; i < SAMPLE_COUNT; ++i) {
+        vec3 h = importance_sample_ggx(hammersley(i), alpha, n);
+        vec3 l = reflect(-n, h);
+        float ndotl = dot(n, l);
+        if (ndotl > 0.0) {
+            sum += texture(t_Cube, l).rgb * ndotl;
+            weight += ndotl;
+        }
+    }
+    return sum / max(weight, 0.001);
+}
+
+// Scale and bias of the specular reflectance, from the split-sum approximation.
+vec2 integrate_brdf(float ndotv, float roughness) {
+    vec3 v = vec3(sqrt(1.0 - ndotv * ndotv), 0.0, ndotv);
+    vec3 n = vec3(0.0, 0.0, 1.0);
+    float alpha = roughness * roughness;
+    float k = alpha / 2.0;
+    vec2 sum = vec2(0.0);
+    for (uint i = 0U; i < SAMPLE_COUNT; ++i) {
+        vec3 h = importance_sample_ggx(hammersley(i), alpha, n);
+        vec3 l = reflect(-v, h);
+        float ndotl = max(l.z, 0.0);
+        float ndoth = max(h.z, 0.0);
+        float vdoth = max(dot(v, h), 0.0);
+        if (ndotl > 0.0) {
+            float g = ndotv / (ndotv * (1.0 - k) + k) * ndotl / (ndotl * (1.0 - k) + k);
+            float g_vis = g * vdoth / (ndoth * ndotv);
+            float fc = pow(1.0 - vdoth, 5.0);
+            sum += vec2(1.0 - fc, fc) * g_vis;
+        }
+    }
+    return sum / float(SAMPLE_COUNT);
+}
+
+void main() {
+    int mode = int(u_Params.x);
+    vec3 dir = normalize(u_Forward.xyz + v_Ndc.x * u_Right.xyz + v_Ndc.y * u_Up.xyz);
+    if (mode == MODE_EQUIRECT) {
+        Target0 = vec4(equirect(dir), 1.0);
+    } else if (mode == MODE_IRRADIANCE) {
+        Target0 = vec4(irradiance(dir), 1.0);
+    } else if (mode == MODE_SPECULAR) {
+        Target0 = vec4(prefilter_specular(dir, u_Params.y), 1.0);
+    } else {
+        vec2 uv = 0.5 * v_Ndc + 0.5;
+        Target0 = vec4(integrate_brdf(max(uv.x, 0.001), uv.y), 0.0, 1.0);
+    }
+}
@@ -0,0 +1,11 @@
+#version 150 core
+
+out vec2 v_Ndc;
+
+void main() {
+    v_Ndc = gl_VertexID == 0 ? vec2(-1.0, -1.0) :
+            gl_VertexID == 1 ? vec2( 1.0, -1.0) :
+            gl_VertexID == 2 ? vec2(-1.0,  1.0) :
+                               vec2( 1.0,  1.0) ;
+    gl_Position = vec4(v_Ndc, 0.0, 1.0);
+}
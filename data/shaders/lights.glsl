@@ -1,5 +1,3 @@
-#define MAX_LIGHTS  4U
-
 struct Light {
     mat4 projection;
     vec4 pos;
@@ -8,9 +6,54 @@ struct Light {
     vec4 color;
     vec4 color_back;
     vec4 intensity;
+    // x: shadow map index, y: shadow type, z: filter kernel size
     ivec4 shadow_params;
+    // x: bias, y: slope bias, z: light size
+    vec4 shadow_filter;
 };
 
-layout(std140) uniform b_Lights {
-    Light u_Lights[MAX_LIGHTS];
-};
+uniform samplerBuffer b_Lights;
+
+Light fetch_light(uint i) {
+    //Note: has to match `render::VECS_PER_LIGHT`
+    int base = 12 * int(i);
+    Light light;
+    light.projection = mat4(
+        texelFetch(b_Lights, base + 0),
+        texelFetch(b_Lights, base + 1),
+        texelFetch(b_Lights, base + 2),
+        texelFetch(b_Lights, base + 3)
+    );
+    light.pos = texelFetch(b_Lights, base + 4);
+    light.dir = texelFetch(b_Lights, base + 5);
+    light.focus = texelFetch(b_Lights, base + 6);
+    light.color = texelFetch(b_Lights, base + 7);
+    light.color_back = texelFetch(b_Lights, base + 8);
+    light.intensity = texelFetch(b_Lights, base + 9);
+    light.shadow_params = ivec4(texelFetch(b_Lights, base + 10));
+    light.shadow_filter = texelFetch(b_Lights, base + 11);
+    return light;
+}
+
+// Matches `render::CUBE_SHADOW_INDEX`.
+#define CUBE_SHADOW_INDEX   2
+
+// Attenuation of spot lights towards the edge of the cone, 1.0 for other lights.
+// `focus.xy` holds the cosines of the outer and inner cone angles.
+float spot_factor(Light light, vec3 dir_to_light) {
+    if (light.focus.x <= -1.0) {
+        return 1.0;
+    }
+    float cos_angle = dot(normalize(dir_to_light), light.dir.xyz);
+    return smoothstep(light.focus.x, light.focus.y, cos_angle);
+}
+
+// Depth stored in a cube shadow map for the point at `dir` from the light,
+// given the `range` of its perspective projection.
+float cube_shadow_depth(vec3 dir, vec2 range) {
+    vec3 a = abs(dir);
+    float z = max(a.x, max(a.y, a.z));
+    float ndc = (range.y + range.x) / (range.y - range.x)
+        - 2.0 * range.y * range.x / ((range.y - range.x) * z);
+    return 0.5 * ndc + 0.5;
+}
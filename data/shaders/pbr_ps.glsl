@@ -1,6 +1,9 @@
 #version 150 core
 #include <lights>
 #include <globals>
+#include <shadows>
+#include <cascades>
+#include <ambient_occlusion>
 
 const int BASE_COLOR_MAP          = 1 << 0;
 const int NORMAL_MAP              = 1 << 1;
@@ -8,6 +11,7 @@ const int METALLIC_ROUGHNESS_MAP  = 1 << 2;
 const int EMISSIVE_MAP            = 1 << 3;
 const int OCCLUSION_MAP           = 1 << 4;
 const int DISPLACEMENT_BUFFER     = 1 << 5;
+const int ENVIRONMENT_MAP         = 1 << 6;
 
 uniform sampler2D u_BaseColorSampler;
 uniform sampler2D u_NormalSampler;
@@ -15,6 +19,14 @@ uniform sampler2D u_EmissiveSampler;
 uniform sampler2D u_MetallicRoughnessSampler;
 uniform sampler2D u_OcclusionSampler;
 
+uniform sampler2DShadow t_Shadow0;
+uniform sampler2DShadow t_Shadow1;
+uniform samplerCubeShadow t_ShadowCube0;
+
+uniform samplerCube u_IrradianceSampler;
+uniform samplerCube u_SpecularSampler;
+uniform sampler2D u_BrdfLutSampler;
+
 layout(std140) uniform b_PbrParams {
     vec4 u_BaseColorFactor;
     vec3 u_Camera;
@@ -23,6 +35,8 @@ layout(std140) uniform b_PbrParams {
     float u_NormalScale;
     float u_OcclusionStrength;
     int u_PbrFlags;
+    // x: index of the roughest specular mip level
+    vec4 u_Environment;
 };
 
 in vec3 v_Position;
@@ -132,9 +146,32 @@ void main() {
     float alpha_roughness = perceptual_roughness * perceptual_roughness;
 
     vec3 color = vec3(0.0);
-    for (uint i = 0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
-        Light light = u_Lights[i];
-        vec3 l = normalize(light.dir.xyz);
+    for (uint i = 0U; i < u_NumLights; ++i) {
+        Light light = fetch_light(i);
+        vec3 dir = light.pos.xyz - light.pos.w * v_Position;
+        vec3 l = normalize(dir);
+
+        vec4 lit_space = light.projection * vec4(v_Position, 1.0);
+        int shadow_type = light.shadow_params[1];
+        int kernel = light.shadow_params[2];
+        float bias = shadow_bias(light.shadow_filter, dot(n, l));
+        float shadow = 1.0;
+        if (light.shadow_params[0] == 0) {
+            shadow = shadow_2d(t_Shadow0, t_ShadowDepth0, lit_space, shadow_type, kernel, bias, light.shadow_filter.z);
+        }
+        if (light.shadow_params[0] == 1) {
+            shadow = shadow_2d(t_Shadow1, t_ShadowDepth1, lit_space, shadow_type, kernel, bias, light.shadow_filter.z);
+        }
+        if (light.shadow_params[0] == CUBE_SHADOW_INDEX) {
+            shadow = shadow_cube(t_ShadowCube0, lit_space.xyz, light.focus.zw, shadow_type, kernel, bias);
+        }
+        if (light.shadow_params[0] == CASCADE_SHADOW_INDEX) {
+            shadow = cascaded_shadow(v_Position, shadow_type, kernel, bias);
+        }
+        shadow *= spot_factor(light, dir);
+        if (shadow == 0.0) {
+            continue;
+        }
         vec3 h = normalize(l + v);
         vec3 reflection = -normalize(reflect(v, n));
 
@@ -161,7 +198,17 @@ void main() {
         float d = ggx(pbr_inputs);
         vec3 diffuse_contrib = (1.0 - f) * lambertian_diffuse(pbr_inputs);
         vec3 spec_contrib = f * g * d / (4.0 * ndotl * ndotv);
-        color += ndotl * light.intensity.y * light.color.rgb * (diffuse_contrib + spec_contrib);
+        color += shadow * ndotl * light.intensity.y * light.color.rgb * (diffuse_contrib + spec_contrib);
+    }
+
+    if (available(ENVIRONMENT_MAP)) {
+        float ndotv = clamp(abs(dot(n, v)), 0.001, 1.0);
+        vec3 reflection = normalize(reflect(-v, n));
+        vec2 brdf = texture(u_BrdfLutSampler, vec2(ndotv, perceptual_roughness)).rg;
+        vec3 diffuse_light = texture(u_IrradianceSampler, n).rgb;
+        vec3 specular_light = textureLod(u_SpecularSampler, reflection, perceptual_roughness * u_Environment.x).rgb;
+        vec3 environment = diffuse_light * diffuse_color + specular_light * (specular_color * brdf.x + brdf.y);
+        color += ambient_occlusion() * environment;
     }
 
     if (available(OCCLUSION_MAP)) {
@@ -18,6 +18,10 @@ out mat3 v_Tbn;
 in vec4 i_World0;
 in vec4 i_World1;
 in vec4 i_World2;
+// Rows of the inverse transpose of the world matrix, for the normals.
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
 
 // Toggles displacement contributions to `a_Position/a_Normal/a_Tangent`.
 struct DisplacementContribution {
@@ -40,6 +44,7 @@ layout(std140) uniform b_PbrParams {
     float u_NormalScale;
     float u_OcclusionStrength;
     int u_PbrFlags;
+    vec4 u_Environment;
 };
 
 uniform samplerBuffer b_JointTransforms;
@@ -85,12 +90,13 @@ void main() {
     }
 
     mat4 mx_world = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    mat3 mx_normal = transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz));
     mat4 mx_mvp = u_ViewProj * mx_world;
     mat4 mx_skin = compute_skin_transform();
 
     vec4 world_position = mx_world * vec4(local_position, a_Position.w);
-    vec3 world_normal = mat3(mx_world) * normalize(local_normal);
-    vec3 world_tangent = mat3(mx_world) * normalize(local_tangent);
+    vec3 world_normal = normalize(mx_normal * normalize(local_normal));
+    vec3 world_tangent = normalize(mat3(mx_world) * normalize(local_tangent));
     vec3 world_bitangent = cross(world_normal, world_tangent) * a_Tangent.w;
 
     v_Tbn = mat3(world_tangent, world_bitangent, world_normal);
@@ -0,0 +1,13 @@
+// Inputs of the full screen passes, see `render::PostEffect`.
+
+layout(std140) uniform b_PostParams {
+    vec4 u_Params;
+    // Size of a pixel in texture coordinates.
+    vec2 u_TexelSize;
+    int u_Mode;
+};
+
+// Result of the previous pass.
+uniform sampler2D t_Input;
+
+in vec2 v_TexCoord;
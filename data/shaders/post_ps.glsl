@@ -0,0 +1,120 @@
+#version 150 core
+#include <post>
+
+// Matches the `POST_MODE_*` constants of the renderer.
+#define MODE_BLOOM_THRESHOLD    0
+#define MODE_BLUR               1
+#define MODE_BLOOM_COMBINE      2
+#define MODE_FXAA               3
+#define MODE_VIGNETTE           4
+#define MODE_COLOR_GRADING      5
+
+// Blurred bloom or color grading lookup table.
+uniform sampler2D t_Extra;
+
+out vec4 Target0;
+
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+// Keeps the colors brighter than `u_Params.x` once scaled by the exposure in `u_Params.y`.
+vec4 bloom_threshold() {
+    vec4 color = texture(t_Input, v_TexCoord) * u_Params.y;
+    float brightness = dot(color.rgb, LUMA);
+    float weight = smoothstep(u_Params.x, u_Params.x + 0.1, brightness);
+    return vec4(color.rgb * weight, 1.0);
+}
+
+// Gaussian blur along `u_Params.xy`, in pixels.
+vec4 blur() {
+    const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    vec2 offset = 0.25 * u_Params.xy * u_TexelSize;
+    vec3 sum = texture(t_Input, v_TexCoord).rgb * WEIGHTS[0];
+    for (int i = 1; i < 5; ++i) {
+        sum += texture(t_Input, v_TexCoord + float(i) * offset).rgb * WEIGHTS[i];
+        sum += texture(t_Input, v_TexCoord - float(i) * offset).rgb * WEIGHTS[i];
+    }
+    return vec4(sum, 1.0);
+}
+
+// Adds the blurred bloom scaled by `u_Params.x`.
+vec4 bloom_combine() {
+    vec4 color = texture(t_Input, v_TexCoord);
+    vec3 bloom = texture(t_Extra, v_TexCoord).rgb;
+    return vec4(color.rgb + u_Params.x * bloom, color.a);
+}
+
+// FXAA by Timothy Lottes, the low quality variant.
+vec4 fxaa() {
+    const float SPAN_MAX = 8.0;
+    const float REDUCE_MUL = 1.0 / 8.0;
+    const float REDUCE_MIN = 1.0 / 128.0;
+
+    vec4 color = texture(t_Input, v_TexCoord);
+    float luma_nw = dot(texture(t_Input, v_TexCoord + vec2(-1.0, -1.0) * u_TexelSize).rgb, LUMA);
+    float luma_ne = dot(texture(t_Input, v_TexCoord + vec2(1.0, -1.0) * u_TexelSize).rgb, LUMA);
+    float luma_sw = dot(texture(t_Input, v_TexCoord + vec2(-1.0, 1.0) * u_TexelSize).rgb, LUMA);
+    float luma_se = dot(texture(t_Input, v_TexCoord + vec2(1.0, 1.0) * u_TexelSize).rgb, LUMA);
+    float luma_m = dot(color.rgb, LUMA);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 dir = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * u_TexelSize;
+
+    vec3 a = 0.5 * (
+        texture(t_Input, v_TexCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(t_Input, v_TexCoord + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 b = a * 0.5 + 0.25 * (
+        texture(t_Input, v_TexCoord - dir * 0.5).rgb +
+        texture(t_Input, v_TexCoord + dir * 0.5).rgb
+    );
+    float luma_b = dot(b, LUMA);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        return vec4(a, color.a);
+    }
+    return vec4(b, color.a);
+}
+
+// Darkens the borders, `u_Params.x` is the offset and `u_Params.y` the darkness.
+vec4 vignette() {
+    vec4 color = texture(t_Input, v_TexCoord);
+    vec2 uv = (v_TexCoord - vec2(0.5)) * u_Params.x;
+    return vec4(mix(color.rgb, vec3(1.0 - u_Params.y), dot(uv, uv)), color.a);
+}
+
+// Looks the color up in a strip of `N` slices of `N` by `N` texels.
+vec4 color_grading() {
+    vec4 color = texture(t_Input, v_TexCoord);
+    vec3 c = clamp(color.rgb, 0.0, 1.0);
+    float size = float(textureSize(t_Extra, 0).y);
+    float slice = c.b * (size - 1.0);
+    float slice0 = floor(slice);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+    // Sample texel centers, so neighbouring slices don't bleed into each other.
+    vec2 uv = (c.rg * (size - 1.0) + 0.5) / vec2(size * size, size);
+    vec3 a = texture(t_Extra, uv + vec2(slice0 / size, 0.0)).rgb;
+    vec3 b = texture(t_Extra, uv + vec2(slice1 / size, 0.0)).rgb;
+    return vec4(mix(a, b, slice - slice0), color.a);
+}
+
+void main() {
+    if (u_Mode == MODE_BLOOM_THRESHOLD) {
+        Target0 = bloom_threshold();
+    } else if (u_Mode == MODE_BLUR) {
+        Target0 = blur();
+    } else if (u_Mode == MODE_BLOOM_COMBINE) {
+        Target0 = bloom_combine();
+    } else if (u_Mode == MODE_FXAA) {
+        Target0 = fxaa();
+    } else if (u_Mode == MODE_VIGNETTE) {
+        Target0 = vignette();
+    } else {
+        Target0 = color_grading();
+    }
+}
@@ -0,0 +1,116 @@
+// Filtering of the shadow maps, see `render::ShadowType` and `light::ShadowFilter`.
+// Requires the `lights` include.
+
+// Matches `render::SHADOW_TYPE_*`.
+#define SHADOW_BASIC    1
+#define SHADOW_PCF      2
+#define SHADOW_PCSS     3
+#define SHADOW_VSM      4
+
+// Lower bound of the visibility from variance shadow maps, cutting off light bleeding.
+#define VSM_MIN_VISIBILITY  0.2
+
+// Depth of the 2D shadow maps, or their blurred moments with `SHADOW_VSM`.
+uniform sampler2D t_ShadowDepth0;
+uniform sampler2D t_ShadowDepth1;
+
+// Depth bias of a surface at `ndotl` to the light, from the bias and slope bias of `params`.
+float shadow_bias(vec4 params, float ndotl) {
+    float cos_theta = clamp(ndotl, 0.05, 1.0);
+    float tan_theta = sqrt(1.0 - cos_theta * cos_theta) / cos_theta;
+    return params.x + params.y * min(tan_theta, 10.0);
+}
+
+// Averages 4x4 comparisons spread over `radius` texels around `coord`.
+float pcf_2d(sampler2DShadow map, vec3 coord, float radius) {
+    vec2 spacing = radius / (1.5 * vec2(textureSize(map, 0)));
+    float sum = 0.0;
+    for (int y = 0; y < 4; ++y) {
+        for (int x = 0; x < 4; ++x) {
+            vec2 offset = (vec2(x, y) - 1.5) * spacing;
+            sum += texture(map, vec3(coord.xy + offset, coord.z));
+        }
+    }
+    return sum / 16.0;
+}
+
+// PCF over the penumbra estimated from the average depth of the blockers,
+// searched for over the widest penumbra of `kernel` texels.
+float pcss_2d(sampler2DShadow map, sampler2D depth_map, vec3 coord, int kernel, float light_size) {
+    vec2 size = vec2(textureSize(map, 0));
+    vec2 spacing = 0.5 * float(kernel) / (1.5 * size);
+    float blockers = 0.0;
+    float count = 0.0;
+    for (int y = 0; y < 4; ++y) {
+        for (int x = 0; x < 4; ++x) {
+            float depth = texture(depth_map, coord.xy + (vec2(x, y) - 1.5) * spacing).r;
+            if (depth < coord.z) {
+                blockers += depth;
+                count += 1.0;
+            }
+        }
+    }
+    if (count == 0.0) {
+        return 1.0;
+    }
+    float blocker = blockers / count;
+    float penumbra = light_size * (coord.z - blocker) / max(blocker, 0.0001);
+    return pcf_2d(map, coord, clamp(penumbra * size.x, 0.5, 0.5 * float(kernel)));
+}
+
+// Chebyshev upper bound of the visibility from the blurred depth moments.
+float vsm_2d(sampler2D moments_map, vec3 coord) {
+    if (any(lessThan(coord.xy, vec2(0.0))) || any(greaterThan(coord.xy, vec2(1.0)))) {
+        return 1.0;
+    }
+    vec2 moments = texture(moments_map, coord.xy).rg;
+    if (coord.z <= moments.x) {
+        return 1.0;
+    }
+    float variance = max(moments.y - moments.x * moments.x, 0.00001);
+    float d = coord.z - moments.x;
+    float visibility = variance / (variance + d * d);
+    return clamp((visibility - VSM_MIN_VISIBILITY) / (1.0 - VSM_MIN_VISIBILITY), 0.0, 1.0);
+}
+
+// Shadow factor from a 2D shadow map of the point at `lit_space` in the light clip space.
+float shadow_2d(
+    sampler2DShadow map,
+    sampler2D depth_map,
+    vec4 lit_space,
+    int type,
+    int kernel,
+    float bias,
+    float light_size
+) {
+    vec3 coord = 0.5 * lit_space.xyz / lit_space.w + 0.5;
+    coord.z -= bias;
+    if (type == SHADOW_PCF) {
+        return pcf_2d(map, coord, 0.5 * float(kernel));
+    }
+    if (type == SHADOW_PCSS) {
+        return pcss_2d(map, depth_map, coord, kernel, light_size);
+    }
+    if (type == SHADOW_VSM) {
+        return vsm_2d(depth_map, coord);
+    }
+    return texture(map, coord);
+}
+
+// Shadow factor from a cube shadow map of the point at `dir` from the light,
+// filtering over `kernel` texels unless `type` is `SHADOW_BASIC`.
+float shadow_cube(samplerCubeShadow map, vec3 dir, vec2 range, int type, int kernel, float bias) {
+    float depth = cube_shadow_depth(dir, range) - bias;
+    if (type == SHADOW_BASIC) {
+        return texture(map, vec4(dir, depth));
+    }
+    // a texel spans `2 * major / size` across the face of the major axis
+    vec3 a = abs(dir);
+    float radius = float(kernel) * max(a.x, max(a.y, a.z)) / float(textureSize(map, 0).x);
+    float sum = 0.0;
+    for (int i = 0; i < 8; ++i) {
+        vec3 offset = 2.0 * vec3(i & 1, (i >> 1) & 1, (i >> 2) & 1) - 1.0;
+        sum += texture(map, vec4(dir + radius * offset, depth));
+    }
+    return sum / 8.0;
+}
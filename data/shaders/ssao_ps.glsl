@@ -0,0 +1,99 @@
+#version 150 core
+
+// Matches the `SSAO_MODE_*` constants of the renderer.
+#define MODE_OCCLUSION  0
+#define MODE_BLUR       1
+
+#define KERNEL_SIZE     16
+
+layout(std140) uniform b_SsaoParams {
+    mat4 u_Proj;
+    mat4 u_InverseProj;
+    // x: radius, y: power, z: mode
+    vec4 u_Params;
+};
+
+// View space normals and window depth for the occlusion,
+// the occlusion itself for the blur.
+uniform sampler2D t_Input;
+
+out vec4 Target0;
+
+const float PI = 3.141592653589793;
+const float GOLDEN_ANGLE = 2.399963229728653;
+
+vec3 view_position(vec2 coord, float depth) {
+    vec4 ndc = vec4(2.0 * vec3(coord, depth) - 1.0, 1.0);
+    vec4 position = u_InverseProj * ndc;
+    return position.xyz / position.w;
+}
+
+float noise(vec2 pixel) {
+    return fract(sin(dot(pixel, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+// Fraction of a hemisphere of samples around the point that isn't behind the scene.
+float occlusion() {
+    vec2 size = vec2(textureSize(t_Input, 0));
+    vec4 depth_normal = texelFetch(t_Input, ivec2(gl_FragCoord.xy), 0);
+    if (depth_normal.w >= 1.0) {
+        return 1.0;
+    }
+    vec3 position = view_position(gl_FragCoord.xy / size, depth_normal.w);
+    vec3 normal = normalize(depth_normal.xyz);
+
+    // Rotate the kernel randomly per pixel, the blur hides the resulting noise.
+    float angle = 2.0 * PI * noise(gl_FragCoord.xy);
+    vec3 random = vec3(cos(angle), sin(angle), 0.0);
+    vec3 tangent = random - normal * dot(random, normal);
+    if (dot(tangent, tangent) < 0.0001) {
+        tangent = vec3(0.0, 0.0, 1.0) - normal * normal.z;
+    }
+    tangent = normalize(tangent);
+    mat3 tbn = mat3(tangent, cross(normal, tangent), normal);
+
+    float radius = u_Params.x;
+    float occluded = 0.0;
+    for (int i = 0; i < KERNEL_SIZE; ++i) {
+        // Spiral over the hemisphere, with the samples gathering near the point.
+        float t = (float(i) + 0.5) / float(KERNEL_SIZE);
+        float z = 1.0 - t;
+        float r = sqrt(1.0 - z * z);
+        vec3 dir = vec3(r * cos(float(i) * GOLDEN_ANGLE), r * sin(float(i) * GOLDEN_ANGLE), z);
+        vec3 probe = position + tbn * dir * radius * mix(0.1, 1.0, t * t);
+
+        vec4 clip = u_Proj * vec4(probe, 1.0);
+        vec2 coord = 0.5 * clip.xy / clip.w + 0.5;
+        if (any(lessThan(coord, vec2(0.0))) || any(greaterThan(coord, vec2(1.0)))) {
+            continue;
+        }
+        float depth = texelFetch(t_Input, ivec2(coord * size), 0).w;
+        vec3 scene = view_position(coord, depth);
+        // Ignore occluders far out of the radius, like the background behind an edge.
+        float range = smoothstep(0.0, 1.0, radius / max(abs(position.z - scene.z), 0.0001));
+        if (scene.z >= probe.z + 0.025 * radius) {
+            occluded += range;
+        }
+    }
+    return pow(1.0 - occluded / float(KERNEL_SIZE), u_Params.y);
+}
+
+// Averages the occlusion over 4x4 pixels, hiding the noise of the kernel rotation.
+float blur() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    ivec2 size = textureSize(t_Input, 0);
+    float sum = 0.0;
+    for (int y = -2; y < 2; ++y) {
+        for (int x = -2; x < 2; ++x) {
+            ivec2 neighbor = clamp(pixel + ivec2(x, y), ivec2(0), size - 1);
+            sum += texelFetch(t_Input, neighbor, 0).r;
+        }
+    }
+    return sum / 16.0;
+}
+
+void main() {
+    int mode = int(u_Params.z);
+    float value = mode == MODE_OCCLUSION ? occlusion() : blur();
+    Target0 = vec4(value, value, value, 1.0);
+}
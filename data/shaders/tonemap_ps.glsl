@@ -0,0 +1,51 @@
+#version 150 core
+
+// Matches `render::ToneMapping`.
+#define TONE_MAPPING_NONE       0
+#define TONE_MAPPING_REINHARD   1
+#define TONE_MAPPING_ACES       2
+#define TONE_MAPPING_FILMIC     3
+
+uniform sampler2D t_Input;
+
+layout(std140) uniform b_ToneMapParams {
+    float u_Exposure;
+    int u_Operator;
+};
+
+in vec2 v_TexCoord;
+
+out vec4 Target0;
+
+// Fit of the ACES curve by Krzysztof Narkowicz.
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 hable(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 filmic(vec3 x) {
+    const float WHITE = 11.2;
+    return hable(2.0 * x) / hable(vec3(WHITE));
+}
+
+void main() {
+    vec4 hdr = texture(t_Input, v_TexCoord);
+    vec3 color = u_Exposure * hdr.rgb;
+    if (u_Operator == TONE_MAPPING_REINHARD) {
+        color = color / (1.0 + color);
+    } else if (u_Operator == TONE_MAPPING_ACES) {
+        color = aces(color);
+    } else if (u_Operator == TONE_MAPPING_FILMIC) {
+        color = filmic(color);
+    }
+    Target0 = vec4(color, hdr.a);
+}
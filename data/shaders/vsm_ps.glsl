@@ -0,0 +1,30 @@
+#version 150 core
+
+layout(std140) uniform b_VsmParams {
+    // xy: step between the taps in texels, z: 1.0 when the input is the shadow map itself
+    vec4 u_Params;
+};
+
+// Shadow map depth, or its moments from the previous pass.
+uniform sampler2D t_Input;
+
+out vec4 Target0;
+
+vec2 moments(vec2 coord) {
+    vec4 value = texture(t_Input, coord);
+    return u_Params.z > 0.5 ? vec2(value.r, value.r * value.r) : value.rg;
+}
+
+// Gaussian blur of the depth moments along `u_Params.xy`.
+void main() {
+    const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+    vec2 texel = 1.0 / vec2(textureSize(t_Input, 0));
+    vec2 coord = gl_FragCoord.xy * texel;
+    vec2 offset = u_Params.xy * texel;
+    vec2 sum = moments(coord) * WEIGHTS[0];
+    for (int i = 1; i < 5; ++i) {
+        sum += moments(coord + float(i) * offset) * WEIGHTS[i];
+        sum += moments(coord - float(i) * offset) * WEIGHTS[i];
+    }
+    Target0 = vec4(sum, 0.0, 1.0);
+}
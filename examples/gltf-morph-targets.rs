@@ -14,10 +14,10 @@ fn main() {
 
     // Load the contents of the glTF files. Scenes loaded from the file are returned as
     // `Template` objects, which can be used to instantiate the actual objects for rendering.
-    let templates = window.factory.load_gltf(&path);
+    let template = window.factory.load_gltf(&path);
 
     // Instantiate the contents of the template, and then add it to the scene.
-    let (instance, animations) = window.factory.instantiate_template(&templates[0]);
+    let (instance, animations) = window.factory.instantiate_template(&template);
     window.scene.add(&instance);
 
     // Begin playing all the animations instantiated from the template.
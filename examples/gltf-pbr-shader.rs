@@ -18,10 +18,10 @@ fn main() {
 
     // Load the contents of the glTF files. Scenes loaded from the file are returned as
     // `Template` objects, which can be used to instantiate the actual objects for rendering.
-    let templates = win.factory.load_gltf(&path);
+    let template = win.factory.load_gltf(&path);
 
     // Instantiate the contents of the template, and then add it to the scene.
-    let (instance, _) = win.factory.instantiate_template(&templates[0]);
+    let (instance, _) = win.factory.instantiate_template(&template);
     win.scene.add(&instance);
 
     // Attempt to find a camera in the instantiated template to use as the perspective for
@@ -48,7 +48,14 @@ fn main() {
         right: "test_data/skybox/posx.jpg",
     };
     let skybox = win.factory.load_cubemap(&skybox_path);
+    // Light the model with its surroundings too.
+    win.scene.environment = Some(win.factory.environment_map(&skybox));
     win.scene.background = three::Background::Skybox(skybox);
+    win.renderer.tone_mapping = three::render::ToneMapping::Aces;
+    win.renderer.ambient_occlusion = three::render::AmbientOcclusion::Ssao {
+        radius: 0.5,
+        power: 1.5,
+    };
 
     // Determine the current position of the camera so that we can use it to initialize the
     // camera controller.
@@ -4,13 +4,25 @@ use three::Object;
 
 fn main() {
     let mut win = three::Window::new("Three-rs lights example");
+    win.renderer.shadow = three::render::ShadowType::Pcss;
     let cam = win.factory.perspective_camera(45.0, 1.0 .. 50.0);
     cam.look_at([-4.0, 15.0, 10.0], [0.0, 0.0, 2.0], None);
 
     let hemisphere_light = win.factory.hemisphere_light(0xffffff, 0x8080ff, 0.5);
     let ambient_light = win.factory.ambient_light(0xffffffff, 0.5);
-    let point_light = win.factory.point_light(0xffffff, 0.9);
-    point_light.set_position([15.0, 35.0, 35.0]);
+    let mut point_light = win.factory.point_light(0xffffff, 0.9);
+    point_light.set_position([0.0, 10.0, 8.0]);
+    let cube_shadow_map = win.factory.cube_shadow_map(512);
+    point_light.set_shadow(cube_shadow_map, 1.0 .. 100.0);
+
+    let mut spot_light = win.factory.spot_light(0xffffff, 0.9, 20.0, 0.3);
+    spot_light.look_at([15.0, 35.0, 35.0], [0.0, 0.0, 2.0], None);
+    let mut spot_shadow_map = win.factory.shadow_map(1024, 1024);
+    spot_shadow_map.set_filter(three::light::ShadowFilter {
+        kernel_size: 9,
+        ..Default::default()
+    });
+    spot_light.set_shadow(spot_shadow_map, 1.0 .. 200.0);
 
     let mut dir_light = win.factory.directional_light(0xffffff, 0.9);
     dir_light.look_at([15.0, 35.0, 35.0], [0.0, 0.0, 2.0], None);
@@ -19,11 +31,12 @@ fn main() {
         .debug_shadow_quad(&shadow_map, 1, [10, 10], [256, 256]);
     dir_light.set_shadow(shadow_map, 40.0, 1.0 .. 200.0);
 
-    let lights: [&three::object::Base; 4] = [
+    let lights: [&three::object::Base; 5] = [
         hemisphere_light.as_ref(),
         ambient_light.as_ref(),
         point_light.as_ref(),
         dir_light.as_ref(),
+        spot_light.as_ref(),
     ];
     for l in &lights {
         l.set_visible(false);
@@ -35,6 +48,7 @@ fn main() {
         let material = three::material::Phong {
             color: 0xffA0A0,
             glossiness: 80.0,
+            .. Default::default()
         };
         win.factory.mesh(geometry, material)
     };
@@ -46,6 +60,7 @@ fn main() {
         let material = three::material::Lambert {
             color: 0xA0ffA0,
             flat: false,
+            .. Default::default()
         };
         win.factory.mesh(geometry, material)
     };
@@ -17,18 +17,22 @@ fn main() {
         three::material::Basic {
             color: 0xFFFFFF,
             map: None,
+            .. Default::default()
         }.into(),
         three::material::Lambert {
             color: 0xFFFFFF,
             flat: true,
+            .. Default::default()
         }.into(),
         three::material::Lambert {
             color: 0xFFFFFF,
             flat: false,
+            .. Default::default()
         }.into(),
         three::material::Phong {
             color: 0xFFFFFF,
             glossiness: 80.0,
+            .. Default::default()
         }.into(),
         three::material::Pbr {
             base_color_factor: 0xFFFFFF,
@@ -43,6 +47,7 @@ fn main() {
             emissive_map: None,
             metallic_roughness_map: None,
             occlusion_map: None,
+            transparent: false,
         }.into(),
     ];
     let count = materials.len();
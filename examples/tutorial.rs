@@ -12,6 +12,7 @@ fn main() {
     let material = three::material::Basic {
         color: 0xFFFF00,
         map: None,
+        .. Default::default()
     };
     let mesh = window.factory.mesh(geometry, material);
     window.scene.add(&mesh);
@@ -22,6 +22,18 @@
 //! The user is expected to create actions from a mixer with the [`Mixer::action`]
 //! function and update actions with the [`Mixer::update`] function.
 //!
+//! ### Blending
+//!
+//! When several actions animate the same target, the mixer blends their values
+//! according to the action weights, see [`set_weight`], and applies the result
+//! once per update. Values animated by a total weight above 1 are divided by it.
+//! Below 1, they are blended with the rest transform the target had before being
+//! animated, as a fading action leaves the target in its rest pose once faded out.
+//! Paused actions keep their current values without contributing to the blend.
+//!
+//! [`fade_in`], [`fade_out`] and [`crossfade_to`] ramp the weights over time,
+//! for example to smoothly switch from a walk cycle to a run cycle.
+//!
 //! ### Clip
 //!
 //! An animation [`Clip`] defines the keyframes and target of an animation.
@@ -94,9 +106,13 @@
 //!
 //! See the `gltf-animation` example for the full code.
 //!
+//! [`crossfade_to`]: struct.Action.html#method.crossfade_to
 //! [`disable`]: struct.Action.html#method.disable
+//! [`fade_in`]: struct.Action.html#method.fade_in
+//! [`fade_out`]: struct.Action.html#method.fade_out
 //! [`play`]: struct.Action.html#method.play
 //! [`pause`]: struct.Action.html#method.pause
+//! [`set_weight`]: struct.Action.html#method.set_weight
 //!
 //! [`Action`]: struct.Action.html
 //! [`Clip`]: struct.Clip.html
@@ -106,10 +122,14 @@
 
 use cgmath;
 use froggy;
+use hub::Operation as HubOperation;
 use mint;
-use object::{Base, Object};
+use node::NodePointer;
+use object::Base;
 
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::ops;
 use std::sync::mpsc;
 
 
@@ -147,7 +167,7 @@ pub enum LoopMode {
 
     /// Play the clip alternatively in forward and reverse order.
     PingPong {
-        /// The maximum number of repetitions.
+        /// The maximum number of repetitions, each change of direction counting as one.
         ///
         /// When set to `None`, the loop will repeat indefinately.
         limit: Option<u32>,
@@ -176,14 +196,11 @@ pub enum Binding {
 
     /// Targets the scale property of an [`Object`].
     ///
-    /// The corresponding keyframe values must be [`Scalar`].
-    ///
-    /// ## Note
-    ///
-    /// Only uniform scaling is supported, hence the glTF importer takes the
-    /// Y axis as the scaling direction, ignoring any scaling in the X and Z axes.
+    /// The corresponding keyframe values must be [`Vector3`] for a scale along each axis,
+    /// or [`Scalar`] for a uniform scale.
     ///
     /// [`Object`]: ../object/trait.Object.html
+    /// [`Vector3`]: enum.Values.html#variant.Vector3
     /// [`Scalar`]: enum.Values.html#variant.Scalar
     Scale,
 
@@ -233,6 +250,9 @@ enum Operation {
     Pause,
     Play,
     SetLoopMode(LoopMode),
+    SetWeight(f32),
+    FadeIn(f32),
+    FadeOut(f32),
 }
 
 /// Message type sent from `Action` to `Mixer`.
@@ -290,15 +310,50 @@ struct ActionData {
     /// the loop mode.
     pub local_time: f32,
 
+    /// Specifies whether a ping-pong loop is playing the clip in reverse order.
+    pub reversed: bool,
+
     /// Time scaling factor.
     pub local_time_scale: f32,
+
+    /// Influence of this action when blended with the other actions of the mixer.
+    pub weight: f32,
+
+    /// Specifies the weight ramp if the action is fading in or out.
+    pub fade: Option<Fade>,
     // Unimplemented properties
     // ------------------------
-    // * weight
     // * zero_slope_at_end
     // * zero_slope_at_start
 }
 
+/// Ramp of the weight of a fading action.
+struct Fade {
+    /// Weight ratio at the start of the fade.
+    start: f32,
+
+    /// Weight ratio at the end of the fade, `0.0` when fading out.
+    end: f32,
+
+    /// Duration of the fade in seconds.
+    duration: f32,
+
+    /// Time elapsed since the start of the fade in seconds.
+    elapsed: f32,
+}
+
+/// Values of a target accumulated from the actions of a mixer.
+///
+/// Each value is stored together with the total weight of the actions
+/// contributing to it.
+#[derive(Default)]
+struct Blend {
+    position: Option<(cgmath::Vector3<f32>, f32)>,
+    orientation: Option<(cgmath::Quaternion<f32>, f32)>,
+    scale: Option<(cgmath::Vector3<f32>, f32)>,
+    weights: Option<(Vec<f32>, f32)>,
+}
+
 /// A reusable set of keyframe tracks which represent an animation.
 #[derive(Clone, Debug)]
 pub struct Clip {
@@ -330,6 +385,8 @@ pub struct Track {
 /// Use this to update animation actions.
 pub struct Mixer {
     actions: froggy::Storage<ActionData>,
+    /// Values accumulated during an update, kept to reuse the allocation.
+    blends: HashMap<NodePointer, (Target, Blend)>,
     rx: mpsc::Receiver<Message>,
     tx: mpsc::Sender<Message>,
 }
@@ -371,6 +428,44 @@ impl Action {
     ) -> &mut Self {
         self.send(Operation::SetLoopMode(loop_mode))
     }
+
+    /// Sets the influence of the action when blended with the other actions
+    /// animating the same targets. Defaults to `1.0`.
+    pub fn set_weight(
+        &mut self,
+        weight: f32,
+    ) -> &mut Self {
+        self.send(Operation::SetWeight(weight))
+    }
+
+    /// Enables the animation action, ramping its weight up from zero
+    /// over `duration` seconds.
+    pub fn fade_in(
+        &mut self,
+        duration: f32,
+    ) -> &mut Self {
+        self.send(Operation::FadeIn(duration))
+    }
+
+    /// Ramps the weight of the animation action down to zero over `duration`
+    /// seconds, then disables it.
+    pub fn fade_out(
+        &mut self,
+        duration: f32,
+    ) -> &mut Self {
+        self.send(Operation::FadeOut(duration))
+    }
+
+    /// Fades the animation action out while `other` plays and fades in,
+    /// both over `duration` seconds.
+    pub fn crossfade_to(
+        &mut self,
+        other: &mut Action,
+        duration: f32,
+    ) -> &mut Self {
+        other.play().fade_in(duration);
+        self.fade_out(duration)
+    }
 }
 
 impl Mixer {
@@ -388,7 +483,29 @@ impl Mixer {
                     action.paused = false;
                     action.enabled = true;
                 }
-                Operation::SetLoopMode(loop_mode) => action.loop_mode = loop_mode,
+                Operation::SetLoopMode(loop_mode) => {
+                    let ping_pong = match loop_mode {
+                        LoopMode::PingPong { .. } => true,
+                        _ => false,
+                    };
+                    if action.reversed && !ping_pong {
+                        // Carry on forward from the current pose.
+                        action.local_time = action.clip.duration() - action.local_time;
+                        action.reversed = false;
+                    }
+                    action.loop_mode = loop_mode;
+                }
+                Operation::SetWeight(weight) => action.weight = weight,
+                Operation::FadeIn(duration) => {
+                    // Start from the current ratio when interrupting a fade out.
+                    let start = if action.fade.is_some() { action.fade_factor() } else { 0.0 };
+                    action.enabled = true;
+                    action.fade = Some(Fade::new(start, 1.0, duration));
+                }
+                Operation::FadeOut(duration) => {
+                    let start = action.fade_factor();
+                    action.fade = Some(Fade::new(start, 0.0, duration));
+                }
             }
         }
     }
@@ -398,7 +515,10 @@ impl Mixer {
         delta_time: f32,
     ) {
         for action in self.actions.iter_mut() {
-            action.update(delta_time);
+            action.update(delta_time, &mut self.blends);
+        }
+        for (_, (target, blend)) in self.blends.drain() {
+            blend.apply(&target);
         }
     }
 
@@ -406,7 +526,12 @@ impl Mixer {
     pub fn new() -> Self {
         let actions = froggy::Storage::new();
         let (tx, rx) = mpsc::channel();
-        Mixer { actions, rx, tx }
+        Mixer {
+            actions,
+            blends: HashMap::new(),
+            rx,
+            tx,
+        }
     }
 
     /// Spawns a new animation [`Action`] to be updated by this mixer.
@@ -440,36 +565,95 @@ impl ActionData {
             loop_mode: LoopMode::Repeat { limit: None },
             paused: false,
             local_time: 0.0,
+            reversed: false,
             local_time_scale: 1.0,
+            weight: 1.0,
+            fade: None,
+        }
+    }
+
+    /// Ratio applied to the weight by the current fade, in range `[0.0, 1.0]`.
+    fn fade_factor(&self) -> f32 {
+        match self.fade {
+            Some(ref fade) if fade.duration > 0.0 => {
+                let s = (fade.elapsed / fade.duration).min(1.0);
+                fade.start * (1.0 - s) + fade.end * s
+            }
+            Some(ref fade) => fade.end,
+            None => 1.0,
         }
     }
 
-    /// Updates a single animation action.
+    /// Advances the current fade, disabling the action once faded out.
+    fn update_fade(
+        &mut self,
+        delta_time: f32,
+    ) {
+        let end = match self.fade {
+            Some(ref mut fade) => {
+                fade.elapsed += delta_time;
+                if fade.elapsed < fade.duration {
+                    return;
+                }
+                fade.end
+            }
+            None => return,
+        };
+        self.fade = None;
+        if end == 0.0 {
+            self.enabled = false;
+        }
+    }
+
+    /// Updates a single animation action, adding the animated values to the
+    /// blend of their targets.
+    // Node pointers hash and compare by index, their mutex only tracks the references.
+    #[allow(clippy::mutable_key_type)]
     fn update(
         &mut self,
         delta_time: f32,
+        blends: &mut HashMap<NodePointer, (Target, Blend)>,
     ) {
         if self.paused || !self.enabled {
             return;
         }
 
+        self.update_fade(delta_time);
+        if !self.enabled {
+            return;
+        }
         self.local_time += delta_time * self.local_time_scale;
+        let weight = self.weight * self.fade_factor();
+
+        // Playing in reverse samples the clip at the mirrored time, so the
+        // tracks end when the time goes past their first frame.
+        let time = if self.reversed {
+            self.clip.duration() - self.local_time
+        } else {
+            self.local_time
+        };
         let mut finish_count = 0;
         for &(ref track, ref target) in self.clip.tracks.iter() {
-            let frame_index = match track.frame_at_time(self.local_time) {
-                FrameRef::Unstarted => continue,
-                FrameRef::Ended => {
+            let frame_index = match (track.frame_at_time(time), self.reversed) {
+                (FrameRef::Unstarted, false) | (FrameRef::Ended, true) => continue,
+                (FrameRef::Ended, false) | (FrameRef::Unstarted, true) => {
                     finish_count += 1;
                     continue;
                 }
-                FrameRef::InProgress(i) => i,
+                (FrameRef::InProgress(i), _) => i,
             };
+            if weight <= 0.0 {
+                continue;
+            }
             let frame_start_time = track.times[frame_index];
             let frame_end_time = track.times[frame_index + 1];
             let frame_delta_time = frame_end_time - frame_start_time;
             // Interpolation constant in range `[0.0, 1.0]` between `frame[i]`
             // and `frame[i + 1]`.
-            let s = (self.local_time - frame_start_time) / frame_delta_time;
+            let s = (time - frame_start_time) / frame_delta_time;
+            let &mut (_, ref mut blend) = blends
+                .entry(target.node.clone())
+                .or_insert_with(|| (target.clone(), Blend::default()));
 
             match (track.binding, &track.values) {
                 (Binding::Orientation, &Values::Euler(ref values)) => {
@@ -490,26 +674,33 @@ impl ActionData {
                         ))
                     };
                     let update = frame_start_value.slerp(frame_end_value, s);
-                    target.set_orientation(update);
+                    blend.add_orientation(update, weight);
                 }
                 (Binding::Orientation, &Values::Quaternion(ref values)) => {
                     let frame_start_value: cgmath::Quaternion<f32> = values[frame_index].into();
                     let frame_end_value: cgmath::Quaternion<f32> = values[frame_index + 1].into();
                     let update = frame_start_value.slerp(frame_end_value, s);
-                    target.set_orientation(update);
+                    blend.add_orientation(update, weight);
                 }
                 (Binding::Position, &Values::Vector3(ref values)) => {
-                    use cgmath::{EuclideanSpace, InnerSpace};
+                    use cgmath::InnerSpace;
                     let frame_start_value: cgmath::Vector3<f32> = values[frame_index].into();
                     let frame_end_value: cgmath::Vector3<f32> = values[frame_index + 1].into();
                     let update = frame_start_value.lerp(frame_end_value, s);
-                    target.set_position(cgmath::Point3::from_vec(update));
+                    Blend::add(&mut blend.position, update, weight);
                 }
                 (Binding::Scale, &Values::Scalar(ref values)) => {
                     let frame_start_value = values[frame_index];
                     let frame_end_value = values[frame_index + 1];
                     let update = frame_start_value * (1.0 - s) + frame_end_value * s;
-                    target.set_scale(update);
+                    Blend::add(&mut blend.scale, cgmath::Vector3::new(update, update, update), weight);
+                }
+                (Binding::Scale, &Values::Vector3(ref values)) => {
+                    use cgmath::InnerSpace;
+                    let frame_start_value: cgmath::Vector3<f32> = values[frame_index].into();
+                    let frame_end_value: cgmath::Vector3<f32> = values[frame_index + 1].into();
+                    let update = frame_start_value.lerp(frame_end_value, s);
+                    Blend::add(&mut blend.scale, update, weight);
                 }
                 (Binding::Weights, &Values::Scalar(ref values)) => {
                     // values are: first all scalars for shape[0], then all scalars for shape[1], etc
@@ -521,7 +712,7 @@ impl ActionData {
                             start_value * (1.0 - s) + end_value * s
                         })
                         .collect();
-                    target.set_weights(update);
+                    blend.add_weights(update, weight);
                 }
                 _ => panic!("Unsupported (binding, value) pair"),
             }
@@ -536,12 +727,117 @@ impl ActionData {
                     self.local_time = 0.0;
                     self.loop_mode = LoopMode::Repeat { limit: Some(n - 1) };
                 }
-                LoopMode::PingPong { .. } => {
-                    // TODO
-                    unimplemented!()
+                LoopMode::PingPong { limit: None } => {
+                    self.local_time = 0.0;
+                    self.reversed = !self.reversed;
+                }
+                LoopMode::PingPong { limit: Some(0) } => self.enabled = false,
+                LoopMode::PingPong { limit: Some(n) } => {
+                    self.local_time = 0.0;
+                    self.reversed = !self.reversed;
+                    self.loop_mode = LoopMode::PingPong { limit: Some(n - 1) };
+                }
+            }
+        }
+    }
+}
+
+impl Blend {
+    /// Adds `value` to a weighted sum.
+    fn add<T>(
+        sum: &mut Option<(T, f32)>,
+        value: T,
+        weight: f32,
+    ) where
+        T: ops::Add<Output = T> + ops::Mul<f32, Output = T>,
+    {
+        *sum = Some(match sum.take() {
+            Some((total, total_weight)) => (total + value * weight, total_weight + weight),
+            None => (value * weight, weight),
+        });
+    }
+
+    fn add_orientation(
+        &mut self,
+        value: cgmath::Quaternion<f32>,
+        weight: f32,
+    ) {
+        use cgmath::InnerSpace;
+        self.orientation = Some(match self.orientation.take() {
+            Some((blended, total_weight)) => {
+                // Take the shortest path between the orientations.
+                let value = if blended.dot(value) < 0.0 { -value } else { value };
+                let total_weight = total_weight + weight;
+                (blended.slerp(value, weight / total_weight), total_weight)
+            }
+            None => (value, weight),
+        });
+    }
+
+    fn add_weights(
+        &mut self,
+        value: Vec<f32>,
+        weight: f32,
+    ) {
+        self.weights = Some(match self.weights.take() {
+            Some((mut total, total_weight)) => {
+                if total.len() < value.len() {
+                    total.resize(value.len(), 0.0);
+                }
+                for (total, value) in total.iter_mut().zip(value) {
+                    *total += value * weight;
                 }
+                (total, total_weight + weight)
             }
+            None => (value.into_iter().map(|value| value * weight).collect(), weight),
+        });
+    }
+
+    /// Sends the blended values to `target`.
+    ///
+    /// Values weighing less than 1 in total are blended with the rest transform of
+    /// the target, and morph target weights with zero.
+    fn apply(
+        self,
+        target: &Target,
+    ) {
+        use cgmath::EuclideanSpace;
+        let position = self.position
+            .map(|(total, weight)| (cgmath::Point3::from_vec(total / weight).into(), weight.min(1.0)));
+        let orientation = self.orientation.map(|(blended, weight)| (blended.into(), weight.min(1.0)));
+        let scale = self.scale.map(|(total, weight)| ((total / weight).into(), weight.min(1.0)));
+        if position.is_some() || orientation.is_some() || scale.is_some() {
+            target.send(HubOperation::BlendTransform(position, orientation, scale));
         }
+        if let Some((total, weight)) = self.weights {
+            let weight = weight.max(1.0);
+            target.send(HubOperation::SetWeights(total.into_iter().map(|value| value / weight).collect()));
+        }
+    }
+}
+
+impl Fade {
+    fn new(
+        start: f32,
+        end: f32,
+        duration: f32,
+    ) -> Self {
+        Fade {
+            start,
+            end,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Clip {
+    /// Time of the last keyframe of all the tracks.
+    fn duration(&self) -> f32 {
+        self.tracks
+            .iter()
+            .filter_map(|&(ref track, _)| track.times.last().cloned())
+            .fold(0.0, f32::max)
     }
 }
 
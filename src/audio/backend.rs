@@ -0,0 +1,354 @@
+//! Audio outputs that don't need a sound card.
+//!
+//! ### Implementation Notes
+//!
+//! * `rodio` sinks always play on a device, so the null and WAV backends use
+//!   a `VirtualSink` built the same way on top of a `rodio` queue, playing into
+//!   a mixer shared by all the sources of the `Factory`.
+//! * The mixer is only drained by [`Factory::advance_audio`], which `Window::update`
+//!   calls with the duration of each frame. The recorded sounds and the playback state
+//!   of the sources only depend on the advanced durations, not on the wall clock.
+//!
+//! [`Factory::advance_audio`]: ../struct.Factory.html#method.advance_audio
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use rodio as r;
+use rodio::Source as _Source;
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::queue::{self, SourcesQueueInput};
+use rodio::source::{Done, Spatial};
+
+use super::SpatialPositions;
+use error::Error;
+
+const CHANNELS: u16 = 2;
+const SAMPLE_RATE: u32 = 44_100;
+/// Size of the RIFF chunk of a WAV file without its samples.
+const WAV_HEADER_LEN: u32 = 36;
+
+/// Where the sounds of the audio sources are played.
+///
+/// Selected with [`window::Builder::audio_backend`], or
+/// [`Factory::set_audio_backend`] for a [`headless`] renderer.
+///
+/// [`window::Builder::audio_backend`]: ../window/struct.Builder.html#method.audio_backend
+/// [`Factory::set_audio_backend`]: ../struct.Factory.html#method.set_audio_backend
+/// [`headless`]: ../struct.Renderer.html#method.headless
+#[derive(Clone, Debug, PartialEq)]
+pub enum Backend {
+    /// Default output device of the system.
+    Device,
+    /// Discards the sounds, while still playing them as the factory is advanced.
+    Null,
+    /// Records the mixed sounds to a 16-bit stereo WAV file at 44.1 kHz,
+    /// as the factory is advanced. The file is valid at any point while recording,
+    /// which stops at the 4 GiB size limit of the format.
+    Wav(PathBuf),
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Device
+    }
+}
+
+/// Output of the sources, created from a [`Backend`](enum.Backend.html).
+#[derive(Clone, Debug)]
+pub(crate) enum Endpoint {
+    Device,
+    Virtual(VirtualOutput),
+}
+
+impl Endpoint {
+    /// Fails if the WAV file can't be created.
+    pub(crate) fn new(backend: &Backend) -> Result<Self, Error> {
+        Ok(match *backend {
+            Backend::Device => Endpoint::Device,
+            Backend::Null => Endpoint::Virtual(VirtualOutput::new(None)),
+            Backend::Wav(ref path) => Endpoint::Virtual(VirtualOutput::new(Some(WavFile::create(path)?))),
+        })
+    }
+
+    /// Mixes `duration` of the sounds of a virtual backend, does nothing for the device.
+    pub(crate) fn advance(
+        &self,
+        duration: Duration,
+    ) {
+        if let Endpoint::Virtual(ref output) = *self {
+            output.advance(duration);
+        }
+    }
+}
+
+/// Mixer of the virtual backends.
+#[derive(Clone)]
+pub(crate) struct VirtualOutput {
+    mixer: Arc<DynamicMixerController<f32>>,
+    state: Arc<Mutex<MixState>>,
+}
+
+struct MixState {
+    mixer: DynamicMixer<f32>,
+    wav: Option<WavFile>,
+    /// Time advanced so far, and the frames mixed during it.
+    time: Duration,
+    frames: u64,
+    samples: Vec<f32>,
+}
+
+impl fmt::Debug for VirtualOutput {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "VirtualOutput")
+    }
+}
+
+impl VirtualOutput {
+    fn new(wav: Option<WavFile>) -> Self {
+        let (controller, mixer) = dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
+        VirtualOutput {
+            mixer: controller,
+            state: Arc::new(Mutex::new(MixState {
+                mixer,
+                wav,
+                time: Duration::from_secs(0),
+                frames: 0,
+                samples: Vec::new(),
+            })),
+        }
+    }
+
+    fn advance(
+        &self,
+        duration: Duration,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.time += duration;
+        // Count the frames from the total time, so the rounding doesn't drift.
+        let target = state.time.as_secs() * SAMPLE_RATE as u64
+            + state.time.subsec_nanos() as u64 * SAMPLE_RATE as u64 / 1_000_000_000;
+        state.samples.clear();
+        while state.frames < target {
+            for _ in 0 .. CHANNELS {
+                // The mixer returns `None` while no sink is alive.
+                state.samples.push(state.mixer.next().unwrap_or(0.0));
+            }
+            state.frames += 1;
+        }
+        let failed = match state.wav {
+            Some(ref mut wav) => match wav.write(&state.samples) {
+                Ok(()) => false,
+                Err(e) => {
+                    error!("Can't record audio: {}", e);
+                    true
+                }
+            },
+            None => false,
+        };
+        if failed {
+            state.wav = None;
+        }
+    }
+}
+
+/// PCM WAV file, with its header updated after each write.
+struct WavFile {
+    file: File,
+    data_len: u32,
+}
+
+impl WavFile {
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut wav = WavFile {
+            file: File::create(path)?,
+            data_len: 0,
+        };
+        let block_align = CHANNELS * 2;
+        let mut header = Vec::with_capacity(44);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&WAV_HEADER_LEN.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&CHANNELS.to_le_bytes());
+        header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        header.extend_from_slice(&(SAMPLE_RATE * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        wav.file.write_all(&header)?;
+        Ok(wav)
+    }
+
+    fn write(
+        &mut self,
+        samples: &[f32],
+    ) -> io::Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        // Keep the sizes of the header in range, cutting at a whole frame.
+        let frame_len = CHANNELS as usize * 2;
+        let room = (u32::MAX - WAV_HEADER_LEN - self.data_len) as usize / frame_len * frame_len;
+        let mut data = Vec::with_capacity(samples.len() * 2);
+        for &sample in samples.iter().take(room / 2) {
+            let value = (sample.max(-1.0).min(1.0) * i16::max_value() as f32) as i16;
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        self.file.write_all(&data)?;
+        self.data_len += data.len() as u32;
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(WAV_HEADER_LEN + self.data_len).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(40))?;
+        self.file.write_all(&self.data_len.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        if data.len() < samples.len() * 2 {
+            return Err(io::Error::other("the WAV file is full"));
+        }
+        Ok(())
+    }
+}
+
+struct Controls {
+    pause: AtomicBool,
+    volume: Mutex<f32>,
+    stopped: AtomicBool,
+    positions: Mutex<SpatialPositions>,
+}
+
+/// Equivalent of `rodio::Sink` and `rodio::SpatialSink` playing into a `VirtualOutput`.
+pub(crate) struct VirtualSink {
+    output: VirtualOutput,
+    queue: Arc<SourcesQueueInput<f32>>,
+    controls: Arc<Controls>,
+    sound_count: Arc<AtomicUsize>,
+    spatial: bool,
+}
+
+impl VirtualSink {
+    pub(crate) fn new(
+        output: &VirtualOutput,
+        positions: Option<SpatialPositions>,
+    ) -> Self {
+        let (queue, queue_output) = queue::queue(true);
+        output.mixer.add(queue_output);
+        VirtualSink {
+            output: output.clone(),
+            queue,
+            controls: Arc::new(Controls {
+                pause: AtomicBool::new(false),
+                volume: Mutex::new(1.0),
+                stopped: AtomicBool::new(false),
+                positions: Mutex::new(positions.unwrap_or_default()),
+            }),
+            sound_count: Arc::new(AtomicUsize::new(0)),
+            spatial: positions.is_some(),
+        }
+    }
+
+    /// Creates an empty sink on the same output, keeping the volume,
+    /// the pause state and the positions.
+    pub(crate) fn fresh(&self) -> Self {
+        let positions = *self.controls.positions.lock().unwrap();
+        let mut sink = VirtualSink::new(&self.output, if self.spatial { Some(positions) } else { None });
+        sink.set_volume(self.volume());
+        if self.is_paused() {
+            sink.pause();
+        }
+        sink
+    }
+
+    pub(crate) fn is_spatial(&self) -> bool {
+        self.spatial
+    }
+
+    pub(crate) fn append<S>(
+        &self,
+        source: S,
+    ) where
+        S: r::Source<Item = i16> + Send + 'static,
+    {
+        let controls = self.controls.clone();
+        let source = source
+            .pausable(false)
+            .amplify(1.0)
+            .stoppable()
+            .periodic_access(Duration::from_millis(5), move |src| {
+                if controls.stopped.load(Ordering::SeqCst) {
+                    src.stop();
+                } else {
+                    src.inner_mut().set_factor(*controls.volume.lock().unwrap());
+                    src.inner_mut()
+                        .inner_mut()
+                        .set_paused(controls.pause.load(Ordering::SeqCst));
+                }
+            })
+            .convert_samples::<f32>();
+        let boxed: Box<dyn r::Source<Item = f32> + Send> = if self.spatial {
+            let controls = self.controls.clone();
+            let p = *controls.positions.lock().unwrap();
+            let spatial = Spatial::new(source, p.emitter, p.left_ear, p.right_ear)
+                .periodic_access(Duration::from_millis(10), move |src| {
+                    let p = *controls.positions.lock().unwrap();
+                    src.set_positions(p.emitter, p.left_ear, p.right_ear);
+                });
+            Box::new(spatial)
+        } else {
+            Box::new(source)
+        };
+        self.sound_count.fetch_add(1, Ordering::Relaxed);
+        self.queue.append(Done::new(boxed, self.sound_count.clone()));
+    }
+
+    pub(crate) fn volume(&self) -> f32 {
+        *self.controls.volume.lock().unwrap()
+    }
+
+    pub(crate) fn set_volume(
+        &mut self,
+        volume: f32,
+    ) {
+        *self.controls.volume.lock().unwrap() = volume;
+    }
+
+    pub(crate) fn play(&self) {
+        self.controls.pause.store(false, Ordering::SeqCst);
+    }
+
+    pub(crate) fn pause(&self) {
+        self.controls.pause.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.controls.pause.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn empty(&self) -> bool {
+        self.sound_count.load(Ordering::Relaxed) == 0
+    }
+
+    pub(crate) fn set_positions(
+        &self,
+        positions: SpatialPositions,
+    ) {
+        *self.controls.positions.lock().unwrap() = positions;
+    }
+}
+
+impl Drop for VirtualSink {
+    fn drop(&mut self) {
+        self.queue.set_keep_alive_if_empty(false);
+        self.controls.stopped.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,1008 @@
+//! Primitives for audio playback.
+//!
+//! ### Implementation Notes
+//!
+//! * Every sound is wrapped before being handed to `rodio`, the wrapper applies
+//!   the [`Bus`](struct.Bus.html) gain and crossfades, and reports the playback
+//!   progress. Both are refreshed every few samples, not for each sample.
+
+mod backend;
+
+pub use self::backend::Backend;
+pub(crate) use self::backend::Endpoint;
+
+use self::backend::VirtualSink;
+use camera::Camera;
+use cgmath::Vector3;
+use error::Error;
+use hub::{self, Hub, SubNode};
+use object::{Base, Object, ObjectType};
+use scene::{Scene, SyncGuard};
+use std::{fmt, mem};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use arc_swap::ArcSwapOption;
+use rodio as r;
+use rodio::Source as _Source;
+
+/// Number of samples between two updates of the gain and the progress of a sound.
+const REFRESH_PERIOD: usize = 128;
+/// Closest the ears of a listener can be, keeping the panning of the sources defined.
+const MIN_EAR_DISTANCE: f32 = 0.001;
+
+/// Contents of a file shared by the decoders of a clip, so playing it doesn't copy them.
+#[derive(Debug, Clone)]
+struct SharedBytes(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Where the samples of a clip come from.
+#[derive(Debug, Clone)]
+enum ClipData {
+    /// The whole file, decoded when played.
+    Memory(SharedBytes),
+    /// Path of the file, decoded while playing.
+    Stream(Rc<PathBuf>),
+}
+
+/// Audio segment with sound effects.
+///
+/// Can be loaded from file using [`Factory::load_audio`](struct.Factory.html#method.load_audio),
+/// or streamed from the disk using
+/// [`Factory::load_audio_stream`](struct.Factory.html#method.load_audio_stream).
+#[derive(Debug, Clone)]
+pub struct Clip {
+    data: ClipData,
+    repeat: bool,
+    duration: Option<Duration>,
+    delay: Option<Duration>,
+    fade_in: Option<Duration>,
+    speed: f32,
+}
+
+impl Clip {
+    fn with_data(data: ClipData) -> Self {
+        Clip {
+            data,
+            repeat: false,
+            duration: None,
+            delay: None,
+            fade_in: None,
+            speed: 1.0,
+        }
+    }
+
+    /// Checks that the format of `data` is recognized before keeping it.
+    pub(crate) fn try_new(data: Vec<u8>) -> Result<Self, Error> {
+        let data = SharedBytes(Arc::new(data));
+        match r::Decoder::new(Cursor::new(data.clone())) {
+            Ok(_) => Ok(Clip::with_data(ClipData::Memory(data))),
+            Err(_) => Err(Error::Unsupported("unrecognized audio format".into())),
+        }
+    }
+
+    /// Checks that the file at `path` can be decoded, without reading it all.
+    pub(crate) fn try_stream(path: &Path) -> Result<Self, Error> {
+        open_stream(path)?;
+        Ok(Clip::with_data(ClipData::Stream(Rc::new(path.to_owned()))))
+    }
+
+    /// Passing true enforces looping sound. Defaults to `false`.
+    pub fn repeat(
+        &mut self,
+        enable: bool,
+    ) {
+        self.repeat = enable;
+    }
+
+    /// Clip the sound to the desired duration.
+    pub fn take_duration(
+        &mut self,
+        duration: Duration,
+    ) {
+        self.duration = Some(duration);
+    }
+
+    /// Play sound after desired delay.
+    pub fn delay(
+        &mut self,
+        delay: Duration,
+    ) {
+        self.delay = Some(delay);
+    }
+
+    /// Fade in sound in desired duration.
+    pub fn fade_in(
+        &mut self,
+        duration: Duration,
+    ) {
+        self.fade_in = Some(duration);
+    }
+
+    /// Adjust the playback speed. Defaults to `1.0`.
+    pub fn speed(
+        &mut self,
+        ratio: f32,
+    ) {
+        self.speed = ratio;
+    }
+
+    /// Creates the decoder of the clip, with its effects applied.
+    fn decode(&self) -> Result<Box<dyn r::Source<Item = i16> + Send>, Error> {
+        let mut boxed: Box<dyn r::Source<Item = i16> + Send> = match self.data {
+            ClipData::Memory(ref data) => match r::Decoder::new(Cursor::new(data.clone())) {
+                Ok(decoder) => Box::new(decoder),
+                Err(_) => return Err(Error::Unsupported("unrecognized audio format".into())),
+            },
+            // Looping a stream with `repeat_infinite` would keep all of it in memory.
+            ClipData::Stream(ref path) => Box::new(FileStream {
+                path: (**path).clone(),
+                decoder: open_stream(path)?,
+                looping: self.repeat,
+            }),
+        };
+        if self.repeat {
+            if let ClipData::Memory(_) = self.data {
+                boxed = Box::new(boxed.repeat_infinite());
+            }
+        }
+        if self.speed != 1.0 {
+            boxed = Box::new(boxed.speed(self.speed));
+        }
+        if let Some(duration) = self.delay {
+            boxed = Box::new(boxed.delay(duration));
+        }
+        if let Some(duration) = self.duration {
+            boxed = Box::new(boxed.take_duration(duration));
+        }
+        if let Some(duration) = self.fade_in {
+            boxed = Box::new(boxed.fade_in(duration));
+        }
+        Ok(boxed)
+    }
+}
+
+fn open_stream(path: &Path) -> Result<r::Decoder<BufReader<File>>, Error> {
+    let file = File::open(path)?;
+    r::Decoder::new(BufReader::new(file))
+        .map_err(|_| Error::Unsupported("unrecognized audio format".into()))
+}
+
+/// Decodes a file while it plays, reopening it at the end when looping.
+struct FileStream {
+    path: PathBuf,
+    decoder: r::Decoder<BufReader<File>>,
+    looping: bool,
+}
+
+impl Iterator for FileStream {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(sample) = self.decoder.next() {
+            return Some(sample);
+        }
+        if !self.looping {
+            return None;
+        }
+        // The file may have been removed since, stop playing in that case.
+        self.decoder = open_stream(&self.path).ok()?;
+        self.decoder.next()
+    }
+}
+
+impl r::Source for FileStream {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looping {
+            None
+        } else {
+            self.decoder.total_duration()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BusState {
+    name: String,
+    /// Bits of the `f32` volume.
+    volume: AtomicU32,
+    muted: AtomicBool,
+}
+
+impl BusState {
+    fn gain(&self) -> f32 {
+        if self.muted.load(Ordering::Relaxed) {
+            0.0
+        } else {
+            f32::from_bits(self.volume.load(Ordering::Relaxed))
+        }
+    }
+}
+
+/// Mixer bus controlling the volume of a group of sources, such as
+/// the music, the sound effects or the voices.
+///
+/// Buses are obtained by name with [`Factory::audio_bus`], and sources are routed
+/// to them with [`Source::set_bus`]. Changes to the bus apply immediately to
+/// every sound of its sources, on top of their own volume.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut window = three::Window::new("Three-rs");
+/// let music = window.factory.audio_bus("music");
+/// let source = window.factory.audio_source();
+/// source.set_bus(&music);
+/// window.scene.add(&source);
+///
+/// music.set_volume(0.5);
+/// music.set_muted(true);
+/// ```
+///
+/// [`Factory::audio_bus`]: ../struct.Factory.html#method.audio_bus
+/// [`Source::set_bus`]: struct.Source.html#method.set_bus
+#[derive(Clone, Debug)]
+pub struct Bus {
+    state: Arc<BusState>,
+}
+
+impl PartialEq for Bus {
+    fn eq(
+        &self,
+        other: &Bus,
+    ) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for Bus {}
+
+impl Bus {
+    pub(crate) fn new(name: &str) -> Self {
+        Bus {
+            state: Arc::new(BusState {
+                name: name.to_owned(),
+                volume: AtomicU32::new(1.0f32.to_bits()),
+                muted: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Name of the bus.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Volume of the bus. Defaults to `1.0`.
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.state.volume.load(Ordering::Relaxed))
+    }
+
+    /// Adjust the volume of all the sources routed to this bus.
+    pub fn set_volume(
+        &self,
+        volume: f32,
+    ) {
+        self.state.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Whether the bus is muted. Defaults to `false`.
+    pub fn is_muted(&self) -> bool {
+        self.state.muted.load(Ordering::Relaxed)
+    }
+
+    /// Silence all the sources routed to this bus, keeping its volume.
+    pub fn set_muted(
+        &self,
+        muted: bool,
+    ) {
+        self.state.muted.store(muted, Ordering::Relaxed);
+    }
+}
+
+/// Bus of a source, swapped without blocking the audio thread reading it.
+type BusRoute = ArcSwapOption<BusState>;
+
+/// Fade-out duration meaning the sounds aren't fading out.
+const NO_FADE_OUT: u64 = u64::MAX;
+
+/// Fade-out of the sounds of a sink being crossfaded.
+#[derive(Debug)]
+struct FadeOut {
+    /// Microseconds, or `NO_FADE_OUT`.
+    duration: AtomicU64,
+    /// Set once a sound has faded out, ending the ones queued after it.
+    finished: AtomicBool,
+}
+
+/// Progress of a sound, updated by the audio thread.
+#[derive(Debug, Default)]
+struct Progress {
+    /// Microseconds played.
+    elapsed: AtomicU64,
+    finished: AtomicBool,
+}
+
+/// Sound applying the gain of its bus and fade-out, and reporting its progress.
+struct Mixed {
+    inner: Box<dyn r::Source<Item = i16> + Send>,
+    bus: Arc<BusRoute>,
+    fade_out: Arc<FadeOut>,
+    progress: Arc<Progress>,
+    gain: f32,
+    samples: usize,
+    /// Samples played since the last refresh.
+    pending: usize,
+    elapsed: f64,
+    fade_start: Option<f64>,
+}
+
+impl Mixed {
+    fn report_progress(&mut self) {
+        let rate = self.inner.sample_rate() as f64 * self.inner.channels() as f64;
+        self.elapsed += self.pending as f64 / rate;
+        self.pending = 0;
+        self.progress
+            .elapsed
+            .store((self.elapsed * 1e6) as u64, Ordering::Relaxed);
+    }
+
+    /// Updates the gain and the progress, returns `false` once faded out.
+    fn refresh(&mut self) -> bool {
+        self.report_progress();
+        self.gain = match *self.bus.load() {
+            Some(ref bus) => bus.gain(),
+            None => 1.0,
+        };
+        if self.fade_out.finished.load(Ordering::Relaxed) {
+            return false;
+        }
+        let duration = self.fade_out.duration.load(Ordering::Relaxed);
+        if duration != NO_FADE_OUT {
+            let start = *self.fade_start.get_or_insert(self.elapsed);
+            let length = duration as f64 * 1e-6;
+            let t = if length > 0.0 { (self.elapsed - start) / length } else { 1.0 };
+            if t >= 1.0 {
+                self.fade_out.finished.store(true, Ordering::Relaxed);
+                return false;
+            }
+            self.gain *= 1.0 - t as f32;
+        }
+        true
+    }
+}
+
+impl Iterator for Mixed {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = if self.samples % REFRESH_PERIOD == 0 && !self.refresh() {
+            None
+        } else {
+            self.inner.next()
+        };
+        match sample {
+            Some(sample) => {
+                self.samples += 1;
+                self.pending += 1;
+                Some((sample as f32 * self.gain) as i16)
+            }
+            None => {
+                self.report_progress();
+                self.progress.finished.store(true, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+}
+
+impl r::Source for Mixed {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+impl Drop for Mixed {
+    fn drop(&mut self) {
+        self.progress.finished.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Operation {
+    Append(Clip),
+    Crossfade(Clip, Duration),
+    Resume,
+    Pause,
+    Stop,
+    SetVolume(f32),
+    SetBus(Option<Bus>),
+}
+
+/// Sink with the sounds queued in it.
+#[derive(Debug)]
+struct Output {
+    source: SourceInternal,
+    fade_out: Arc<FadeOut>,
+    sounds: Vec<Arc<Progress>>,
+}
+
+impl Output {
+    fn new(source: SourceInternal) -> Self {
+        Output {
+            source,
+            fade_out: Arc::new(FadeOut {
+                duration: AtomicU64::new(NO_FADE_OUT),
+                finished: AtomicBool::new(false),
+            }),
+            sounds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AudioData {
+    output: Output,
+    /// Outputs fading out after a crossfade.
+    fading: Vec<Output>,
+    bus: Arc<BusRoute>,
+}
+
+fn default_endpoint() -> Result<r::Device, Error> {
+    // TODO: Change to `r::default_endpoint()` in next `rodio` release.
+    #[allow(deprecated)]
+    let device = r::default_output_device();
+    device.ok_or_else(|| Error::Resource("no default audio output device".into()))
+}
+
+impl AudioData {
+    fn with_source(source: SourceInternal) -> Self {
+        AudioData {
+            output: Output::new(source),
+            fading: Vec::new(),
+            bus: Arc::new(BusRoute::empty()),
+        }
+    }
+
+    /// Fails if the `Device` backend has no output device.
+    pub(crate) fn new(endpoint: &Endpoint) -> Result<Self, Error> {
+        Ok(AudioData::with_source(match *endpoint {
+            Endpoint::Device => {
+                let device = default_endpoint()?;
+                SourceInternal::D2(r::Sink::new(&device), device)
+            }
+            Endpoint::Virtual(ref output) => SourceInternal::Virtual(VirtualSink::new(output, None)),
+        }))
+    }
+
+    /// Fails if the `Device` backend has no output device.
+    pub(crate) fn new_spatial(endpoint: &Endpoint) -> Result<Self, Error> {
+        let positions = SpatialPositions::default();
+        Ok(AudioData::with_source(match *endpoint {
+            Endpoint::Device => {
+                let device = default_endpoint()?;
+                SourceInternal::D3(positions.sink(&device), positions, device)
+            }
+            Endpoint::Virtual(ref output) => {
+                SourceInternal::Virtual(VirtualSink::new(output, Some(positions)))
+            }
+        }))
+    }
+
+    pub(crate) fn playback(&self) -> Playback {
+        let current = self.output
+            .sounds
+            .iter()
+            .find(|progress| !progress.finished.load(Ordering::Relaxed));
+        match current {
+            Some(progress) => Playback {
+                position: Duration::from_micros(progress.elapsed.load(Ordering::Relaxed)),
+                finished: false,
+            },
+            None => Playback {
+                position: Duration::from_secs(0),
+                finished: true,
+            },
+        }
+    }
+
+    /// Current output, followed by the ones fading out.
+    fn outputs<'a>(&'a mut self) -> impl Iterator<Item = &'a mut Output> + 'a {
+        ::std::iter::once(&mut self.output).chain(self.fading.iter_mut())
+    }
+
+    fn set_positions(
+        &mut self,
+        positions: SpatialPositions,
+    ) {
+        for output in self.outputs() {
+            output.source.set_positions(positions);
+        }
+    }
+
+    pub(crate) fn append(
+        &mut self,
+        clip: Clip,
+    ) {
+        append(&self.bus, &mut self.output, &clip);
+    }
+
+    pub(crate) fn crossfade(
+        &mut self,
+        mut clip: Clip,
+        duration: Duration,
+    ) {
+        self.fading.retain(|output| !output.source.empty());
+        let playing = !self.playback().finished;
+        let fresh = Output::new(self.output.source.fresh());
+        let old = mem::replace(&mut self.output, fresh);
+        if playing {
+            let micros = duration.as_secs() * 1_000_000 + duration.subsec_micros() as u64;
+            old.fade_out.duration.store(micros.min(NO_FADE_OUT - 1), Ordering::Relaxed);
+            self.fading.push(old);
+            clip.fade_in = Some(duration);
+        }
+        append(&self.bus, &mut self.output, &clip);
+    }
+
+    pub(crate) fn pause(&mut self) {
+        for output in self.outputs() {
+            output.source.pause();
+        }
+    }
+
+    pub(crate) fn resume(&mut self) {
+        for output in self.outputs() {
+            output.source.resume();
+        }
+    }
+
+    pub(crate) fn stop(&mut self) {
+        // Sinks can't be reused once stopped, the sounds stop when the old ones are dropped.
+        self.fading.clear();
+        self.output = Output::new(self.output.source.fresh());
+    }
+
+    pub(crate) fn set_volume(
+        &mut self,
+        volume: f32,
+    ) {
+        for output in self.outputs() {
+            output.source.set_volume(volume);
+        }
+    }
+
+    pub(crate) fn set_bus(
+        &mut self,
+        bus: Option<Bus>,
+    ) {
+        self.bus.store(bus.map(|bus| bus.state));
+    }
+}
+
+/// Queues `clip` in `output`, routed to the bus in `bus`.
+fn append(
+    bus: &Arc<BusRoute>,
+    output: &mut Output,
+    clip: &Clip,
+) {
+    let inner = match clip.decode() {
+        Ok(inner) => inner,
+        Err(e) => {
+            error!("Can't play audio clip: {}", e);
+            return;
+        }
+    };
+    let progress = Arc::new(Progress::default());
+    output
+        .sounds
+        .retain(|progress| !progress.finished.load(Ordering::Relaxed));
+    output.sounds.push(progress.clone());
+    output.source.append(Mixed {
+        inner,
+        bus: bus.clone(),
+        fade_out: output.fade_out.clone(),
+        progress,
+        gain: 1.0,
+        samples: 0,
+        pending: 0,
+        elapsed: 0.0,
+        fade_start: None,
+    });
+}
+
+/// Playback state of a [`Source`](struct.Source.html),
+/// obtained with [`SyncGuard::resolve_data`].
+///
+/// [`SyncGuard::resolve_data`]: ../scene/struct.SyncGuard.html#method.resolve_data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Playback {
+    /// Time since the current sound started playing, including its delay.
+    pub position: Duration,
+    /// `true` once all the queued sounds have been played.
+    pub finished: bool,
+}
+
+/// Audio source. Can play only one sound at a time.
+///
+/// You must add it to the scene to play sounds.
+/// You may create several `Source`s to play sounds simultaneously.
+///
+/// Sources created by [`Factory::audio_source_3d`] are spatial: they are heard from
+/// their position in the scene, see [`Listener`](struct.Listener.html).
+///
+/// The [`Playback`](struct.Playback.html) state is read with [`SyncGuard::resolve_data`].
+///
+/// [`Factory::audio_source_3d`]: ../struct.Factory.html#method.audio_source_3d
+/// [`SyncGuard::resolve_data`]: ../scene/struct.SyncGuard.html#method.resolve_data
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Source {
+    pub(crate) object: Base,
+}
+derive_DowncastObject!(Source => ObjectType::AudioSource);
+
+impl AsRef<Base> for Source {
+    fn as_ref(&self) -> &Base {
+        &self.object
+    }
+}
+
+impl Object for Source {
+    type Data = Playback;
+
+    fn resolve_data(&self, sync_guard: &SyncGuard) -> Self::Data {
+        match &sync_guard.hub[self].sub_node {
+            SubNode::Audio(ref data) => data.playback(),
+            sub_node @ _ => panic!("`Source` had a bad sub node type: {:?}", sub_node),
+        }
+    }
+}
+
+impl Source {
+    pub(crate) fn with_object(object: Base) -> Self {
+        Source { object }
+    }
+
+    /// Add clip to the queue.
+    pub fn play(
+        &self,
+        clip: &Clip,
+    ) {
+        let msg = hub::Operation::SetAudio(Operation::Append(clip.clone()));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Replace the queue with `clip`, fading out the current sound while `clip` fades in.
+    ///
+    /// Plays `clip` right away if nothing is playing.
+    pub fn crossfade(
+        &self,
+        clip: &Clip,
+        duration: Duration,
+    ) {
+        let msg = hub::Operation::SetAudio(Operation::Crossfade(clip.clone(), duration));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Pause current sound.
+    ///
+    /// You can [`resume`](struct.Source.html#method.resume) playback.
+    pub fn pause(&self) {
+        let msg = hub::Operation::SetAudio(Operation::Pause);
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Resume playback after [`pausing`](struct.Source.html#method.pause).
+    pub fn resume(&self) {
+        let msg = hub::Operation::SetAudio(Operation::Resume);
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Stop the playback by emptying the queue.
+    pub fn stop(&self) {
+        let msg = hub::Operation::SetAudio(Operation::Stop);
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Adjust playback volume.
+    ///
+    /// Default value is `1.0`.
+    pub fn set_volume(
+        &self,
+        volume: f32,
+    ) {
+        let msg = hub::Operation::SetAudio(Operation::SetVolume(volume));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Route the sounds of this source to `bus`, including the ones already playing.
+    pub fn set_bus(
+        &self,
+        bus: &Bus,
+    ) {
+        let msg = hub::Operation::SetAudio(Operation::SetBus(Some(bus.clone())));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Stop routing this source to a bus.
+    pub fn clear_bus(&self) {
+        let msg = hub::Operation::SetAudio(Operation::SetBus(None));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+}
+
+/// Point of view from which spatial audio sources are heard.
+///
+/// The listener follows the world transform of an object, usually the [`Camera`],
+/// with the ears on either side of its local X axis. Set it as [`Scene::listener`],
+/// the camera rendered by [`Renderer::render`] is the listener otherwise.
+/// [`Renderer::render_to`] only updates the ears of a listener set on the scene,
+/// so that offscreen views don't move them.
+///
+/// Spatial sources are panned between the ears, and their volume falls off
+/// with the square of the distance past one unit from the listener.
+///
+/// # Examples
+///
+/// ```no_run
+/// use three::Object;
+///
+/// # let mut window = three::Window::new("Three-rs");
+/// let camera = window.factory.perspective_camera(60.0, 0.1 .. 100.0);
+/// window.scene.listener = Some(three::audio::Listener::new(&camera));
+///
+/// let source = window.factory.audio_source_3d();
+/// source.set_position([5.0, 0.0, 0.0]);
+/// window.scene.add(&source);
+/// let clip = window.factory.load_audio("engine.ogg");
+/// source.play(&clip);
+/// ```
+///
+/// [`Camera`]: ../camera/struct.Camera.html
+/// [`Scene::listener`]: ../scene/struct.Scene.html#structfield.listener
+/// [`Renderer::render`]: ../struct.Renderer.html#method.render
+/// [`Renderer::render_to`]: ../struct.Renderer.html#method.render_to
+#[derive(Clone, Debug)]
+pub struct Listener {
+    object: Base,
+    ear_distance: f32,
+}
+
+impl Listener {
+    /// Creates a listener following `object`.
+    pub fn new<T: AsRef<Base>>(object: &T) -> Self {
+        Listener {
+            object: object.as_ref().clone(),
+            ear_distance: 0.2,
+        }
+    }
+
+    /// Sets the distance between the ears, in world units. Defaults to `0.2`.
+    ///
+    /// Distances below `0.001`, including negative ones, are clamped to `0.001`.
+    pub fn set_ear_distance(
+        &mut self,
+        distance: f32,
+    ) {
+        self.ear_distance = distance.max(MIN_EAR_DISTANCE);
+    }
+}
+
+/// Moves the spatial sources of the scene and the ears of its listener
+/// to their world positions.
+pub(crate) fn update_spatial(
+    hub: &mut Hub,
+    scene: &Scene,
+    camera: &Camera,
+) {
+    let (target, ear_distance) = match scene.listener {
+        Some(ref listener) => (&listener.object, listener.ear_distance),
+        None => (camera.as_ref(), 0.2),
+    };
+    let target_node = &hub[target] as *const _;
+    let mut listener = hub[target].transform;
+    let mut emitters = Vec::new();
+    for w in hub.walk_all(&scene.first_child) {
+        if w.node as *const _ == target_node {
+            listener = w.world_transform.into();
+        }
+        if let SubNode::Audio(ref data) = w.node.sub_node {
+            if data.output.source.is_spatial() {
+                emitters.push((w.node_ptr.clone(), w.world_transform.w.truncate()));
+            }
+        }
+    }
+
+    let half_ear = listener.rot * Vector3::unit_x() * (ear_distance * 0.5);
+    for (ptr, emitter) in emitters {
+        if let SubNode::Audio(ref mut data) = hub.nodes[&ptr].sub_node {
+            data.set_positions(SpatialPositions {
+                emitter: emitter.into(),
+                left_ear: (listener.disp - half_ear).into(),
+                right_ear: (listener.disp + half_ear).into(),
+            });
+        }
+    }
+}
+
+/// Positions of a spatial source and the ears hearing it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SpatialPositions {
+    emitter: [f32; 3],
+    left_ear: [f32; 3],
+    right_ear: [f32; 3],
+}
+
+impl Default for SpatialPositions {
+    fn default() -> Self {
+        SpatialPositions {
+            emitter: [0.0, 0.0, 0.0],
+            left_ear: [-0.1, 0.0, 0.0],
+            right_ear: [0.1, 0.0, 0.0],
+        }
+    }
+}
+
+impl SpatialPositions {
+    fn sink(
+        &self,
+        device: &r::Device,
+    ) -> r::SpatialSink {
+        r::SpatialSink::new(device, self.emitter, self.left_ear, self.right_ear)
+    }
+}
+
+/// The device sinks keep their output device, to create fresh sinks on it.
+enum SourceInternal {
+    D2(r::Sink, r::Device),
+    D3(r::SpatialSink, SpatialPositions, r::Device),
+    Virtual(VirtualSink),
+}
+
+impl fmt::Debug for SourceInternal {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match *self {
+            SourceInternal::D2(..) => write!(f, "SourceInternal::D2"),
+            SourceInternal::D3(..) => write!(f, "SourceInternal::D3"),
+            SourceInternal::Virtual(_) => write!(f, "SourceInternal::Virtual"),
+        }
+    }
+}
+
+impl SourceInternal {
+    fn pause(&self) {
+        match *self {
+            SourceInternal::D2(ref sink, _) => sink.pause(),
+            SourceInternal::D3(ref sink, ..) => sink.pause(),
+            SourceInternal::Virtual(ref sink) => sink.pause(),
+        }
+    }
+
+    fn resume(&self) {
+        match *self {
+            SourceInternal::D2(ref sink, _) => sink.play(),
+            SourceInternal::D3(ref sink, ..) => sink.play(),
+            SourceInternal::Virtual(ref sink) => sink.play(),
+        }
+    }
+
+    /// Creates an empty sink of the same kind, keeping the volume and the pause state.
+    fn fresh(&self) -> Self {
+        match *self {
+            SourceInternal::D2(ref sink, ref device) => {
+                let mut new_sink = r::Sink::new(device);
+                new_sink.set_volume(sink.volume());
+                if sink.is_paused() {
+                    new_sink.pause();
+                }
+                SourceInternal::D2(new_sink, device.clone())
+            }
+            SourceInternal::D3(ref sink, positions, ref device) => {
+                let mut new_sink = positions.sink(device);
+                new_sink.set_volume(sink.volume());
+                if sink.is_paused() {
+                    new_sink.pause();
+                }
+                SourceInternal::D3(new_sink, positions, device.clone())
+            }
+            SourceInternal::Virtual(ref sink) => SourceInternal::Virtual(sink.fresh()),
+        }
+    }
+
+    fn is_spatial(&self) -> bool {
+        match *self {
+            SourceInternal::D2(..) => false,
+            SourceInternal::D3(..) => true,
+            SourceInternal::Virtual(ref sink) => sink.is_spatial(),
+        }
+    }
+
+    fn empty(&self) -> bool {
+        match *self {
+            SourceInternal::D2(ref sink, _) => sink.empty(),
+            SourceInternal::D3(ref sink, ..) => sink.empty(),
+            SourceInternal::Virtual(ref sink) => sink.empty(),
+        }
+    }
+
+    fn set_volume(
+        &mut self,
+        volume: f32,
+    ) {
+        match *self {
+            SourceInternal::D2(ref mut sink, _) => sink.set_volume(volume),
+            SourceInternal::D3(ref mut sink, ..) => sink.set_volume(volume),
+            SourceInternal::Virtual(ref mut sink) => sink.set_volume(volume),
+        }
+    }
+
+    fn set_positions(
+        &mut self,
+        new_positions: SpatialPositions,
+    ) {
+        match *self {
+            SourceInternal::D2(..) => {}
+            SourceInternal::D3(ref mut sink, ref mut positions, _) => {
+                if *positions != new_positions {
+                    sink.set_emitter_position(new_positions.emitter);
+                    sink.set_left_ear_position(new_positions.left_ear);
+                    sink.set_right_ear_position(new_positions.right_ear);
+                    *positions = new_positions;
+                }
+            }
+            SourceInternal::Virtual(ref sink) => {
+                if sink.is_spatial() {
+                    sink.set_positions(new_positions);
+                }
+            }
+        }
+    }
+
+    fn append(
+        &self,
+        sound: Mixed,
+    ) {
+        match *self {
+            SourceInternal::D2(ref sink, _) => sink.append(sound),
+            SourceInternal::D3(ref sink, ..) => sink.append(sound),
+            SourceInternal::Virtual(ref sink) => sink.append(sound),
+        }
+    }
+}
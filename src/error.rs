@@ -0,0 +1,87 @@
+//! Errors returned by the loaders and the `try_*` resource constructors.
+
+use std::io;
+
+use gfx;
+#[cfg(feature = "gltf")]
+use gltf;
+use image;
+use obj;
+
+quick_error! {
+    #[doc = "Error encountered when loading an asset."]
+    #[derive(Debug)]
+    pub enum Error {
+        #[doc = "Standard I/O error, such as a missing file."]
+        Io(err: io::Error) {
+            from()
+            description("I/O error")
+            display("I/O error: {}", err)
+            cause(err)
+        }
+
+        #[doc = "Malformed or corrupted data."]
+        Decode(message: String) {
+            description("decoding error")
+            display("decoding error: {}", message)
+        }
+
+        #[doc = "Valid data using a format or feature that isn't supported."]
+        Unsupported(message: String) {
+            description("unsupported feature")
+            display("unsupported feature: {}", message)
+        }
+
+        #[doc = "Failure to create a GPU resource."]
+        Resource(message: String) {
+            description("GPU resource creation error")
+            display("GPU resource creation error: {}", message)
+            from(err: gfx::CombinedError) -> (format!("{:?}", err))
+            from(err: gfx::buffer::CreationError) -> (format!("{:?}", err))
+            from(err: gfx::texture::CreationError) -> (format!("{:?}", err))
+            from(err: gfx::ResourceViewError) -> (format!("{:?}", err))
+            from(err: gfx::TargetViewError) -> (format!("{:?}", err))
+            from(err: gfx::mapping::Error) -> (format!("{:?}", err))
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => Error::Io(err),
+            image::ImageError::Unsupported(err) => Error::Unsupported(err.to_string()),
+            other => Error::Decode(other.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl From<gltf::Error> for Error {
+    fn from(err: gltf::Error) -> Self {
+        match err {
+            gltf::Error::Io(err) => Error::Io(err),
+            other => Error::Decode(other.to_string()),
+        }
+    }
+}
+
+impl From<obj::ObjError> for Error {
+    fn from(err: obj::ObjError) -> Self {
+        match err {
+            obj::ObjError::Io(err) => Error::Io(err),
+            other => Error::Decode(other.to_string()),
+        }
+    }
+}
+
+impl From<obj::MtlLibsLoadError> for Error {
+    fn from(err: obj::MtlLibsLoadError) -> Self {
+        // only the first library that failed is reported
+        match err.0.into_iter().next() {
+            Some((_, obj::MtlError::Io(err))) => Error::Io(err),
+            Some((name, other)) => Error::Decode(format!("{}: {}", name, other)),
+            None => Error::Decode("material libraries failed to load".into()),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Asset loading on worker threads.
+//!
+//! ### Implementation Notes
+//!
+//! * GPU resources can only be created on the thread owning the `Factory`,
+//!   so loading is split in two: the worker reads and decodes the files,
+//!   then `Loading::poll` uploads the result on the main thread.
+//! * The workers are a small pool of threads owned by the `Factory`, started by
+//!   the first asset loaded in the background and fed through a channel.
+
+use std::{panic, thread};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+
+#[cfg(feature = "gltf")]
+use template::Template;
+
+use Error;
+use mesh::Mesh;
+use object::Group;
+use texture::{Sampler, Texture};
+use super::Factory;
+
+/// Number of threads in the pool of a `Factory`.
+const WORKER_COUNT: usize = 4;
+
+/// Uploads the data decoded by the worker.
+type Finish<T> = Box<dyn FnOnce(&mut Factory) -> Result<T, Error> + Send>;
+
+/// Reads an asset and sends the result to its `Loading` handle.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Pool of worker threads, which exit once it is dropped and the queued jobs are done.
+#[derive(Default)]
+pub(crate) struct Workers {
+    sender: Option<mpsc::Sender<Job>>,
+}
+
+impl Workers {
+    fn run(
+        &mut self,
+        job: Job,
+    ) {
+        let sender = self.sender.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::channel::<Job>();
+            let rx = Arc::new(Mutex::new(rx));
+            for index in 0 .. WORKER_COUNT {
+                let rx = rx.clone();
+                thread::Builder::new()
+                    .name(format!("three-loader-{}", index))
+                    .spawn(move || loop {
+                        // The lock is released before running the job.
+                        let job = match rx.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+                        // A panicking job disconnects its handle, the thread keeps going.
+                        let _ = panic::catch_unwind(panic::AssertUnwindSafe(job));
+                    })
+                    .expect("Unable to start a loading thread");
+            }
+            tx
+        });
+        // The workers only exit once the sender is dropped.
+        sender.send(job).unwrap();
+    }
+}
+
+/// Handle to an asset being loaded in the background.
+///
+/// Returned by the `Factory::load_*_async` methods, the asset is read and
+/// decoded on a worker thread while the application keeps rendering.
+/// Call [`poll`](#method.poll) once per frame to get the finished asset.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut window = three::Window::new("Three-rs");
+/// # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+/// let mut loading = window.factory.load_obj_async("level.obj");
+/// while window.update() {
+///     if let Some(result) = loading.poll(&mut window.factory) {
+///         let (groups, _meshes) = result.expect("load level");
+///         for group in groups.values() {
+///             window.scene.add(group);
+///         }
+///     }
+///     window.render(&camera);
+/// }
+/// ```
+pub struct Loading<T> {
+    receiver: Option<mpsc::Receiver<Result<Finish<T>, Error>>>,
+}
+
+impl<T: 'static> Loading<T> {
+    fn spawn<F>(
+        workers: &mut Workers,
+        read: F,
+    ) -> Self
+    where
+        F: FnOnce() -> Result<Finish<T>, Error> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        workers.run(Box::new(move || {
+            // The handle may have been dropped in the meantime.
+            let _ = tx.send(read());
+        }));
+        Loading { receiver: Some(rx) }
+    }
+
+    fn ready(finish: Finish<T>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        tx.send(Ok(finish)).unwrap();
+        Loading { receiver: Some(rx) }
+    }
+
+    /// Returns the asset once the worker has decoded it, uploading its GPU resources.
+    ///
+    /// Returns `None` while the asset is still loading, and after the result
+    /// has been returned once.
+    pub fn poll(
+        &mut self,
+        factory: &mut Factory,
+    ) -> Option<Result<T, Error>> {
+        let result = match self.receiver {
+            Some(ref rx) => match rx.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => Err(worker_panicked()),
+            },
+            None => return None,
+        };
+        self.receiver = None;
+        Some(result.and_then(|finish| finish(factory)))
+    }
+
+    /// Blocks until the worker has decoded the asset, then uploads it.
+    ///
+    /// #### Panics
+    /// Panics if the result was already returned by [`poll`](#method.poll).
+    pub fn wait(
+        self,
+        factory: &mut Factory,
+    ) -> Result<T, Error> {
+        let rx = self.receiver.expect("Asset was already returned by `Loading::poll`");
+        rx.recv()
+            .unwrap_or_else(|_| Err(worker_panicked()))
+            .and_then(|finish| finish(factory))
+    }
+}
+
+/// Decoders only panic on data they fail to handle, so it's reported as such.
+fn worker_panicked() -> Error {
+    Error::Decode("loading thread panicked".into())
+}
+
+impl super::Factory {
+    /// Load texture from file in the background, with default `Sampler`.
+    ///
+    /// See [`load_texture`](#method.load_texture) for the supported formats,
+    /// and [`Loading`](struct.Loading.html) for getting the result.
+    pub fn load_texture_async<P: AsRef<Path>>(
+        &mut self,
+        path_str: P,
+    ) -> Loading<Texture<[f32; 4]>> {
+        let sampler = self.default_sampler();
+        self.load_texture_with_sampler_async(path_str, sampler)
+    }
+
+    /// Load texture from file in the background, with custom `Sampler`.
+    ///
+    /// A texture already in the cache is returned on the first poll.
+    pub fn load_texture_with_sampler_async<P: AsRef<Path>>(
+        &mut self,
+        path_str: P,
+        sampler: Sampler,
+    ) -> Loading<Texture<[f32; 4]>> {
+        if let Some(texture) = self.cached_texture(path_str.as_ref()) {
+            return Loading::ready(Box::new(move |_: &mut Factory| Ok(texture)));
+        }
+        let path = path_str.as_ref().to_owned();
+        Loading::spawn(&mut self.workers, move || {
+            let image = Factory::read_texture(&path)?;
+            let finish: Finish<_> = Box::new(move |factory: &mut Factory| {
+                factory.cache_texture(&path, image, sampler)
+            });
+            Ok(finish)
+        })
+    }
+
+    /// Load mesh from Wavefront Obj format in the background.
+    ///
+    /// Parsing, triangulation and decoding of the material textures happen on
+    /// the worker, see [`Loading`](struct.Loading.html) for getting the result.
+    pub fn load_obj_async(
+        &mut self,
+        path_str: &str,
+    ) -> Loading<(HashMap<String, Group>, Vec<Mesh>)> {
+        let path = path_str.to_owned();
+        Loading::spawn(&mut self.workers, move || {
+            let mut data = Factory::read_obj(&path)?;
+            Factory::read_obj_textures(&mut data)?;
+            let finish: Finish<_> = Box::new(move |factory: &mut Factory| factory.build_obj(data));
+            Ok(finish)
+        })
+    }
+
+    /// Loads a template from a glTF 2.0 file in the background.
+    ///
+    /// Importing the file, decoding its images and reading the mesh geometry
+    /// happen on the worker, see [`load_gltf`](#method.load_gltf) for details on
+    /// the template and [`Loading`](struct.Loading.html) for getting the result.
+    #[cfg(feature = "gltf")]
+    pub fn load_gltf_async(
+        &mut self,
+        path_str: &str,
+    ) -> Loading<Template> {
+        let path = path_str.to_owned();
+        Loading::spawn(&mut self.workers, move || {
+            let data = Factory::read_gltf(&path)?;
+            let finish: Finish<_> = Box::new(move |factory: &mut Factory| factory.build_gltf(data));
+            Ok(finish)
+        })
+    }
+}
@@ -0,0 +1,815 @@
+//! Loading of block compressed textures from DDS and KTX2 files.
+
+use std::cmp;
+use std::path::Path;
+
+use Error;
+
+/// Identifier at the start of KTX2 files.
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+/// `caps2` flag of DDS cube maps.
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+/// `pixel_format.flags` flag of DDS files with a four character code.
+const DDPF_FOURCC: u32 = 0x4;
+/// `misc_flag` of DX10 DDS cube maps.
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Block compressed formats the DDS and KTX2 loaders understand.
+///
+/// Only `Bc1` without alpha and `Bc3` can be uploaded as they are,
+/// the others are decompressed on the CPU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BlockFormat {
+    /// BC1 (DXT1), with 1-bit alpha if `alpha` is set.
+    Bc1 { alpha: bool },
+    /// BC2 (DXT3), with explicit 4-bit alpha.
+    Bc2,
+    /// BC3 (DXT5), with interpolated alpha.
+    Bc3,
+    /// BC4, a single red channel.
+    Bc4,
+    /// BC5, red and green channels.
+    Bc5,
+    /// ETC2 RGB.
+    Etc2Rgb,
+    /// ETC2 RGB with EAC alpha.
+    Etc2Rgba,
+}
+
+impl BlockFormat {
+    fn block_size(&self) -> usize {
+        match *self {
+            BlockFormat::Bc1 { .. } | BlockFormat::Bc4 | BlockFormat::Etc2Rgb => 8,
+            BlockFormat::Bc2 | BlockFormat::Bc3 | BlockFormat::Bc5 | BlockFormat::Etc2Rgba => 16,
+        }
+    }
+
+    /// Size in bytes of a `width` by `height` image.
+    fn image_size(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> usize {
+        ((width as usize + 3) / 4) * ((height as usize + 3) / 4) * self.block_size()
+    }
+}
+
+/// Graphics driver support for uploading block compressed textures.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Support {
+    /// `GL_EXT_texture_compression_s3tc`, for BC1 and BC3.
+    pub s3tc: bool,
+    /// The sRGB variants of BC1 and BC3.
+    pub s3tc_srgb: bool,
+}
+
+/// Contents of a DDS or KTX2 file.
+#[derive(Clone, Debug)]
+pub(crate) struct CompressedImage {
+    pub format: BlockFormat,
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    /// `6` for cube maps, in the `+X`, `-X`, `+Y`, `-Y`, `+Z` and `-Z` order, `1` otherwise.
+    pub faces: usize,
+    pub levels: usize,
+    /// Image of each face and mip level, the faces first, rows from the top.
+    pub data: Vec<Vec<u8>>,
+}
+
+/// Returns `true` if `path` has the extension of a DDS or KTX2 file.
+pub(crate) fn is_compressed(path: &Path) -> bool {
+    match path.extension() {
+        Some(extension) => {
+            let extension = extension.to_string_lossy().to_lowercase();
+            extension == "dds" || extension == "ktx2"
+        }
+        None => false,
+    }
+}
+
+fn truncated() -> Error {
+    Error::Decode("unexpected end of file".into())
+}
+
+fn read_u32(
+    bytes: &[u8],
+    offset: usize,
+) -> Result<u32, Error> {
+    match bytes.get(offset .. offset + 4) {
+        Some(b) => Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24),
+        None => Err(truncated()),
+    }
+}
+
+fn read_u64(
+    bytes: &[u8],
+    offset: usize,
+) -> Result<u64, Error> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+
+fn level_dimension(
+    size: u32,
+    level: usize,
+) -> u32 {
+    cmp::max(size >> level, 1)
+}
+
+/// Parses a DDS or KTX2 file.
+pub(crate) fn parse(bytes: &[u8]) -> Result<CompressedImage, Error> {
+    if bytes.starts_with(b"DDS ") {
+        parse_dds(bytes)
+    } else if bytes.starts_with(&KTX2_IDENTIFIER) {
+        parse_ktx2(bytes)
+    } else {
+        Err(Error::Decode("not a DDS or KTX2 file".into()))
+    }
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<CompressedImage, Error> {
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let levels = cmp::max(read_u32(bytes, 28)?, 1) as usize;
+    let pixel_format_flags = read_u32(bytes, 80)?;
+    let caps2 = read_u32(bytes, 112)?;
+    if pixel_format_flags & DDPF_FOURCC == 0 {
+        return Err(Error::Unsupported("uncompressed DDS files".into()));
+    }
+
+    let mut faces = if caps2 & DDSCAPS2_CUBEMAP != 0 { 6 } else { 1 };
+    let mut offset = 128;
+    // Legacy formats don't tell the color space, assume sRGB like the other textures.
+    let (format, srgb) = match &bytes[84 .. 88] {
+        b"DXT1" => (BlockFormat::Bc1 { alpha: false }, true),
+        b"DXT2" | b"DXT3" => (BlockFormat::Bc2, true),
+        b"DXT4" | b"DXT5" => (BlockFormat::Bc3, true),
+        b"ATI1" | b"BC4U" => (BlockFormat::Bc4, false),
+        b"ATI2" | b"BC5U" => (BlockFormat::Bc5, false),
+        b"DX10" => {
+            let dxgi_format = read_u32(bytes, 128)?;
+            let misc_flag = read_u32(bytes, 136)?;
+            let array_size = read_u32(bytes, 140)?;
+            if array_size > 1 {
+                return Err(Error::Unsupported("DDS texture arrays".into()));
+            }
+            if misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0 {
+                faces = 6;
+            }
+            offset = 148;
+            match dxgi_format {
+                71 => (BlockFormat::Bc1 { alpha: false }, false),
+                72 => (BlockFormat::Bc1 { alpha: false }, true),
+                74 => (BlockFormat::Bc2, false),
+                75 => (BlockFormat::Bc2, true),
+                77 => (BlockFormat::Bc3, false),
+                78 => (BlockFormat::Bc3, true),
+                80 => (BlockFormat::Bc4, false),
+                83 => (BlockFormat::Bc5, false),
+                other => return Err(Error::Unsupported(format!("DXGI format {}", other))),
+            }
+        }
+        other => {
+            return Err(Error::Unsupported(format!(
+                "DDS format {}",
+                String::from_utf8_lossy(other)
+            )))
+        }
+    };
+
+    let mut data = Vec::with_capacity(faces * levels);
+    for _ in 0 .. faces {
+        for level in 0 .. levels {
+            let size = format.image_size(level_dimension(width, level), level_dimension(height, level));
+            let image = bytes
+                .get(offset .. offset + size)
+                .ok_or_else(truncated)?;
+            data.push(image.to_vec());
+            offset += size;
+        }
+    }
+
+    Ok(CompressedImage {
+        format,
+        srgb,
+        width,
+        height,
+        faces,
+        levels,
+        data,
+    })
+}
+
+fn parse_ktx2(bytes: &[u8]) -> Result<CompressedImage, Error> {
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layers = read_u32(bytes, 32)?;
+    let faces = read_u32(bytes, 36)? as usize;
+    let levels = cmp::max(read_u32(bytes, 40)?, 1) as usize;
+    let supercompression = read_u32(bytes, 44)?;
+    if supercompression != 0 {
+        return Err(Error::Unsupported("supercompressed KTX2 files".into()));
+    }
+    if depth > 1 || layers > 1 {
+        return Err(Error::Unsupported("3D textures and texture arrays".into()));
+    }
+    if faces != 1 && faces != 6 {
+        return Err(Error::Decode(format!("invalid number of faces {}", faces)));
+    }
+
+    let (format, srgb) = match vk_format {
+        131 => (BlockFormat::Bc1 { alpha: false }, false),
+        132 => (BlockFormat::Bc1 { alpha: false }, true),
+        133 => (BlockFormat::Bc1 { alpha: true }, false),
+        134 => (BlockFormat::Bc1 { alpha: true }, true),
+        135 => (BlockFormat::Bc2, false),
+        136 => (BlockFormat::Bc2, true),
+        137 => (BlockFormat::Bc3, false),
+        138 => (BlockFormat::Bc3, true),
+        139 => (BlockFormat::Bc4, false),
+        141 => (BlockFormat::Bc5, false),
+        147 => (BlockFormat::Etc2Rgb, false),
+        148 => (BlockFormat::Etc2Rgb, true),
+        151 => (BlockFormat::Etc2Rgba, false),
+        152 => (BlockFormat::Etc2Rgba, true),
+        other => return Err(Error::Unsupported(format!("Vulkan format {}", other))),
+    };
+
+    // Each level holds all of its faces, and the index lists the levels from the largest.
+    let mut data = vec![Vec::new(); faces * levels];
+    for level in 0 .. levels {
+        let offset = read_u64(bytes, 80 + level * 24)? as usize;
+        let size = format.image_size(level_dimension(width, level), level_dimension(height, level));
+        for face in 0 .. faces {
+            let start = offset + face * size;
+            let image = bytes
+                .get(start .. start + size)
+                .ok_or_else(truncated)?;
+            data[face * levels + level] = image.to_vec();
+        }
+    }
+
+    Ok(CompressedImage {
+        format,
+        srgb,
+        width,
+        height,
+        faces,
+        levels,
+        data,
+    })
+}
+
+impl CompressedImage {
+    /// Width and height of a mip level.
+    pub fn level_size(
+        &self,
+        level: usize,
+    ) -> (u32, u32) {
+        (level_dimension(self.width, level), level_dimension(self.height, level))
+    }
+
+    /// Flips the images vertically without decompressing them.
+    ///
+    /// Returns `false` and leaves the images untouched if the format or size doesn't allow it,
+    /// which happens with ETC2 and with levels taller than one block but not made of whole blocks.
+    pub fn flip_blocks(&mut self) -> bool {
+        match self.format {
+            BlockFormat::Etc2Rgb | BlockFormat::Etc2Rgba => return false,
+            _ => {}
+        }
+        let sizes = (0 .. self.levels).map(|level| self.level_size(level)).collect::<Vec<_>>();
+        if sizes.iter().any(|&(_, height)| height > 4 && height % 4 != 0) {
+            return false;
+        }
+
+        let block_size = self.format.block_size();
+        for (index, image) in self.data.iter_mut().enumerate() {
+            let (width, height) = sizes[index % self.levels];
+            let row_size = (width as usize + 3) / 4 * block_size;
+            let rows = (height as usize + 3) / 4;
+            for y in 0 .. rows / 2 {
+                for x in 0 .. row_size {
+                    image.swap(y * row_size + x, (rows - 1 - y) * row_size + x);
+                }
+            }
+            let texel_rows = cmp::min(height as usize, 4);
+            for block in image.chunks_mut(block_size) {
+                flip_block(self.format, block, texel_rows);
+            }
+        }
+        true
+    }
+
+    /// Decompresses the images to RGBA8.
+    pub fn decompress(&self) -> Vec<Vec<u8>> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                let (width, height) = self.level_size(index % self.levels);
+                decompress(self.format, width, height, image)
+            })
+            .collect()
+    }
+}
+
+/// Reverses the first `rows` texel rows of a block.
+fn flip_block(
+    format: BlockFormat,
+    block: &mut [u8],
+    rows: usize,
+) {
+    match format {
+        BlockFormat::Bc1 { .. } => flip_color_block(block, rows),
+        BlockFormat::Bc2 => {
+            for y in 0 .. rows / 2 {
+                block.swap(y * 2, (rows - 1 - y) * 2);
+                block.swap(y * 2 + 1, (rows - 1 - y) * 2 + 1);
+            }
+            flip_color_block(&mut block[8 ..], rows);
+        }
+        BlockFormat::Bc3 => {
+            flip_alpha_block(block, rows);
+            flip_color_block(&mut block[8 ..], rows);
+        }
+        BlockFormat::Bc4 => flip_alpha_block(block, rows),
+        BlockFormat::Bc5 => {
+            flip_alpha_block(block, rows);
+            flip_alpha_block(&mut block[8 ..], rows);
+        }
+        BlockFormat::Etc2Rgb | BlockFormat::Etc2Rgba => unreachable!(),
+    }
+}
+
+/// BC1 colors, with a byte of 2-bit indices per row.
+fn flip_color_block(
+    block: &mut [u8],
+    rows: usize,
+) {
+    block[4 .. 4 + rows].reverse();
+}
+
+/// BC3 and BC4 channels, with 12 bits of 3-bit indices per row.
+fn flip_alpha_block(
+    block: &mut [u8],
+    rows: usize,
+) {
+    let mut indices = 0u64;
+    for i in 0 .. 6 {
+        indices |= (block[2 + i] as u64) << (8 * i);
+    }
+    let mut flipped = indices;
+    for y in 0 .. rows {
+        let row = (indices >> (12 * y)) & 0xFFF;
+        let target = 12 * (rows - 1 - y);
+        flipped = (flipped & !(0xFFF << target)) | (row << target);
+    }
+    for i in 0 .. 6 {
+        block[2 + i] = (flipped >> (8 * i)) as u8;
+    }
+}
+
+/// Decompresses a `width` by `height` image to RGBA8.
+fn decompress(
+    format: BlockFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let blocks_x = (width + 3) / 4;
+    let mut pixels = vec![0; width * height * 4];
+    for (index, block) in data.chunks(format.block_size()).enumerate() {
+        let texels = match format {
+            BlockFormat::Bc1 { alpha } => decode_colors(block, false, alpha),
+            BlockFormat::Bc2 => {
+                let mut texels = decode_colors(&block[8 ..], true, false);
+                for (i, texel) in texels.iter_mut().enumerate() {
+                    let value = (block[i / 2] >> (4 * (i % 2))) & 0xF;
+                    texel[3] = value * 17;
+                }
+                texels
+            }
+            BlockFormat::Bc3 => {
+                let mut texels = decode_colors(&block[8 ..], true, false);
+                let alpha = decode_channel(block);
+                for (texel, &a) in texels.iter_mut().zip(alpha.iter()) {
+                    texel[3] = a;
+                }
+                texels
+            }
+            BlockFormat::Bc4 => {
+                let mut texels = [[0, 0, 0, 0xFF]; 16];
+                for (texel, &r) in texels.iter_mut().zip(decode_channel(block).iter()) {
+                    texel[0] = r;
+                }
+                texels
+            }
+            BlockFormat::Bc5 => {
+                let mut texels = [[0, 0, 0, 0xFF]; 16];
+                let red = decode_channel(block);
+                let green = decode_channel(&block[8 ..]);
+                for (i, texel) in texels.iter_mut().enumerate() {
+                    texel[0] = red[i];
+                    texel[1] = green[i];
+                }
+                texels
+            }
+            BlockFormat::Etc2Rgb => decode_etc2(block),
+            BlockFormat::Etc2Rgba => {
+                let mut texels = decode_etc2(&block[8 ..]);
+                let alpha = decode_eac(block);
+                for (texel, &a) in texels.iter_mut().zip(alpha.iter()) {
+                    texel[3] = a;
+                }
+                texels
+            }
+        };
+
+        let (bx, by) = (index % blocks_x * 4, index / blocks_x * 4);
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                if bx + x < width && by + y < height {
+                    let offset = ((by + y) * width + bx + x) * 4;
+                    pixels[offset .. offset + 4].copy_from_slice(&texels[y * 4 + x]);
+                }
+            }
+        }
+    }
+    pixels
+}
+
+fn rgb565(color: u16) -> [i32; 3] {
+    let r = (color >> 11) as i32 & 0x1F;
+    let g = (color >> 5) as i32 & 0x3F;
+    let b = color as i32 & 0x1F;
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// Decodes a BC1 color block into texels in row order.
+///
+/// BC2 and BC3 always use four colors, BC1 switches to three colors and black,
+/// transparent if `alpha` is set, when the first endpoint isn't the larger one.
+fn decode_colors(
+    block: &[u8],
+    four_colors: bool,
+    alpha: bool,
+) -> [[u8; 4]; 16] {
+    let c0 = block[0] as u16 | (block[1] as u16) << 8;
+    let c1 = block[2] as u16 | (block[3] as u16) << 8;
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let mix = |a: i32, b: i32, den: i32| {
+        let mut color = [0xFF; 4];
+        for i in 0 .. 3 {
+            color[i] = ((a * e0[i] + b * e1[i]) / den) as u8;
+        }
+        color
+    };
+    let palette = if four_colors || c0 > c1 {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0, 0, 0, if alpha { 0 } else { 0xFF }]]
+    };
+
+    let indices = block[4] as u32 | (block[5] as u32) << 8 | (block[6] as u32) << 16 | (block[7] as u32) << 24;
+    let mut texels = [[0; 4]; 16];
+    for (i, texel) in texels.iter_mut().enumerate() {
+        *texel = palette[(indices >> (2 * i)) as usize & 3];
+    }
+    texels
+}
+
+/// Decodes a BC3 alpha or BC4 channel block into values in row order.
+fn decode_channel(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0xFF];
+    if a0 > a1 {
+        for i in 1 .. 7 {
+            palette[i + 1] = ((7 - i as u32) * a0 + i as u32 * a1) / 7;
+        }
+    } else {
+        for i in 1 .. 5 {
+            palette[i + 1] = ((5 - i as u32) * a0 + i as u32 * a1) / 5;
+        }
+    }
+
+    let mut indices = 0u64;
+    for i in 0 .. 6 {
+        indices |= (block[2 + i] as u64) << (8 * i);
+    }
+    let mut values = [0; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[(indices >> (3 * i)) as usize & 7] as u8;
+    }
+    values
+}
+
+fn read_be_u64(block: &[u8]) -> u64 {
+    block[.. 8].iter().fold(0, |bits, &byte| bits << 8 | byte as u64)
+}
+
+fn clamp_color(value: i32) -> u8 {
+    cmp::max(0, cmp::min(value, 0xFF)) as u8
+}
+
+fn extend4(value: u64) -> i32 {
+    (value as i32 & 0xF) * 17
+}
+
+fn extend5(value: i32) -> i32 {
+    value << 3 | value >> 2
+}
+
+/// Decodes an ETC2 RGB block into texels in row order.
+fn decode_etc2(block: &[u8]) -> [[u8; 4]; 16] {
+    const MODIFIERS: [[i32; 2]; 8] = [[2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183]];
+    const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+    let bits = read_be_u64(block);
+    let bit = |i: u32| (bits >> i) & 1;
+    let field = |high: u32, count: u32| (bits >> (high + 1 - count)) & ((1 << count) - 1);
+    // Indices are stored by column, with the most significant bits first.
+    let index = |x: usize, y: usize| {
+        let i = (x * 4 + y) as u32;
+        (bit(16 + i) << 1 | bit(i)) as usize
+    };
+
+    let mut texels = [[0, 0, 0, 0xFF]; 16];
+    let paint = |texels: &mut [[u8; 4]; 16], colors: [[i32; 3]; 4]| {
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                let color = colors[index(x, y)];
+                texels[y * 4 + x] = [clamp_color(color[0]), clamp_color(color[1]), clamp_color(color[2]), 0xFF];
+            }
+        }
+    };
+    let offset = |color: [i32; 3], d: i32| [color[0] + d, color[1] + d, color[2] + d];
+
+    let differential = bit(33) == 1;
+    let base = [field(63, 5) as i32, field(55, 5) as i32, field(47, 5) as i32];
+    let delta = |high: u32| {
+        let value = field(high, 3) as i32;
+        if value >= 4 { value - 8 } else { value }
+    };
+    let second = [base[0] + delta(58), base[1] + delta(50), base[2] + delta(42)];
+
+    if differential && (second[0] < 0 || second[0] > 31) {
+        // T mode
+        let c1 = [
+            extend4(field(60, 2) << 2 | field(57, 2)),
+            extend4(field(55, 4)),
+            extend4(field(51, 4)),
+        ];
+        let c2 = [extend4(field(47, 4)), extend4(field(43, 4)), extend4(field(39, 4))];
+        let d = DISTANCES[(field(35, 2) << 1 | bit(32)) as usize];
+        paint(&mut texels, [c1, offset(c2, d), c2, offset(c2, -d)]);
+    } else if differential && (second[1] < 0 || second[1] > 31) {
+        // H mode
+        let r1 = field(62, 4);
+        let g1 = field(58, 3) << 1 | bit(52);
+        let b1 = bit(51) << 3 | field(49, 3);
+        let (r2, g2, b2) = (field(46, 4), field(42, 4), field(38, 4));
+        let ordered = (r1 << 8 | g1 << 4 | b1) >= (r2 << 8 | g2 << 4 | b2);
+        let d = DISTANCES[(bit(34) << 2 | bit(32) << 1 | ordered as u64) as usize];
+        let c1 = [extend4(r1), extend4(g1), extend4(b1)];
+        let c2 = [extend4(r2), extend4(g2), extend4(b2)];
+        paint(&mut texels, [offset(c1, d), offset(c1, -d), offset(c2, d), offset(c2, -d)]);
+    } else if differential && (second[2] < 0 || second[2] > 31) {
+        // planar mode
+        let extend6 = |v: u64| (v as i32) << 2 | (v as i32) >> 4;
+        let extend7 = |v: u64| (v as i32) << 1 | (v as i32) >> 6;
+        let o = [
+            extend6(field(62, 6)),
+            extend7(bit(56) << 6 | field(54, 6)),
+            extend6(bit(48) << 5 | field(44, 2) << 3 | field(41, 3)),
+        ];
+        let h = [
+            extend6(field(38, 5) << 1 | bit(32)),
+            extend7(field(31, 7)),
+            extend6(field(24, 6)),
+        ];
+        let v = [extend6(field(18, 6)), extend7(field(12, 7)), extend6(field(5, 6))];
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                let mut texel = [0xFF; 4];
+                for c in 0 .. 3 {
+                    let value = x as i32 * (h[c] - o[c]) + y as i32 * (v[c] - o[c]) + 4 * o[c] + 2;
+                    texel[c] = clamp_color(value >> 2);
+                }
+                texels[y * 4 + x] = texel;
+            }
+        }
+    } else {
+        // individual or differential mode, with two sub-blocks
+        let (c1, c2) = if differential {
+            (
+                [extend5(base[0]), extend5(base[1]), extend5(base[2])],
+                [extend5(second[0]), extend5(second[1]), extend5(second[2])],
+            )
+        } else {
+            (
+                [extend4(field(63, 4)), extend4(field(55, 4)), extend4(field(47, 4))],
+                [extend4(field(59, 4)), extend4(field(51, 4)), extend4(field(43, 4))],
+            )
+        };
+        let tables = [MODIFIERS[field(39, 3) as usize], MODIFIERS[field(36, 3) as usize]];
+        let flipped = bit(32) == 1;
+        for y in 0 .. 4 {
+            for x in 0 .. 4 {
+                let second_block = if flipped { y >= 2 } else { x >= 2 };
+                let (color, table) = if second_block { (c2, tables[1]) } else { (c1, tables[0]) };
+                let modifier = match index(x, y) {
+                    0 => table[0],
+                    1 => table[1],
+                    2 => -table[0],
+                    _ => -table[1],
+                };
+                texels[y * 4 + x] = [
+                    clamp_color(color[0] + modifier),
+                    clamp_color(color[1] + modifier),
+                    clamp_color(color[2] + modifier),
+                    0xFF,
+                ];
+            }
+        }
+    }
+    texels
+}
+
+/// Decodes an EAC alpha block into values in row order.
+fn decode_eac(block: &[u8]) -> [u8; 16] {
+    const TABLES: [[i32; 8]; 16] = [
+        [-3, -6, -9, -15, 2, 5, 8, 14],
+        [-3, -7, -10, -13, 2, 6, 9, 12],
+        [-2, -5, -8, -13, 1, 4, 7, 12],
+        [-2, -4, -6, -13, 1, 3, 5, 12],
+        [-3, -6, -8, -12, 2, 5, 7, 11],
+        [-3, -7, -9, -11, 2, 6, 8, 10],
+        [-4, -7, -8, -11, 3, 6, 7, 10],
+        [-3, -5, -8, -11, 2, 4, 7, 10],
+        [-2, -6, -8, -10, 1, 5, 7, 9],
+        [-2, -5, -8, -10, 1, 4, 7, 9],
+        [-2, -4, -8, -10, 1, 3, 7, 9],
+        [-2, -5, -7, -10, 1, 4, 6, 9],
+        [-3, -4, -7, -10, 2, 3, 6, 9],
+        [-1, -2, -3, -10, 0, 1, 2, 9],
+        [-4, -6, -8, -9, 3, 5, 7, 8],
+        [-3, -5, -7, -9, 2, 4, 6, 8],
+    ];
+
+    let bits = read_be_u64(block);
+    let base = (bits >> 56) as i32;
+    let multiplier = (bits >> 52) as i32 & 0xF;
+    let table = TABLES[(bits >> 48) as usize & 0xF];
+    let mut values = [0; 16];
+    for x in 0 .. 4 {
+        for y in 0 .. 4 {
+            // Indices are stored by column, from the most significant bits.
+            let i = x * 4 + y;
+            let index = (bits >> (45 - 3 * i)) as usize & 7;
+            values[y * 4 + x] = clamp_color(base + table[index] * multiplier);
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs a BC1 color block.
+    fn color_block(
+        c0: u16,
+        c1: u16,
+        indices: &[u8; 16],
+    ) -> Vec<u8> {
+        let bits = indices.iter().rev().fold(0u32, |bits, &i| bits << 2 | i as u32);
+        vec![c0 as u8, (c0 >> 8) as u8, c1 as u8, (c1 >> 8) as u8, bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8]
+    }
+
+    /// Packs a BC3 alpha or BC4 channel block.
+    fn channel_block(
+        a0: u8,
+        a1: u8,
+        indices: &[u8; 16],
+    ) -> Vec<u8> {
+        let bits = indices.iter().rev().fold(0u64, |bits, &i| bits << 3 | i as u64);
+        let mut block = vec![a0, a1];
+        block.extend((0 .. 6).map(|i| (bits >> (8 * i)) as u8));
+        block
+    }
+
+    fn texel(
+        pixels: &[u8],
+        x: usize,
+        y: usize,
+    ) -> [u8; 4] {
+        let offset = (y * 4 + x) * 4;
+        [pixels[offset], pixels[offset + 1], pixels[offset + 2], pixels[offset + 3]]
+    }
+
+    #[test]
+    fn bc1_to_bc5_round_trip() {
+        let indices = [0, 1, 2, 3, 3, 2, 1, 0, 0, 0, 1, 1, 2, 2, 3, 3];
+        // red and blue endpoints, which 5:6:5 stores exactly
+        let palette = [[255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255], [85, 0, 170, 255]];
+        let colors = color_block(0xF800, 0x001F, &indices);
+        let bc1 = decompress(BlockFormat::Bc1 { alpha: false }, 4, 4, &colors);
+        for (i, &index) in indices.iter().enumerate() {
+            assert_eq!(texel(&bc1, i % 4, i / 4), palette[index as usize]);
+        }
+
+        let channel_indices = [0, 1, 2, 3, 4, 5, 6, 7, 7, 6, 5, 4, 3, 2, 1, 0];
+        let channel_palette = [255, 0, 218, 182, 145, 109, 72, 36];
+        // swapped endpoints select six values, then 0 and 255
+        let green_palette = [0, 255, 51, 102, 153, 204, 0, 255];
+        let channel = channel_block(255, 0, &channel_indices);
+        let bc4 = decompress(BlockFormat::Bc4, 4, 4, &channel);
+        let mut bc5_data = channel.clone();
+        bc5_data.extend(channel_block(0, 255, &channel_indices));
+        let bc5 = decompress(BlockFormat::Bc5, 4, 4, &bc5_data);
+        let mut bc3_data = channel.clone();
+        bc3_data.extend(&colors);
+        let bc3 = decompress(BlockFormat::Bc3, 4, 4, &bc3_data);
+        let mut bc2_data = (0 .. 8).map(|i| (2 * i + 1) << 4 | 2 * i).collect::<Vec<u8>>();
+        bc2_data.extend(&colors);
+        let bc2 = decompress(BlockFormat::Bc2, 4, 4, &bc2_data);
+        for i in 0 .. 16 {
+            let (x, y) = (i % 4, i / 4);
+            let index = channel_indices[i] as usize;
+            let alpha = channel_palette[index];
+            assert_eq!(texel(&bc4, x, y), [alpha, 0, 0, 255]);
+            assert_eq!(texel(&bc5, x, y), [alpha, green_palette[index], 0, 255]);
+            let color = palette[indices[i] as usize];
+            assert_eq!(texel(&bc3, x, y), [color[0], color[1], color[2], alpha]);
+            assert_eq!(texel(&bc2, x, y), [color[0], color[1], color[2], i as u8 * 17]);
+        }
+    }
+
+    #[test]
+    fn flipped_blocks_match_flipped_pixels() {
+        let formats = [BlockFormat::Bc1 { alpha: true }, BlockFormat::Bc2, BlockFormat::Bc3, BlockFormat::Bc4, BlockFormat::Bc5];
+        for &format in &formats {
+            for &(width, height) in &[(8, 8), (4, 2), (2, 1)] {
+                let mut seed = 0x1234_5678u32;
+                let data = (0 .. format.image_size(width, height))
+                    .map(|_| {
+                        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                        (seed >> 16) as u8
+                    })
+                    .collect::<Vec<_>>();
+                let mut image = CompressedImage {
+                    format,
+                    srgb: false,
+                    width,
+                    height,
+                    faces: 1,
+                    levels: 1,
+                    data: vec![data],
+                };
+                let expected = image.decompress()[0]
+                    .chunks(width as usize * 4)
+                    .rev()
+                    .flat_map(|row| row.iter().cloned())
+                    .collect::<Vec<_>>();
+                assert!(image.flip_blocks());
+                assert_eq!(image.decompress()[0], expected, "{:?} {}x{}", format, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn etc2_differential_with_eac_alpha() {
+        // base color 16 in all channels, no delta, the first modifier table, differential bit
+        let mut color = 16u64 << 59 | 16 << 51 | 16 << 43 | 1 << 33;
+        // index 1 for the texel at (1, 0), index 3 for the one at (3, 3)
+        color |= 1 << 4;
+        color |= 1 << 15 | 1 << 31;
+        // base 128, multiplier 1, the first table, index 4 everywhere but 7 at (0, 1)
+        let mut alpha = 128u64 << 56 | 1 << 52;
+        for i in 0 .. 16 {
+            let index = if i == 1 { 7 } else { 4 };
+            alpha |= index << (45 - 3 * i);
+        }
+        let mut block = alpha.to_be_bytes().to_vec();
+        block.extend(&color.to_be_bytes());
+
+        let pixels = decompress(BlockFormat::Etc2Rgba, 4, 4, &block);
+        // 16 extended from 5 bits is 132
+        assert_eq!(texel(&pixels, 0, 0), [134, 134, 134, 130]);
+        assert_eq!(texel(&pixels, 1, 0), [140, 140, 140, 130]);
+        assert_eq!(texel(&pixels, 3, 3), [124, 124, 124, 130]);
+        assert_eq!(texel(&pixels, 0, 1), [134, 134, 134, 142]);
+        assert_eq!(decompress(BlockFormat::Etc2Rgb, 4, 4, &block[8 ..]), {
+            let mut rgb = pixels.clone();
+            for texel in rgb.chunks_mut(4) {
+                texel[3] = 255;
+            }
+            rgb
+        });
+    }
+}
@@ -11,16 +11,16 @@ use geometry;
 use gltf;
 use material;
 use mint;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use camera::{Orthographic, Perspective, Projection};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use {Material, Texture};
+use {Error, Material, Texture};
 use geometry::{Geometry, Shape};
 use image::{DynamicImage, ImageBuffer};
 use node::Transform;
-use super::Factory;
+use super::{rgba16_to_float, Factory};
 use template::{
     AnimationTemplate,
     BoneTemplate,
@@ -28,14 +28,42 @@ use template::{
     InstancedGeometry,
     MeshTemplate,
     ObjectTemplate,
+    SceneTemplate,
     Template,
 };
 
+/// Contents of a glTF file, imported on the CPU.
+pub(crate) struct GltfData {
+    path: PathBuf,
+    document: gltf::Document,
+    buffers: Vec<gltf::buffer::Data>,
+    images: Vec<gltf::image::Data>,
+    /// Geometry of each primitive, grouped by mesh.
+    geometries: Vec<Vec<Geometry>>,
+}
+
+/// Reads 16 bit channels stored in native byte order.
+fn to_u16(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks(2)
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
+
 fn load_textures(
     factory: &mut Factory,
     document: &gltf::Document,
     images: Vec<gltf::image::Data>,
-) -> Vec<Texture<[f32; 4]>> {
+) -> Result<Vec<Texture<[f32; 4]>>, Error> {
+    // Only the base color and emissive maps hold sRGB colors, the others linear data.
+    let srgb_textures = document
+        .materials()
+        .flat_map(|mat| {
+            let base_color = mat.pbr_metallic_roughness().base_color_texture().map(|t| t.as_ref().index());
+            let emissive = mat.emissive_texture().map(|t| t.as_ref().index());
+            base_color.into_iter().chain(emissive)
+        })
+        .collect::<HashSet<_>>();
     let mut textures = Vec::new();
     for (texture, data) in document.textures().zip(images.into_iter()) {
         let (width, height) = (data.width, data.height);
@@ -45,54 +73,86 @@ fn load_textures(
                     width,
                     height,
                     data.pixels,
-                ).expect("incorrect image dimensions")
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
+            ),
+            gltf::image::Format::R16 => DynamicImage::ImageLuma16(
+                ImageBuffer::from_raw(
+                    width,
+                    height,
+                    to_u16(&data.pixels),
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
+            ),
+            gltf::image::Format::R16G16 => DynamicImage::ImageLumaA16(
+                ImageBuffer::from_raw(
+                    width,
+                    height,
+                    to_u16(&data.pixels),
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
+            ),
+            gltf::image::Format::R16G16B16 => DynamicImage::ImageRgb16(
+                ImageBuffer::from_raw(
+                    width,
+                    height,
+                    to_u16(&data.pixels),
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
+            ),
+            gltf::image::Format::R16G16B16A16 => DynamicImage::ImageRgba16(
+                ImageBuffer::from_raw(
+                    width,
+                    height,
+                    to_u16(&data.pixels),
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
             ),
-            gltf::image::Format::R16 | gltf::image::Format::R16G16 | gltf::image::Format::R16G16B16
-                | gltf::image::Format::R16G16B16A16 => panic!("16 bit images are unsupported"), 
             gltf::image::Format::R8G8 => DynamicImage::ImageLumaA8(
                 ImageBuffer::from_raw(
                     width,
                     height,
                     data.pixels,
-                ).expect("incorrect image dimensions")
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
             ),
             gltf::image::Format::R8G8B8 => DynamicImage::ImageRgb8(
                 ImageBuffer::from_raw(
                     width,
                     height,
                     data.pixels,
-                ).expect("incorrect image dimensions")
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
             ),
             gltf::image::Format::R8G8B8A8 => DynamicImage::ImageRgba8(
                 ImageBuffer::from_raw(
                     width,
                     height,
                     data.pixels,
-                ).unwrap()
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
             ),
             gltf::image::Format::B8G8R8 => DynamicImage::ImageBgr8(
                 ImageBuffer::from_raw(
                     width,
                     height,
                     data.pixels,
-                ).unwrap()
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
             ),
             gltf::image::Format::B8G8R8A8 => DynamicImage::ImageBgra8(
                 ImageBuffer::from_raw(
                     width,
                     height,
                     data.pixels,
-                ).unwrap()
+                ).ok_or_else(|| Error::Decode("incorrect image dimensions".into()))?
             ),
-        }.to_rgba();
+        };
         use {FilterMethod, WrapMode};
-        use gltf::texture::{MagFilter, WrappingMode};
+        use gltf::texture::{MagFilter, MinFilter, WrappingMode};
         let params = texture.sampler();
-        // gfx does not support separate min / mag
-        // filters yet, so for now we'll use `mag_filter` for both.
-        let mag_filter = match params.mag_filter() {
-            None | Some(MagFilter::Nearest) => FilterMethod::Scale,
-            Some(MagFilter::Linear) => FilterMethod::Bilinear,
+        // gfx does not support separate min / mag filters yet, so for now
+        // we'll use `min_filter` for both when it uses mipmaps, otherwise `mag_filter`.
+        let filter = match (params.min_filter(), params.mag_filter()) {
+            (Some(MinFilter::LinearMipmapLinear), _) | (Some(MinFilter::NearestMipmapLinear), _) => {
+                FilterMethod::Trilinear
+            }
+            (Some(MinFilter::LinearMipmapNearest), _) | (Some(MinFilter::NearestMipmapNearest), _) => {
+                FilterMethod::Mipmap
+            }
+            (_, None) | (_, Some(MagFilter::Nearest)) => FilterMethod::Scale,
+            (_, Some(MagFilter::Linear)) => FilterMethod::Bilinear,
         };
         let wrap_s = match params.wrap_s() {
             WrappingMode::ClampToEdge => WrapMode::Clamp,
@@ -104,11 +164,24 @@ fn load_textures(
             WrappingMode::MirroredRepeat => WrapMode::Mirror,
             WrappingMode::Repeat => WrapMode::Tile,
         };
-        let sampler = factory.sampler(mag_filter, wrap_s, wrap_t);
-        let texture = factory.load_texture_from_memory(width as u16, height as u16, &image, sampler);
+        let sampler = factory.sampler(filter, wrap_s, wrap_t);
+        let texture = match image {
+            DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_)
+                | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => {
+                // Keep the precision of 16 bit images in a floating point texture.
+                let srgb = srgb_textures.contains(&texture.index());
+                let pixels = image
+                    .to_rgba16()
+                    .pixels()
+                    .map(|p| rgba16_to_float(p.0, srgb))
+                    .collect::<Vec<_>>();
+                factory.try_load_float_texture_from_memory(width as u16, height as u16, &pixels, sampler)?
+            }
+            _ => factory.try_load_texture_from_memory(width as u16, height as u16, &image.to_rgba8(), sampler)?,
+        };
         textures.push(texture);
     }
-    textures
+    Ok(textures)
 }
 
 fn load_material<'a>(
@@ -144,6 +217,7 @@ fn load_material<'a>(
         material::Basic {
             color: base_color_factor,
             map: base_color_map,
+            .. Default::default()
         }.into()
     } else {
         material::Pbr {
@@ -163,16 +237,15 @@ fn load_material<'a>(
             emissive_map,
             metallic_roughness_map,
             occlusion_map,
+            transparent: mat.alpha_mode() == gltf::material::AlphaMode::Blend,
         }.into()
     }
 }
 
-fn load_primitive<'a>(
-    factory: &mut Factory,
+fn read_primitive<'a>(
     primitive: gltf::Primitive<'a>,
     buffers: &[gltf::buffer::Data],
-    textures: &[Texture<[f32; 4]>],
-) -> (InstancedGeometry, Material) {
+) -> Result<Geometry, Error> {
     use itertools::Itertools;
 
     let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()].0));
@@ -183,7 +256,7 @@ fn load_primitive<'a>(
     }
     let vertices: Vec<mint::Point3<f32>> = reader
         .read_positions()
-        .unwrap()
+        .ok_or_else(|| Error::Decode("mesh primitive without positions".into()))?
         .map(|x| x.into())
         .collect();
     let normals = if let Some(iter) = reader.read_normals() {
@@ -231,7 +304,7 @@ fn load_primitive<'a>(
             })
             .collect()
     };
-    let geometry = Geometry {
+    Ok(Geometry {
         base: Shape {
             vertices,
             normals,
@@ -244,11 +317,20 @@ fn load_primitive<'a>(
             indices: joint_indices,
             weights: joint_weights,
         },
-    };
+    })
+}
 
-    let geometry = factory.upload_geometry(geometry);
+fn load_primitive<'a>(
+    factory: &mut Factory,
+    primitive: gltf::Primitive<'a>,
+    geometry: Geometry,
+    textures: &[Texture<[f32; 4]>],
+) -> Result<(InstancedGeometry, Material), Error> {
+    let geometry = InstancedGeometry {
+        gpu_data: factory.create_gpu_data(geometry)?,
+    };
     let material = load_material(primitive.material(), textures);
-    (geometry, material)
+    Ok((geometry, material))
 }
 
 /// Creates bone and skeleton templates from a glTF skin.
@@ -319,7 +401,7 @@ fn load_animation<'a>(
     animation: gltf::Animation<'a>,
     buffers: &[gltf::buffer::Data],
     groups: &[usize],
-) -> AnimationTemplate {
+) -> Result<AnimationTemplate, Error> {
     use gltf::animation::Interpolation::*;
 
     let mut tracks = Vec::new();
@@ -336,13 +418,21 @@ fn load_animation<'a>(
         };
         use animation::{Binding, Track, Values};
         let reader = channel.reader(|buffer| Some(&buffers[buffer.index()].0));
-        let times: Vec<f32> = reader.read_inputs().unwrap().collect();
-        let (binding, values) = match reader.read_outputs().unwrap() {
+        let times: Vec<f32> = reader
+            .read_inputs()
+            .ok_or_else(|| Error::Decode("animation sampler without inputs".into()))?
+            .collect();
+        let outputs = reader
+            .read_outputs()
+            .ok_or_else(|| Error::Decode("animation sampler without outputs".into()))?;
+        let (binding, values) = match outputs {
             gltf::animation::util::ReadOutputs::Translations(iter) => {
                 let values = iter
                     .map(|v| mint::Vector3::from(v))
                     .collect::<Vec<_>>();
-                assert_eq!(values.len(), times.len());
+                if values.len() != times.len() {
+                    return Err(Error::Decode("animation outputs don't match its inputs".into()));
+                }
                 (Binding::Position, Values::Vector3(values))
             }
             gltf::animation::util::ReadOutputs::Rotations(rotations) => {
@@ -350,24 +440,24 @@ fn load_animation<'a>(
                     .into_f32()
                     .map(|r| mint::Quaternion::from(r))
                     .collect::<Vec<_>>();
-                assert_eq!(values.len(), times.len());
+                if values.len() != times.len() {
+                    return Err(Error::Decode("animation outputs don't match its inputs".into()));
+                }
                 (Binding::Orientation, Values::Quaternion(values))
             }
             gltf::animation::util::ReadOutputs::Scales(iter) => {
-                // TODO: Groups do not handle non-uniform scaling, so for now
-                // we'll choose Y to be the scale factor in all directions.
-                let values = iter.map(|s| s[1]).collect::<Vec<_>>();
-                assert_eq!(values.len(), times.len());
-                (Binding::Scale, Values::Scalar(values))
+                let values = iter.map(|s| s.into()).collect::<Vec<_>>();
+                if values.len() != times.len() {
+                    return Err(Error::Decode("animation outputs don't match its inputs".into()));
+                }
+                (Binding::Scale, Values::Vector3(values))
             }
             gltf::animation::util::ReadOutputs::MorphTargetWeights(weights) => {
                 // Write all values for target[0] first, then all values for target[1], etc.
                 let num_targets = node
                     .mesh()
-                    .unwrap()
-                    .primitives()
-                    .next()
-                    .unwrap()
+                    .and_then(|mesh| mesh.primitives().next())
+                    .ok_or_else(|| Error::Decode("morph target weights animate a node without a mesh".into()))?
                     .morph_targets()
                     .len();
                 let mut values = vec![0.0; times.len() * num_targets];
@@ -393,10 +483,10 @@ fn load_animation<'a>(
         ));
     }
 
-    AnimationTemplate {
+    Ok(AnimationTemplate {
         name,
         tracks,
-    }
+    })
 }
 
 /// Partially loads a single glTF node and creates template nodes from its data.
@@ -432,10 +522,6 @@ fn load_node<'a>(
     // Decompose the transform to get the translation, rotation, and scale.
     let (translation, rotation, scale) = node.transform().decomposed();
 
-    // TODO: Groups do not handle non-uniform scaling, so for now we'll choose Y to be the
-    // scale factor in all directions.
-    let scale = scale[1];
-
     // Create a `Group` node to directly represent the original glTF node, listing any extra
     // nodes we needed to create as its children.
     let object_index = objects.len();
@@ -445,7 +531,7 @@ fn load_node<'a>(
         transform: Transform {
             position: translation.into(),
             orientation: rotation.into(),
-            scale,
+            scale: scale.into(),
         },
 
         // NOTE: Since glTF has parents list their children, and three-rs templates do the
@@ -513,27 +599,26 @@ fn load_camera<'a>(
     }
 }
 
-fn load_scene<'a>(scene: gltf::Scene<'a>, raw: &Template) -> Template {
-    // TODO: Create a new template that just contains the objects for the specified scene.
-
-    Template {
+fn load_scene<'a>(scene: gltf::Scene<'a>, groups: &[usize]) -> SceneTemplate {
+    SceneTemplate {
         name: scene.name().map(Into::into),
-        .. raw.clone()
+        roots: scene.nodes().map(|node| groups[node.index()]).collect(),
     }
 }
 
 impl super::Factory {
-    /// Loads templates from a glTF 2.0 file.
+    /// Loads a template from a glTF 2.0 file.
     ///
-    /// The returned [`Template`] objects cannot be added to the scene directly, rather they
-    /// contain definitions for meshes, node hierarchies, skinned meshes and their skeletons,
+    /// The returned [`Template`] cannot be added to the scene directly, rather it
+    /// contains definitions for meshes, node hierarchies, skinned meshes and their skeletons,
     /// animations, and other things that can be instantiated and added to the scene. Use
     /// [`Factory::instantiate_template`] to create an instance of the template that can be
     /// added to your scene. See the module documentation for [`template`] for more information
     /// on templates and how they are used.
     ///
-    /// Each scene in the glTF file results in a separate [`Template`]. Any animations that
-    /// reference nodes in a scene will be included in that scene's [`Template`].
+    /// Every scene in the glTF file is listed in [`Template::scenes`] with its name, use
+    /// [`Factory::instantiate_template_scene`] to create an instance of a single scene along
+    /// with the animations that reference its nodes. The template is named after the file.
     ///
     /// # Examples
     ///
@@ -541,11 +626,11 @@ impl super::Factory {
     /// use three::animation;
     ///
     /// # let mut window = three::Window::new("Three-rs");
-    /// // Load all templates from a glTF file.
-    /// let mut templates = window.factory.load_gltf("my-model.gltf");
+    /// // Load the template from a glTF file.
+    /// let template = window.factory.load_gltf("my-model.gltf");
     ///
-    /// // Instantiate the first template loaded and add the root group to the scene.
-    /// let (root, animations) = window.factory.instantiate_template(&templates[0]);
+    /// // Instantiate the template and add the root group to the scene.
+    /// let (root, animations) = window.factory.instantiate_template(&template);
     /// window.scene.add(&root);
     ///
     /// // Start playing all the animations instantiated from the template.
@@ -557,18 +642,66 @@ impl super::Factory {
     ///
     /// [`template`]: ./template/index.html
     /// [`Template`]: ./template/struct.Template.html
+    /// [`Template::scenes`]: ./template/struct.Template.html#structfield.scenes
     /// [`Factory::instantiate_template`]: #method.instantiate_template
+    /// [`Factory::instantiate_template_scene`]: #method.instantiate_template_scene
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file can't be loaded, see [`Factory::try_load_gltf`] to handle
+    /// the error instead.
+    ///
+    /// [`Factory::try_load_gltf`]: #method.try_load_gltf
     pub fn load_gltf(
         &mut self,
         path_str: &str,
-    ) -> Vec<Template> {
+    ) -> Template {
+        self.try_load_gltf(path_str)
+            .unwrap_or_else(|e| panic!("Unable to load {}: {}", path_str, e))
+    }
+
+    /// Loads a template from a glTF 2.0 file, returning an error if the file is malformed
+    /// or uses unsupported features.
+    ///
+    /// See [`Factory::load_gltf`] for details.
+    ///
+    /// [`Factory::load_gltf`]: #method.load_gltf
+    pub fn try_load_gltf(
+        &mut self,
+        path_str: &str,
+    ) -> Result<Template, Error> {
+        let data = Factory::read_gltf(path_str)?;
+        self.build_gltf(data)
+    }
+
+    /// Imports a glTF file and reads the geometry of every mesh primitive.
+    pub(crate) fn read_gltf(path_str: &str) -> Result<GltfData, Error> {
         info!("Loading glTF file {}", path_str);
 
         let path = Path::new(path_str);
-        let (gltf, buffers, images) = gltf::import(path)
-            .expect("invalid glTF 2.0");
+        let (document, buffers, images) = gltf::import(path)?;
+        let geometries = document
+            .meshes()
+            .map(|mesh| {
+                mesh.primitives()
+                    .map(|prim| read_primitive(prim, &buffers))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(GltfData {
+            path: path.to_owned(),
+            document,
+            buffers,
+            images,
+            geometries,
+        })
+    }
 
-        let textures = load_textures(self, &gltf, images);
+    /// Uploads the textures and meshes of an imported glTF file and builds its template.
+    pub(crate) fn build_gltf(&mut self, data: GltfData) -> Result<Template, Error> {
+        let GltfData { path, document: gltf, buffers, images, geometries } = data;
+        let textures = load_textures(self, &gltf, images)?;
 
         // Mappings that allow us to convert from indices in the glTF document to the indices in
         // the resulting template, for objects where the two don't necessarily line up.
@@ -579,17 +712,15 @@ impl super::Factory {
         // meshes, and populate `mesh_map` with information on how to lookup meshes in the
         // flattened list given the index in the original glTF document.
         let mut primitives = Vec::new();
-        for gltf_mesh in gltf.meshes() {
+        for (gltf_mesh, mesh_geometries) in gltf.meshes().zip(geometries) {
             // Save the index within the glTF document so that we can add an entry to the mesh map.
             let gltf_index = gltf_mesh.index();
 
             // Add all of the meshes to the flattened list of meshes, and generate a list of new
             // indices that can be used to map from the glTF index to the flattened indices.
             let mut indices = Vec::new();
-            let prim_iter = gltf_mesh
-                .primitives()
-                .map(|prim| load_primitive(self, prim, &buffers, &textures));
-            for primitive in prim_iter {
+            for (prim, geometry) in gltf_mesh.primitives().zip(mesh_geometries) {
+                let primitive = load_primitive(self, prim, geometry, &textures)?;
                 indices.push(primitives.len());
                 primitives.push(primitive);
             }
@@ -621,7 +752,9 @@ impl super::Factory {
             for child_index in gltf_node.children().map(|child| child.index()) {
                 let object = &mut objects[groups[child_index]];
 
-                assert!(object.parent.is_none(), "Object template already had a parent specified");
+                if object.parent.is_some() {
+                    return Err(Error::Decode("node with several parents".into()));
+                }
                 object.parent = Some(gltf_node.index());
             }
         }
@@ -637,10 +770,16 @@ impl super::Factory {
         let animations = gltf
             .animations()
             .map(|anim| load_animation(anim, &buffers, &groups))
+            .collect::<Result<_, _>>()?;
+
+        // List the root nodes of every scene, the objects below them are part of the scene too.
+        let scenes = gltf
+            .scenes()
+            .map(|scene| load_scene(scene, &groups))
             .collect();
 
-        let raw_template = Template {
-            name: None,
+        Ok(Template {
+            name: path.file_stem().map(|stem| stem.to_string_lossy().into_owned()),
             objects,
             groups,
             cameras,
@@ -649,15 +788,7 @@ impl super::Factory {
             bones,
             skeletons,
             animations,
-        };
-
-        if gltf.scenes().len() > 1 {
-            warn!("Mutliple scenes found in {}, glTF loading does not currently work correctly for glTF files with multiple scenes", path.display());
-        }
-
-        gltf
-            .scenes()
-            .map(|scene| load_scene(scene, &raw_template))
-            .collect()
+            scenes,
+        })
     }
 }
@@ -1,16 +1,25 @@
+mod background;
+pub(crate) mod compressed;
 #[cfg(feature = "gltf")]
 mod load_gltf;
 
+pub use self::background::Loading;
+
 use std::{cmp, fs, io, iter, ops};
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::collections::hash_map::{Entry, HashMap};
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "audio")]
+use std::time::Duration;
 
-use cgmath::{Vector3};
+use cgmath::{InnerSpace, Point3, Vector3};
 use gfx;
 use gfx::format::I8Norm;
+use gfx::memory::Typed;
 use gfx::traits::{Factory as Factory_, FactoryExt};
 use hub;
 use image;
@@ -24,16 +33,18 @@ use audio;
 use animation;
 use camera::{Camera, Projection, ZRange};
 use color::{BLACK, Color};
+use error::Error;
 use geometry::Geometry;
 use hub::{Hub, HubPtr, LightData, SubLight, SubNode};
-use light::{Ambient, Directional, Hemisphere, Point, ShadowMap};
+use light::{Ambient, CascadedShadowMap, CubeShadowMap, Directional, Hemisphere, Point, ShadowFilter, ShadowMap, Spot, MAX_SPOT_ANGLE};
 use material::{self, Material};
 use mesh::{DynamicMesh, Mesh};
 use object::{self, Group, Object};
-use render::{basic_pipe,
-    BackendFactory, BackendResources, BasicPipelineState, DisplacementContribution,
-    DynamicData, GpuData, Instance, InstanceCacheKey, PipelineCreationError, ShadowFormat, Source, Vertex,
-    DEFAULT_VERTEX, VECS_PER_BONE, ZEROED_DISPLACEMENT_CONTRIBUTION,
+use raycast::Triangles;
+use render::{basic_pipe, post_pipe,
+    BackendFactory, BackendResources, BasicPipelineState, PendingMipmaps, ColorFormat, DepthFormat, DisplacementContribution,
+    BoundingSphere, DynamicData, GpuData, HdrFormat, Instance, InstanceCacheKey, PipelineCreationError, RenderTarget, ShadowFormat, Source, Vertex,
+    PostPipelineState, DEFAULT_VERTEX, MAX_CASCADES, VECS_PER_BONE, ZEROED_DISPLACEMENT_CONTRIBUTION,
 };
 use scene::{Background, Scene};
 use sprite::Sprite;
@@ -41,11 +52,21 @@ use skeleton::{Bone, InverseBindMatrix, Skeleton};
 use template::{
     InstancedGeometry,
     LightTemplate,
+    SceneSelector,
     SubLightTemplate,
     Template,
 };
 use text::{Font, Text, TextData};
-use texture::{CubeMap, CubeMapPath, FilterMethod, Sampler, Texture, WrapMode};
+use texture::{CubeMap, CubeMapPath, EnvironmentMap, EnvironmentSource, FilterMethod, Sampler, Texture, WrapMode};
+
+/// Size of the cube faces an equirectangular environment is converted into.
+const ENVIRONMENT_CUBE_SIZE: u16 = 512;
+/// Size of the diffuse irradiance cube faces, which hold only low frequencies.
+const IRRADIANCE_SIZE: u16 = 32;
+/// Size of the sharpest specular cube faces.
+const SPECULAR_SIZE: u16 = 128;
+/// Number of specular mip levels, the roughest one being 4 by 4 texels.
+const SPECULAR_LEVELS: u8 = 6;
 
 const TANGENT_X: [I8Norm; 4] = [I8Norm(1), I8Norm(0), I8Norm(0), I8Norm(1)];
 const NORMAL_Z: [I8Norm; 4] = [I8Norm(0), I8Norm(0), I8Norm(1), I8Norm(0)];
@@ -73,6 +94,40 @@ const QUAD: [Vertex; 4] = [
     },
 ];
 
+/// Pixels of an image file, the bottom row first.
+enum DecodedImage {
+    /// sRGB encoded 8-bit colors.
+    Srgba8(image::RgbaImage),
+    /// Linear floating point colors, with the width and height.
+    Float(u32, u32, Vec<[f32; 4]>),
+}
+
+/// Contents of a texture file, read and decoded before the GPU upload.
+enum TextureImage {
+    Decoded(DecodedImage),
+    Compressed(compressed::CompressedImage),
+}
+
+/// Geometry of an OBJ group, triangulated before creating the GPU buffers.
+struct ObjMesh {
+    name: String,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    material: Option<Arc<obj::Material>>,
+    num_normals: usize,
+    num_uvs: usize,
+}
+
+/// Contents of an OBJ file, read on the CPU.
+struct ObjData {
+    /// Meshes of each object, with the object name.
+    objects: Vec<(String, Vec<ObjMesh>)>,
+    /// Directory the material textures are relative to.
+    dir: Option<PathBuf>,
+    /// Material textures decoded in advance, by path.
+    textures: HashMap<PathBuf, TextureImage>,
+}
+
 /// Mapping writer.
 pub type MapVertices<'a> = gfx::mapping::Writer<'a, BackendResources, Vertex>;
 
@@ -83,26 +138,61 @@ pub struct Factory {
     quad_buf: gfx::handle::Buffer<BackendResources, Vertex>,
     texture_cache: HashMap<PathBuf, Texture<[f32; 4]>>,
     default_sampler: gfx::handle::Sampler<BackendResources>,
+    /// Textures waiting for the `Renderer` to generate their mipmaps.
+    pending_mipmaps: PendingMipmaps,
+    /// Block compressed formats the driver can sample directly.
+    compressed_support: compressed::Support,
+    /// Threads reading and decoding the assets loaded in the background.
+    workers: background::Workers,
+    #[cfg(feature = "audio")]
+    audio_buses: HashMap<String, audio::Bus>,
+    #[cfg(feature = "audio")]
+    audio_endpoint: audio::Endpoint,
 }
 
 fn f2i(x: f32) -> I8Norm {
     I8Norm(cmp::min(cmp::max((x * 127.0) as isize, -128), 127) as i8)
 }
 
+/// Reinterprets linear colors as the `[u32; 4]` surface data of `Rgba32F`.
+fn float_texels(pixels: &[[f32; 4]]) -> Vec<[u32; 4]> {
+    pixels
+        .iter()
+        .map(|p| [p[0].to_bits(), p[1].to_bits(), p[2].to_bits(), p[3].to_bits()])
+        .collect()
+}
+
+/// Converts a 16-bit color to floating point, from sRGB to linear if `srgb` is set.
+/// The alpha is linear either way.
+fn rgba16_to_float(
+    pixel: [u16; 4],
+    srgb: bool,
+) -> [f32; 4] {
+    let f = |c: u16| {
+        let x = c as f32 / 65535.0;
+        if !srgb {
+            x
+        } else if x > 0.04045 {
+            ((x + 0.055) / 1.055).powf(2.4)
+        } else {
+            x / 12.92
+        }
+    };
+    [f(pixel[0]), f(pixel[1]), f(pixel[2]), pixel[3] as f32 / 65535.0]
+}
+
 impl Factory {
-    fn create_instance_buffer(&mut self) -> gfx::handle::Buffer<BackendResources, Instance> {
-        // TODO: Better error handling
-        self.backend
-            .create_buffer(
-                1,
-                gfx::buffer::Role::Vertex,
-                gfx::memory::Usage::Dynamic,
-                gfx::memory::Bind::TRANSFER_DST,
-            )
-            .unwrap()
+    fn create_instance_buffer(&mut self) -> Result<gfx::handle::Buffer<BackendResources, Instance>, Error> {
+        let buffer = self.backend.create_buffer(
+            1,
+            gfx::buffer::Role::Vertex,
+            gfx::memory::Usage::Dynamic,
+            gfx::memory::Bind::TRANSFER_DST,
+        )?;
+        Ok(buffer)
     }
 
-    fn create_gpu_data(&mut self, geometry: Geometry) -> GpuData {
+    fn create_gpu_data(&mut self, geometry: Geometry) -> Result<GpuData, Error> {
         let vertices = Self::mesh_vertices(&geometry);
         let (vbuf, mut slice) = if geometry.faces.is_empty() {
             self.backend.create_vertex_buffer_with_slice(&vertices, ())
@@ -114,7 +204,7 @@ impl Factory {
         slice.instances = Some((1, 0));
         let num_shapes = geometry.shapes.len();
         let mut displacement_contributions = Vec::with_capacity(num_shapes);
-        let instances = self.create_instance_buffer();
+        let instances = self.create_instance_buffer()?;
         let displacements = if num_shapes != 0 {
             let num_vertices = geometry.base.vertices.len();
             let mut contents = vec![[0.0; 4]; num_shapes * 3 * num_vertices];
@@ -150,14 +240,29 @@ impl Factory {
                     ),
                     gfx::texture::Mipmap::Provided,
                     &[gfx::memory::cast_slice(&contents)],
-                )
-                .unwrap();
+                )?;
             Some(texture_and_view)
         } else {
             None
         };
 
-        GpuData {
+        let bounds = BoundingSphere::from_points(
+            geometry.base.vertices.iter().map(|&v| Point3::from(v)),
+        ).map(|sphere| {
+            // Morph targets can move vertices as far as all their displacements combined.
+            let max_displacement = geometry.shapes
+                .iter()
+                .map(|shape| {
+                    shape.vertices
+                        .iter()
+                        .map(|v| Vector3::new(v.x, v.y, v.z).magnitude())
+                        .fold(0.0, f32::max)
+                })
+                .sum();
+            sphere.inflated(max_displacement)
+        });
+
+        Ok(GpuData {
             slice,
             vertices: vbuf,
             instances,
@@ -165,10 +270,19 @@ impl Factory {
             pending: None,
             instance_cache_key: None,
             displacement_contributions,
-        }
+            bounds,
+            triangles: Some(Arc::new(Triangles::new(
+                geometry.base.vertices.iter().map(|&v| Point3::from(v)).collect(),
+                geometry.faces,
+            ))),
+        })
     }
 
-    pub(crate) fn new(mut backend: BackendFactory) -> Self {
+    pub(crate) fn new(
+        mut backend: BackendFactory,
+        pending_mipmaps: PendingMipmaps,
+        compressed_support: compressed::Support,
+    ) -> Self {
         let quad_buf = backend.create_vertex_buffer(&QUAD);
         let default_sampler = backend.create_sampler_linear();
         Factory {
@@ -177,6 +291,13 @@ impl Factory {
             quad_buf,
             texture_cache: HashMap::new(),
             default_sampler: default_sampler,
+            pending_mipmaps,
+            compressed_support,
+            workers: background::Workers::default(),
+            #[cfg(feature = "audio")]
+            audio_buses: HashMap::new(),
+            #[cfg(feature = "audio")]
+            audio_endpoint: audio::Endpoint::Device,
         }
     }
 
@@ -188,6 +309,9 @@ impl Factory {
             hub,
             first_child: None,
             background,
+            environment: None,
+            #[cfg(feature = "audio")]
+            listener: None,
         }
     }
 
@@ -212,10 +336,62 @@ impl Factory {
     /// let (group, animations) = window.factory.instantiate_template(&template);
     /// ```
     ///
+    /// All objects are instantiated, including the ones of every scene listed in the template.
+    /// Use [`Factory::instantiate_template_scene`] to create an instance of a single scene.
+    ///
     /// [`Group`]: ./struct.Group.html
     /// [`template`]: ./template/index.html
     /// [`Factory::group`]: #method.group
+    /// [`Factory::instantiate_template_scene`]: #method.instantiate_template_scene
     pub fn instantiate_template(&mut self, template: &Template) -> (Group, Vec<animation::Clip>) {
+        self.instantiate_objects(template, None)
+    }
+
+    /// Creates an instance of the objects of a single scene described in the template,
+    /// selected by index into [`Template::scenes`] or by name.
+    ///
+    /// Returns the root [`Group`] of the scene and the animation clips targeting its objects,
+    /// or `None` if the template has no such scene.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "gltf")]
+    /// # fn main() {
+    /// # let mut window = three::Window::new("Three-rs");
+    /// let template = window.factory.load_gltf("asset-pack.gltf");
+    /// let (forest, _) = window.factory
+    ///     .instantiate_template_scene(&template, "Forest")
+    ///     .expect("no forest in the pack");
+    /// let (first, _) = window.factory
+    ///     .instantiate_template_scene(&template, 0)
+    ///     .expect("empty asset pack");
+    /// window.scene.add(&forest);
+    /// # }
+    /// # #[cfg(not(feature = "gltf"))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// [`Group`]: ./struct.Group.html
+    /// [`Template::scenes`]: ./template/struct.Template.html#structfield.scenes
+    pub fn instantiate_template_scene<'a, S: Into<SceneSelector<'a>>>(
+        &mut self,
+        template: &Template,
+        scene: S,
+    ) -> Option<(Group, Vec<animation::Clip>)> {
+        let index = template.find_scene(scene)?;
+        let objects = template.scene_objects(index);
+        Some(self.instantiate_objects(template, Some(&objects)))
+    }
+
+    /// Instantiates the objects of `template`, or only the ones in `filter` if given.
+    fn instantiate_objects(
+        &mut self,
+        template: &Template,
+        filter: Option<&HashSet<usize>>,
+    ) -> (Group, Vec<animation::Clip>) {
+        let included = |object: usize| filter.map_or(true, |filter| filter.contains(&object));
+
         // Create group to act as the root node of the instantiated hierarchy.
         let root = self.group();
 
@@ -226,9 +402,12 @@ impl Factory {
             .groups
             .iter()
             .map(|&object| {
+                if !included(object) {
+                    return None;
+                }
                 let group = self.group();
                 objects.insert(object, group.upcast());
-                group
+                Some(group)
             })
             .collect();
 
@@ -236,9 +415,12 @@ impl Factory {
             .bones
             .iter()
             .map(|template| {
+                if !included(template.object) {
+                    return None;
+                }
                 let bone = self.bone(template.index, template.inverse_bind_matrix);
                 objects.insert(template.object, bone.upcast());
-                bone
+                Some(bone)
             })
             .collect();
 
@@ -257,44 +439,44 @@ impl Factory {
             .iter()
             .enumerate()
             .map(|(index, &object)| {
+                if !included(object) {
+                    return None;
+                }
                 let bones = template
                     .bones
                     .iter()
                     .zip(bones.iter())
-                    .filter_map(|(template, bone)| {
-                        if template.skeleton == index {
-                            Some(bone.clone())
-                        } else {
-                            None
-                        }
+                    .filter_map(|(template, bone)| match *bone {
+                        Some(ref bone) if template.skeleton == index => Some(bone.clone()),
+                        _ => None,
                     })
                     .collect();
                 let skeleton = self.skeleton(bones);
                 objects.insert(object, skeleton.upcast());
                 skeleton_objects.insert(object);
-                skeleton
+                Some(skeleton)
             })
             .collect();
 
-        for template in &template.meshes {
+        for template in template.meshes.iter().filter(|template| included(template.object)) {
             let mesh = self.create_instanced_mesh(
                 &template.geometry,
                 template.material.clone(),
             );
 
-            if let Some(skeleton_index) = template.skeleton {
-                mesh.set_skeleton(skeletons[skeleton_index].clone())
+            if let Some(&Some(ref skeleton)) = template.skeleton.map(|index| &skeletons[index]) {
+                mesh.set_skeleton(skeleton.clone())
             }
 
             objects.insert(template.object, mesh.upcast());
         }
 
-        for template in &template.cameras {
+        for template in template.cameras.iter().filter(|template| included(template.object)) {
             let camera = self.camera(template.projection.clone());
             objects.insert(template.object, camera.upcast());
         }
 
-        for &template in &template.lights {
+        for &template in template.lights.iter().filter(|template| included(template.object)) {
             let LightTemplate { object, color, intensity, sub_light } = template;
             let light = match sub_light {
                 SubLightTemplate::Ambient =>
@@ -327,8 +509,9 @@ impl Factory {
             // we skip them. See note above for more details.
             if skeleton_objects.contains(&index) { continue; }
 
-            match template.parent {
-                Some(parent) => groups[parent].add(base),
+            // Objects whose parent isn't instantiated are roots of the instance.
+            match template.parent.and_then(|parent| groups[parent].as_ref()) {
+                Some(group) => group.add(base),
                 None => root.add(base),
             }
         }
@@ -338,8 +521,8 @@ impl Factory {
         for index in skeleton_objects {
             let base = &objects[&index];
             let template = &template.objects[index];
-            match template.parent {
-                Some(parent) => groups[parent].add(base),
+            match template.parent.and_then(|parent| groups[parent].as_ref()) {
+                Some(group) => group.add(base),
                 None => root.add(base),
             }
         }
@@ -348,17 +531,22 @@ impl Factory {
         let animations = template
             .animations
             .iter()
-            .map(|animation| {
-                let tracks = animation
+            .filter_map(|animation| {
+                let tracks: Vec<_> = animation
                     .tracks
                     .iter()
+                    .filter(|&&(_, target)| included(target))
                     .map(|&(ref track, target)| (track.clone(), objects[&target].clone()))
                     .collect();
 
-                animation::Clip {
-                    name: template.name.clone(),
-                    tracks,
+                // Skip the clips animating none of the instantiated objects.
+                if filter.is_some() && tracks.is_empty() {
+                    return None;
                 }
+                Some(animation::Clip {
+                    name: animation.name.clone(),
+                    tracks,
+                })
             })
             .collect();
 
@@ -558,36 +746,65 @@ impl Factory {
     /// let material = three::material::Basic {
     ///     color: 0xFFFF00,
     ///     map: None,
+    ///     .. Default::default()
     /// };
     /// let first = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
     /// let second = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
     /// let third = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
     /// ```
     ///
+    /// #### Panics
+    /// Panics if the GPU buffers can't be created,
+    /// see [`try_upload_geometry`](#method.try_upload_geometry) to handle the error instead.
+    ///
     /// [`template`]: ./template/index.html#mesh-instancing
     pub fn upload_geometry(
         &mut self,
         geometry: Geometry,
     ) -> InstancedGeometry {
-        let gpu_data = self.create_gpu_data(geometry);
-        InstancedGeometry { gpu_data }
+        self.try_upload_geometry(geometry)
+            .unwrap_or_else(|e| panic!("Unable to upload geometry: {}", e))
+    }
+
+    /// Uploads geometry to the GPU, returning an error if the GPU buffers can't be created.
+    pub fn try_upload_geometry(
+        &mut self,
+        geometry: Geometry,
+    ) -> Result<InstancedGeometry, Error> {
+        let gpu_data = self.create_gpu_data(geometry)?;
+        Ok(InstancedGeometry { gpu_data })
     }
 
     /// Create new `Mesh` with desired `Geometry` and `Material`.
+    ///
+    /// #### Panics
+    /// Panics if the GPU buffers can't be created,
+    /// see [`try_mesh`](#method.try_mesh) to handle the error instead.
     pub fn mesh<M: Into<Material>>(
         &mut self,
         geometry: Geometry,
         material: M,
     ) -> Mesh {
-        let gpu_data = self.create_gpu_data(geometry);
+        self.try_mesh(geometry, material)
+            .unwrap_or_else(|e| panic!("Unable to create mesh: {}", e))
+    }
 
-        Mesh {
+    /// Create new `Mesh` with desired `Geometry` and `Material`,
+    /// returning an error if the GPU buffers can't be created.
+    pub fn try_mesh<M: Into<Material>>(
+        &mut self,
+        geometry: Geometry,
+        material: M,
+    ) -> Result<Mesh, Error> {
+        let gpu_data = self.create_gpu_data(geometry)?;
+
+        Ok(Mesh {
             object: self.hub.lock().unwrap().spawn_visual(
                 material.into(),
                 gpu_data,
                 None,
             ),
-        }
+        })
     }
 
     /// Creates a [`Mesh`] using geometry that has already been loaded to the GPU.
@@ -616,6 +833,7 @@ impl Factory {
     /// let material = three::material::Basic {
     ///     color: 0xFFFF00,
     ///     map: None,
+    ///     .. Default::default()
     /// };
     /// let first = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
     /// let second = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
@@ -649,11 +867,26 @@ impl Factory {
     }
 
     /// Create a new `DynamicMesh` with desired `Geometry` and `Material`.
+    ///
+    /// #### Panics
+    /// Panics if the GPU buffers can't be created,
+    /// see [`try_mesh_dynamic`](#method.try_mesh_dynamic) to handle the error instead.
     pub fn mesh_dynamic<M: Into<Material>>(
         &mut self,
         geometry: Geometry,
         material: M,
     ) -> DynamicMesh {
+        self.try_mesh_dynamic(geometry, material)
+            .unwrap_or_else(|e| panic!("Unable to create dynamic mesh: {}", e))
+    }
+
+    /// Create a new `DynamicMesh` with desired `Geometry` and `Material`,
+    /// returning an error if the GPU buffers can't be created.
+    pub fn try_mesh_dynamic<M: Into<Material>>(
+        &mut self,
+        geometry: Geometry,
+        material: M,
+    ) -> Result<DynamicMesh, Error> {
         let slice = {
             let data: &[u32] = gfx::memory::cast_slice(&geometry.faces);
             gfx::Slice {
@@ -667,21 +900,19 @@ impl Factory {
         let (num_vertices, vertices, upload_buf) = {
             let data = Self::mesh_vertices(&geometry);
             let dest_buf = self.backend
-                .create_buffer_immutable(&data, gfx::buffer::Role::Vertex, gfx::memory::Bind::TRANSFER_DST)
-                .unwrap();
-            let upload_buf = self.backend.create_upload_buffer(data.len()).unwrap();
+                .create_buffer_immutable(&data, gfx::buffer::Role::Vertex, gfx::memory::Bind::TRANSFER_DST)?;
+            let upload_buf = self.backend.create_upload_buffer(data.len())?;
             // TODO: Workaround for not having a 'write-to-slice' capability.
             // Reason: The renderer copies the entire staging buffer upon updates.
             {
                 self.backend
-                    .write_mapping(&upload_buf)
-                    .unwrap()
+                    .write_mapping(&upload_buf)?
                     .copy_from_slice(&data);
             }
             (data.len(), dest_buf, upload_buf)
         };
-        let instances = self.create_instance_buffer();
-        DynamicMesh {
+        let instances = self.create_instance_buffer()?;
+        Ok(DynamicMesh {
             object: self.hub.lock().unwrap().spawn_visual(
                 material.into(),
                 GpuData {
@@ -692,6 +923,9 @@ impl Factory {
                     pending: None,
                     instance_cache_key: None,
                     displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                    // The vertices can be changed at any time with `map_vertices`.
+                    bounds: None,
+                    triangles: None,
                 },
                 None,
             ),
@@ -700,17 +934,31 @@ impl Factory {
                 num_vertices,
                 buffer: upload_buf,
             },
-        }
+        })
     }
 
     /// Create a `Mesh` sharing the geometry with another one.
     /// Rendering a sequence of meshes with the same geometry is faster.
     /// The material is duplicated from the template.
+    ///
+    /// #### Panics
+    /// Panics if the GPU instance buffer can't be created,
+    /// see [`try_mesh_instance`](#method.try_mesh_instance) to handle the error instead.
     pub fn mesh_instance(
         &mut self,
         template: &Mesh,
     ) -> Mesh {
-        let instances = self.create_instance_buffer();
+        self.try_mesh_instance(template)
+            .unwrap_or_else(|e| panic!("Unable to create mesh instance: {}", e))
+    }
+
+    /// Create a `Mesh` sharing the geometry and the material with another one,
+    /// returning an error if the GPU instance buffer can't be created.
+    pub fn try_mesh_instance(
+        &mut self,
+        template: &Mesh,
+    ) -> Result<Mesh, Error> {
+        let instances = self.create_instance_buffer()?;
         let mut hub = self.hub.lock().unwrap();
         let (material, gpu_data) = match hub[template].sub_node {
             SubNode::Visual(ref mat, ref gpu, _) => {
@@ -725,19 +973,35 @@ impl Factory {
             }
             _ => unreachable!(),
         };
-        Mesh {
+        Ok(Mesh {
             object: hub.spawn_visual(material, gpu_data, None),
-        }
+        })
     }
 
     /// Create a `Mesh` sharing the geometry with another one but with a different material.
     /// Rendering a sequence of meshes with the same geometry is faster.
+    ///
+    /// #### Panics
+    /// Panics if the GPU instance buffer can't be created, see
+    /// [`try_mesh_instance_with_material`](#method.try_mesh_instance_with_material)
+    /// to handle the error instead.
     pub fn mesh_instance_with_material<M: Into<Material>>(
         &mut self,
         template: &Mesh,
         material: M,
     ) -> Mesh {
-        let instances = self.create_instance_buffer();
+        self.try_mesh_instance_with_material(template, material)
+            .unwrap_or_else(|e| panic!("Unable to create mesh instance: {}", e))
+    }
+
+    /// Create a `Mesh` sharing the geometry with another one but with a different material,
+    /// returning an error if the GPU instance buffer can't be created.
+    pub fn try_mesh_instance_with_material<M: Into<Material>>(
+        &mut self,
+        template: &Mesh,
+        material: M,
+    ) -> Result<Mesh, Error> {
+        let instances = self.create_instance_buffer()?;
         let material = material.into();
         let mut hub = self.hub.lock().unwrap();
         let gpu_data = match hub[template].sub_node {
@@ -751,21 +1015,35 @@ impl Factory {
             },
             _ => unreachable!(),
         };
-        Mesh {
+        Ok(Mesh {
             object: hub.spawn_visual(material, gpu_data, None),
-        }
+        })
     }
 
     /// Create new sprite from `Material`.
+    ///
+    /// #### Panics
+    /// Panics if the GPU instance buffer can't be created,
+    /// see [`try_sprite`](#method.try_sprite) to handle the error instead.
     pub fn sprite(
         &mut self,
         material: material::Sprite,
     ) -> Sprite {
-        let instances = self.create_instance_buffer();
+        self.try_sprite(material)
+            .unwrap_or_else(|e| panic!("Unable to create sprite: {}", e))
+    }
+
+    /// Create new sprite from `Material`, returning an error if the GPU instance buffer
+    /// can't be created.
+    pub fn try_sprite(
+        &mut self,
+        material: material::Sprite,
+    ) -> Result<Sprite, Error> {
+        let instances = self.create_instance_buffer()?;
         let mut slice = gfx::Slice::new_match_vertex_buffer(&self.quad_buf);
         slice.instances = Some((1, 0));
         let material = Material::from(material);
-        Sprite::new(self.hub.lock().unwrap().spawn_visual(
+        Ok(Sprite::new(self.hub.lock().unwrap().spawn_visual(
             material,
             GpuData {
                 slice,
@@ -775,18 +1053,34 @@ impl Factory {
                 pending: None,
                 instance_cache_key: None,
                 displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                bounds: BoundingSphere::from_points(QUAD.iter().map(|v| Point3::new(v.pos[0], v.pos[1], v.pos[2]))),
+                triangles: None,
             },
             None,
-        ))
+        )))
     }
 
     /// Create a `Sprite` sharing the material with another one.
     /// Rendering a sequence of instanced sprites is much faster.
+    ///
+    /// #### Panics
+    /// Panics if the GPU instance buffer can't be created,
+    /// see [`try_sprite_instance`](#method.try_sprite_instance) to handle the error instead.
     pub fn sprite_instance(
         &mut self,
         template: &Sprite,
     ) -> Sprite {
-        let instances = self.create_instance_buffer();
+        self.try_sprite_instance(template)
+            .unwrap_or_else(|e| panic!("Unable to create sprite instance: {}", e))
+    }
+
+    /// Create a `Sprite` sharing the material with another one,
+    /// returning an error if the GPU instance buffer can't be created.
+    pub fn try_sprite_instance(
+        &mut self,
+        template: &Sprite,
+    ) -> Result<Sprite, Error> {
+        let instances = self.create_instance_buffer()?;
         let mut hub = self.hub.lock().unwrap();
         let (material, gpu_data) = match hub[template].sub_node {
             SubNode::Visual(ref mat, ref gpu, _) => {
@@ -801,7 +1095,7 @@ impl Factory {
             }
             _ => unreachable!(),
         };
-        Sprite::new(hub.spawn_visual(material, gpu_data, None))
+        Ok(Sprite::new(hub.spawn_visual(material, gpu_data, None)))
     }
 
     /// Create new `AmbientLight`.
@@ -815,6 +1109,8 @@ impl Factory {
             intensity,
             sub_light: SubLight::Ambient,
             shadow: None,
+            cube_shadow: None,
+            cascaded_shadow: None,
         }))
     }
 
@@ -829,6 +1125,8 @@ impl Factory {
             intensity,
             sub_light: SubLight::Directional,
             shadow: None,
+            cube_shadow: None,
+            cascaded_shadow: None,
         }))
     }
 
@@ -846,6 +1144,8 @@ impl Factory {
                 ground: ground_color,
             },
             shadow: None,
+            cube_shadow: None,
+            cascaded_shadow: None,
         }))
     }
 
@@ -860,18 +1160,50 @@ impl Factory {
             intensity,
             sub_light: SubLight::Point,
             shadow: None,
+            cube_shadow: None,
+            cascaded_shadow: None,
+        }))
+    }
+
+    /// Create new `SpotLight`.
+    ///
+    /// `angle` is the angle between the axis and the edge of the cone in degrees, clamped
+    /// to the range [0.0, 89.0], and `penumbra` is the part of the cone, in range [0.0, 1.0],
+    /// where the light fades out.
+    pub fn spot_light(
+        &mut self,
+        color: Color,
+        intensity: f32,
+        angle: f32,
+        penumbra: f32,
+    ) -> Spot {
+        Spot::new(self.hub.lock().unwrap().spawn_light(LightData {
+            color,
+            intensity,
+            sub_light: SubLight::Spot {
+                angle: angle.clamp(0.0, MAX_SPOT_ANGLE),
+                penumbra,
+            },
+            shadow: None,
+            cube_shadow: None,
+            cascaded_shadow: None,
         }))
     }
 
     /// Create a `Sampler` with default properties.
     ///
     /// The default sampler has `Clamp` as its horizontal and vertical
-    /// wrapping mode and `Scale` as its filtering method.
+    /// wrapping mode and `Trilinear` as its filtering method.
     pub fn default_sampler(&self) -> Sampler {
         Sampler(self.default_sampler.clone())
     }
 
     /// Create new `Sampler`.
+    ///
+    /// Textures loaded from images get a full chain of mipmaps, which `FilterMethod::Mipmap`,
+    /// `Trilinear` and `Anisotropic` blend between to avoid shimmering in the distance.
+    /// `Anisotropic` additionally keeps surfaces seen at grazing angles sharp, taking up
+    /// to the given number of samples, clamped between 1 and 16.
     pub fn sampler(
         &mut self,
         filter_method: FilterMethod,
@@ -879,6 +1211,10 @@ impl Factory {
         vertical_wrap_mode: WrapMode,
     ) -> Sampler {
         use gfx::texture::Lod;
+        let filter_method = match filter_method {
+            FilterMethod::Anisotropic(max) => FilterMethod::Anisotropic(max.max(1).min(16)),
+            other => other,
+        };
         let info = gfx::texture::SamplerInfo {
             filter: filter_method,
             wrap_mode: (horizontal_wrap_mode, vertical_wrap_mode, WrapMode::Clamp),
@@ -892,15 +1228,186 @@ impl Factory {
     }
 
     /// Create new `ShadowMap`.
+    ///
+    /// #### Panics
+    /// Panics if the depth texture can't be created,
+    /// see [`try_shadow_map`](#method.try_shadow_map) to handle the error instead.
     pub fn shadow_map(
         &mut self,
         width: u16,
         height: u16,
     ) -> ShadowMap {
+        self.try_shadow_map(width, height)
+            .unwrap_or_else(|e| panic!("Unable to create shadow map: {}", e))
+    }
+
+    /// Create new `ShadowMap`, returning an error if the depth texture can't be created.
+    pub fn try_shadow_map(
+        &mut self,
+        width: u16,
+        height: u16,
+    ) -> Result<ShadowMap, Error> {
         let (_, resource, target) = self.backend
-            .create_depth_stencil::<ShadowFormat>(width, height)
-            .unwrap();
-        ShadowMap { resource, target }
+            .create_depth_stencil::<ShadowFormat>(width, height)?;
+        Ok(ShadowMap {
+            resource,
+            target,
+            filter: ShadowFilter::default(),
+        })
+    }
+
+    /// Create new `CubeShadowMap` with `size` by `size` faces.
+    ///
+    /// #### Panics
+    /// Panics if the depth texture can't be created,
+    /// see [`try_cube_shadow_map`](#method.try_cube_shadow_map) to handle the error instead.
+    pub fn cube_shadow_map(
+        &mut self,
+        size: u16,
+    ) -> CubeShadowMap {
+        self.try_cube_shadow_map(size)
+            .unwrap_or_else(|e| panic!("Unable to create cube shadow map: {}", e))
+    }
+
+    /// Create new `CubeShadowMap` with `size` by `size` faces,
+    /// returning an error if the depth texture can't be created.
+    pub fn try_cube_shadow_map(
+        &mut self,
+        size: u16,
+    ) -> Result<CubeShadowMap, Error> {
+        use gfx::format::{ChannelTyped, Formatted};
+        use gfx::texture as t;
+
+        let channel = <<ShadowFormat as Formatted>::Channel as ChannelTyped>::get_channel_type();
+        let texture = self.backend
+            .create_texture(
+                t::Kind::Cube(size),
+                1,
+                gfx::memory::Bind::SHADER_RESOURCE | gfx::memory::Bind::DEPTH_STENCIL,
+                gfx::memory::Usage::Data,
+                Some(channel),
+            )?;
+        let resource = self.backend
+            .view_texture_as_shader_resource::<ShadowFormat>(&texture, (0, 0), gfx::format::Swizzle::new())?;
+        let mut face = |layer| {
+            self.backend
+                .view_texture_as_depth_stencil::<ShadowFormat>(&texture, 0, Some(layer), t::DepthStencilFlags::empty())
+        };
+        let targets = [face(0)?, face(1)?, face(2)?, face(3)?, face(4)?, face(5)?];
+        Ok(CubeShadowMap {
+            resource,
+            targets,
+            filter: ShadowFilter::default(),
+        })
+    }
+
+    /// Create new `CascadedShadowMap` with `cascades` layers of `size` by `size` texels.
+    ///
+    /// #### Panics
+    /// Panics if `cascades` is zero or greater than 4, or if the depth texture can't be created,
+    /// see [`try_cascaded_shadow_map`](#method.try_cascaded_shadow_map) to handle the latter instead.
+    pub fn cascaded_shadow_map(
+        &mut self,
+        size: u16,
+        cascades: u8,
+    ) -> CascadedShadowMap {
+        self.try_cascaded_shadow_map(size, cascades)
+            .unwrap_or_else(|e| panic!("Unable to create cascaded shadow map: {}", e))
+    }
+
+    /// Create new `CascadedShadowMap` with `cascades` layers of `size` by `size` texels,
+    /// returning an error if the depth texture can't be created.
+    ///
+    /// #### Panics
+    /// Panics if `cascades` is zero or greater than 4.
+    pub fn try_cascaded_shadow_map(
+        &mut self,
+        size: u16,
+        cascades: u8,
+    ) -> Result<CascadedShadowMap, Error> {
+        use gfx::format::{ChannelTyped, Formatted};
+        use gfx::texture as t;
+
+        assert!(
+            cascades > 0 && cascades as usize <= MAX_CASCADES,
+            "Number of cascades must be between 1 and {}",
+            MAX_CASCADES,
+        );
+        let channel = <<ShadowFormat as Formatted>::Channel as ChannelTyped>::get_channel_type();
+        let texture = self.backend
+            .create_texture(
+                t::Kind::D2Array(size, size, cascades as t::Layer, t::AaMode::Single),
+                1,
+                gfx::memory::Bind::SHADER_RESOURCE | gfx::memory::Bind::DEPTH_STENCIL,
+                gfx::memory::Usage::Data,
+                Some(channel),
+            )?;
+        let resource = self.backend
+            .view_texture_as_shader_resource::<ShadowFormat>(&texture, (0, 0), gfx::format::Swizzle::new())?;
+        let targets = (0 .. cascades as t::Layer)
+            .map(|layer| {
+                self.backend
+                    .view_texture_as_depth_stencil::<ShadowFormat>(&texture, 0, Some(layer), t::DepthStencilFlags::empty())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(CascadedShadowMap {
+            resource,
+            targets,
+            filter: ShadowFilter::default(),
+        })
+    }
+
+    /// Create new offscreen [`RenderTarget`] together with a `Texture` of its color buffer.
+    ///
+    /// The texture reflects whatever was last drawn by [`Renderer::render_to`], so it can be
+    /// used as a material map for mirrors, monitors or minimaps.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+    /// let (target, texture) = window.factory.render_target(256, 256);
+    /// let monitor = window.factory.mesh(
+    ///     three::Geometry::plane(1.0, 1.0),
+    ///     three::material::Basic { color: 0xFFFFFF, map: Some(texture), .. Default::default() },
+    /// );
+    /// window.renderer.render_to(&window.scene, &camera, &target);
+    /// # let _ = monitor;
+    /// ```
+    ///
+    /// #### Panics
+    /// Panics if the GPU textures can't be created,
+    /// see [`try_render_target`](#method.try_render_target) to handle the error instead.
+    ///
+    /// [`RenderTarget`]: render/struct.RenderTarget.html
+    /// [`Renderer::render_to`]: struct.Renderer.html#method.render_to
+    pub fn render_target(
+        &mut self,
+        width: u16,
+        height: u16,
+    ) -> (RenderTarget, Texture<[f32; 4]>) {
+        self.try_render_target(width, height)
+            .unwrap_or_else(|e| panic!("Unable to create render target: {}", e))
+    }
+
+    /// Create new offscreen [`RenderTarget`] together with a `Texture` of its color buffer,
+    /// returning an error if the GPU textures can't be created.
+    ///
+    /// [`RenderTarget`]: render/struct.RenderTarget.html
+    pub fn try_render_target(
+        &mut self,
+        width: u16,
+        height: u16,
+    ) -> Result<(RenderTarget, Texture<[f32; 4]>), Error> {
+        let (_, resource, color_view) = self.backend
+            .create_render_target::<ColorFormat>(width, height)?;
+        let depth_view = self.backend
+            .create_depth_stencil_view_only::<DepthFormat>(width, height)?;
+        let size = [width as u32, height as u32];
+        let target = RenderTarget { color_view, depth_view, size };
+        let texture = Texture::new(resource, self.default_sampler.clone(), size);
+        Ok((target, texture))
     }
 
     /// Create a basic mesh pipeline using a custom shader.
@@ -915,12 +1422,13 @@ impl Factory {
         depth_state: gfx::state::Depth,
         stencil_state: gfx::state::Stencil,
     ) -> Result<BasicPipelineState, PipelineCreationError> {
+        use gfx::format::Formatted;
         let vs = Source::user(&dir, name, "vs")?;
         let ps = Source::user(&dir, name, "ps")?;
         let shaders = self.backend
             .create_shader_set(vs.0.as_bytes(), ps.0.as_bytes())?;
         let init = basic_pipe::Init {
-            out_color: ("Target0", color_mask, blend_state),
+            out_color: ("Target0", ColorFormat::get_format(), color_mask, Some(blend_state)),
             out_depth: (depth_state, stencil_state),
             ..basic_pipe::new()
         };
@@ -929,6 +1437,33 @@ impl Factory {
         Ok(pso)
     }
 
+    /// Create a full screen pass for [`PostEffect::Custom`] from the `name_ps.glsl`
+    /// fragment shader in `dir`.
+    ///
+    /// The shader can `#include <post>` to declare its inputs: `t_Input` is the image
+    /// to process, sampled at `v_TexCoord`, and the `b_PostParams` block holds the
+    /// effect `u_Params` and the size of a pixel in `u_TexelSize`. The result is
+    /// written to `Target0`.
+    ///
+    /// [`PostEffect::Custom`]: render/enum.PostEffect.html#variant.Custom
+    pub fn post_effect_pipeline<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        name: &str,
+    ) -> Result<PostPipelineState, PipelineCreationError> {
+        let vs = Source::default("fullscreen", "vs")?;
+        let ps = Source::user(&dir, name, "ps")?;
+        let shaders = self.backend
+            .create_shader_set(vs.0.as_bytes(), ps.0.as_bytes())?;
+        let pso = self.backend.create_pipeline_state(
+            &shaders,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            post_pipe::new(),
+        )?;
+        Ok(pso)
+    }
+
     /// Create new UI (on-screen) text. See [`Text`](struct.Text.html) for default settings.
     pub fn ui_text<S: Into<String>>(
         &mut self,
@@ -940,12 +1475,87 @@ impl Factory {
         Text::with_object(object)
     }
 
+    #[cfg(feature = "audio")]
+    /// Select where the sounds of the audio sources created afterwards are played.
+    ///
+    /// Defaults to the [`Backend`](audio/enum.Backend.html) selected when building
+    /// the window, and to `Backend::Null` for a headless renderer.
+    ///
+    /// Fails if the file of `Backend::Wav` can't be created, keeping the previous backend.
+    pub fn set_audio_backend(
+        &mut self,
+        backend: audio::Backend,
+    ) -> Result<(), Error> {
+        self.audio_endpoint = audio::Endpoint::new(&backend)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "audio")]
+    /// Play `duration` of the sounds of the `Null` and `Wav` audio backends.
+    ///
+    /// [`Window::update`](window/struct.Window.html#method.update) advances them by
+    /// the duration of each frame. Call it after rendering a frame with a
+    /// [`headless`](struct.Renderer.html#method.headless) renderer. The `Device` backend
+    /// plays in real time, unaffected by this.
+    pub fn advance_audio(
+        &self,
+        duration: Duration,
+    ) {
+        self.audio_endpoint.advance(duration);
+    }
+
     #[cfg(feature = "audio")]
     /// Create new audio source.
+    ///
+    /// #### Panics
+    /// Panics if the `Device` audio backend has no output device,
+    /// see [`try_audio_source`](#method.try_audio_source) to handle the error instead.
     pub fn audio_source(&mut self) -> audio::Source {
-        let sub = SubNode::Audio(audio::AudioData::new());
+        self.try_audio_source()
+            .unwrap_or_else(|e| panic!("Unable to create audio source: {}", e))
+    }
+
+    #[cfg(feature = "audio")]
+    /// Create new audio source, returning an error if the `Device` audio backend
+    /// has no output device.
+    pub fn try_audio_source(&mut self) -> Result<audio::Source, Error> {
+        let sub = SubNode::Audio(audio::AudioData::new(&self.audio_endpoint)?);
         let object = self.hub.lock().unwrap().spawn(sub);
-        audio::Source::with_object(object)
+        Ok(audio::Source::with_object(object))
+    }
+
+    #[cfg(feature = "audio")]
+    /// Create new spatial audio source, heard from its position in the scene
+    /// by the [`Listener`](audio/struct.Listener.html).
+    ///
+    /// #### Panics
+    /// Panics if the `Device` audio backend has no output device,
+    /// see [`try_audio_source_3d`](#method.try_audio_source_3d) to handle the error instead.
+    pub fn audio_source_3d(&mut self) -> audio::Source {
+        self.try_audio_source_3d()
+            .unwrap_or_else(|e| panic!("Unable to create audio source: {}", e))
+    }
+
+    #[cfg(feature = "audio")]
+    /// Create new spatial audio source, returning an error if the `Device` audio backend
+    /// has no output device.
+    pub fn try_audio_source_3d(&mut self) -> Result<audio::Source, Error> {
+        let sub = SubNode::Audio(audio::AudioData::new_spatial(&self.audio_endpoint)?);
+        let object = self.hub.lock().unwrap().spawn(sub);
+        Ok(audio::Source::with_object(object))
+    }
+
+    #[cfg(feature = "audio")]
+    /// Get the mixer [`Bus`](audio/struct.Bus.html) called `name`, such as `"music"`,
+    /// `"sfx"` or `"voice"`, creating it on first use.
+    pub fn audio_bus(
+        &mut self,
+        name: &str,
+    ) -> audio::Bus {
+        self.audio_buses
+            .entry(name.to_owned())
+            .or_insert_with(|| audio::Bus::new(name))
+            .clone()
     }
 
     /// Map vertices for updating their data.
@@ -985,24 +1595,27 @@ impl Factory {
 
     /// Load TrueTypeFont (.ttf) from file.
     /// #### Panics
-    /// Panics if I/O operations with file fails (e.g. file not found or corrupted)
+    /// Panics if I/O operations with file fails (e.g. file not found or corrupted),
+    /// see [`try_load_font`](#method.try_load_font) to handle the error instead.
     pub fn load_font<P: AsRef<Path>>(
         &mut self,
         file_path: P,
     ) -> Font {
+        let file_path = file_path.as_ref();
+        self.try_load_font(file_path)
+            .unwrap_or_else(|e| panic!("Unable to load font {}: {}", file_path.display(), e))
+    }
+
+    /// Load TrueTypeFont (.ttf) from file, returning an error if it can't be read or parsed.
+    pub fn try_load_font<P: AsRef<Path>>(
+        &mut self,
+        file_path: P,
+    ) -> Result<Font, Error> {
         let file_path = file_path.as_ref();
         let mut buffer = Vec::new();
-        let file = fs::File::open(&file_path).expect(&format!(
-            "Can't open font file:\nFile: {}",
-            file_path.display()
-        ));
-        io::BufReader::new(file)
-            .read_to_end(&mut buffer)
-            .expect(&format!(
-                "Can't read font file:\nFile: {}",
-                file_path.display()
-            ));
-        Font::new(buffer, format!("path: {:?}", file_path), self.backend.clone())
+        let file = fs::File::open(&file_path)?;
+        io::BufReader::new(file).read_to_end(&mut buffer)?;
+        Font::try_new(buffer, format!("path: {:?}", file_path), self.backend.clone())
     }
 
     /// Load the Karla font
@@ -1011,13 +1624,13 @@ impl Factory {
         Font::new(buffer, String::from("Embedded Karla-Regular.ttf"), self.backend.clone())
     }
 
-    fn parse_texture_format(path: &Path) -> image::ImageFormat {
+    fn parse_texture_format(path: &Path) -> Result<image::ImageFormat, Error> {
         use image::ImageFormat as F;
         let extension = path.extension()
-            .expect("no extension for an image?")
+            .ok_or_else(|| Error::Unsupported("image without an extension".into()))?
             .to_string_lossy()
             .to_lowercase();
-        match extension.as_str() {
+        Ok(match extension.as_str() {
             "png" => F::Png,
             "jpg" | "jpeg" => F::Jpeg,
             "gif" => F::Gif,
@@ -1028,94 +1641,299 @@ impl Factory {
             "bmp" => F::Bmp,
             "ico" => F::Ico,
             "hdr" => F::Hdr,
-            _ => panic!("Unrecognized image extension: {}", extension),
+            _ => return Err(Error::Unsupported(format!("image extension {}", extension))),
+        })
+    }
+
+    fn load_compressed_impl(path: &Path) -> Result<compressed::CompressedImage, Error> {
+        let mut bytes = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        compressed::parse(&bytes)
+    }
+
+    /// Uploads the mip chain of a DDS or KTX2 file, decompressing it
+    /// if the driver can't sample its format.
+    ///
+    /// 2D images are flipped to start from the bottom row like the other textures,
+    /// and get their mipmaps generated if the file holds a single level and
+    /// `pending_mipmaps` is given.
+    fn upload_compressed(
+        mut image: compressed::CompressedImage,
+        kind: gfx::texture::Kind,
+        factory: &mut BackendFactory,
+        support: compressed::Support,
+        pending_mipmaps: Option<&PendingMipmaps>,
+    ) -> Result<gfx::handle::ShaderResourceView<BackendResources, [f32; 4]>, Error> {
+        use self::compressed::BlockFormat;
+        use gfx::format::{Rgba8, Srgb, Srgba8, Unorm, BC1_R8_G8_B8, BC3_R8_G8_B8_A8};
+        use gfx::texture::{Kind, Mipmap};
+
+        let flip = match kind {
+            Kind::Cube(_) => false,
+            _ => true,
+        };
+        let native = match image.format {
+            BlockFormat::Bc1 { alpha: false } | BlockFormat::Bc3 => support.s3tc && (support.s3tc_srgb || !image.srgb),
+            _ => false,
+        };
+        if native && (!flip || image.flip_blocks()) {
+            let data = image.data.iter().map(|level| &level[..]).collect::<Vec<_>>();
+            // the textures differ in their surface type, only the views are alike
+            let view = match (image.format, image.srgb) {
+                (BlockFormat::Bc3, true) => factory.create_texture_immutable_u8::<(BC3_R8_G8_B8_A8, Srgb)>(kind, Mipmap::Provided, &data).map(|(_, view)| view)?,
+                (BlockFormat::Bc3, false) => factory.create_texture_immutable_u8::<(BC3_R8_G8_B8_A8, Unorm)>(kind, Mipmap::Provided, &data).map(|(_, view)| view)?,
+                (_, true) => factory.create_texture_immutable_u8::<(BC1_R8_G8_B8, Srgb)>(kind, Mipmap::Provided, &data).map(|(_, view)| view)?,
+                (_, false) => factory.create_texture_immutable_u8::<(BC1_R8_G8_B8, Unorm)>(kind, Mipmap::Provided, &data).map(|(_, view)| view)?,
+            };
+            return Ok(view);
         }
+
+        let mut levels = image.decompress();
+        if flip {
+            for (index, pixels) in levels.iter_mut().enumerate() {
+                let (width, _) = image.level_size(index % image.levels);
+                *pixels = pixels
+                    .chunks(width as usize * 4)
+                    .rev()
+                    .flat_map(|row| row.iter().cloned())
+                    .collect();
+            }
+        }
+        let data = levels.iter().map(|level| &level[..]).collect::<Vec<_>>();
+        let generate = pending_mipmaps.filter(|_| image.levels == 1);
+        let mipmap = if generate.is_some() { Mipmap::Allocated } else { Mipmap::Provided };
+        let (_, view) = if image.srgb {
+            factory.create_texture_immutable_u8::<Srgba8>(kind, mipmap, &data)?
+        } else {
+            factory.create_texture_immutable_u8::<Rgba8>(kind, mipmap, &data)?
+        };
+        if let Some(pending_mipmaps) = generate {
+            pending_mipmaps.lock().unwrap().push(view.raw().clone());
+        }
+        Ok(view)
     }
 
-    fn load_texture_impl(
-        path: &Path,
+    /// Decodes a Radiance HDR file, the bottom row first.
+    fn decode_hdr(path: &Path) -> Result<DecodedImage, Error> {
+        use image::codecs::hdr::HdrDecoder;
+
+        let file = fs::File::open(path)?;
+        let decoder = HdrDecoder::new(io::BufReader::new(file))?;
+        let (width, height) = {
+            let meta = decoder.metadata();
+            (meta.width, meta.height)
+        };
+        let pixels = decoder.read_image_hdr()?;
+        let data = pixels
+            .chunks(width as usize)
+            .rev()
+            .flat_map(|row| row.iter().map(|p| [p[0], p[1], p[2], 1.0]))
+            .collect();
+        Ok(DecodedImage::Float(width, height, data))
+    }
+
+    /// Decodes the first RGBA layer of an OpenEXR file, the bottom row first.
+    #[cfg(feature = "exr")]
+    fn decode_exr(path: &Path) -> Result<DecodedImage, Error> {
+        use exr::prelude::read_first_rgba_layer_from_file;
+
+        let image = read_first_rgba_layer_from_file(
+            path,
+            |resolution, _| vec![vec![[0.0; 4]; resolution.width()]; resolution.height()],
+            |rows: &mut Vec<Vec<[f32; 4]>>, position, (r, g, b, a): (f32, f32, f32, f32)| {
+                rows[position.y()][position.x()] = [r, g, b, a];
+            },
+        ).map_err(|e| Error::Decode(e.to_string()))?;
+        let size = image.layer_data.size;
+        let data = image.layer_data.channel_data.pixels
+            .into_iter()
+            .rev()
+            .flat_map(|row| row.into_iter())
+            .collect();
+        Ok(DecodedImage::Float(size.width() as u32, size.height() as u32, data))
+    }
+
+    #[cfg(not(feature = "exr"))]
+    fn decode_exr(_path: &Path) -> Result<DecodedImage, Error> {
+        Err(Error::Unsupported("OpenEXR images without the `exr` feature".into()))
+    }
+
+    /// Decodes an image file, the bottom row first.
+    ///
+    /// Radiance HDR and OpenEXR files keep their range, 16-bit images become linear
+    /// floating point data to keep their precision, and 8-bit images are uploaded in sRGB.
+    fn decode_image(path: &Path) -> Result<DecodedImage, Error> {
+        use image::DynamicImage as D;
+
+        let is_exr = path.extension().map_or(false, |e| e.to_string_lossy().to_lowercase() == "exr");
+        if is_exr {
+            return Factory::decode_exr(path);
+        }
+        let format = Factory::parse_texture_format(path)?;
+        if format == image::ImageFormat::Hdr {
+            return Factory::decode_hdr(path);
+        }
+        let file = fs::File::open(path)?;
+        let img = image::load(io::BufReader::new(file), format)?.flipv();
+        Ok(match img {
+            D::ImageLuma16(_) | D::ImageLumaA16(_) | D::ImageRgb16(_) | D::ImageRgba16(_) => {
+                let img = img.to_rgba16();
+                let (width, height) = img.dimensions();
+                let data = img.pixels().map(|p| rgba16_to_float(p.0, false)).collect();
+                DecodedImage::Float(width, height, data)
+            }
+            _ => DecodedImage::Srgba8(img.to_rgba8()),
+        })
+    }
+
+    /// Reads and decodes a texture file, leaving the GPU upload to `upload_texture`.
+    fn read_texture(path: &Path) -> Result<TextureImage, Error> {
+        if compressed::is_compressed(path) {
+            Ok(TextureImage::Compressed(Factory::load_compressed_impl(path)?))
+        } else {
+            Ok(TextureImage::Decoded(Factory::decode_image(path)?))
+        }
+    }
+
+    fn upload_texture(
+        image: TextureImage,
         sampler: Sampler,
         factory: &mut BackendFactory,
-    ) -> Texture<[f32; 4]> {
+        pending_mipmaps: &PendingMipmaps,
+        compressed_support: compressed::Support,
+    ) -> Result<Texture<[f32; 4]>, Error> {
         use gfx::texture as t;
-        //TODO: generate mipmaps
-        let format = Factory::parse_texture_format(path);
-        let file = fs::File::open(path).unwrap_or_else(|e| panic!("Unable to open {}: {:?}", path.display(), e));
-        let img = image::load(io::BufReader::new(file), format)
-            .unwrap_or_else(|e| panic!("Unable to decode {}: {:?}", path.display(), e))
-            .flipv()
-            .to_rgba();
-        let (width, height) = img.dimensions();
+        let image = match image {
+            TextureImage::Compressed(image) => {
+                if image.faces != 1 {
+                    return Err(Error::Unsupported("cube maps as 2D textures, see `Factory::load_cubemap`".into()));
+                }
+                let (width, height) = (image.width, image.height);
+                let kind = t::Kind::D2(width as t::Size, height as t::Size, t::AaMode::Single);
+                let view = Factory::upload_compressed(image, kind, factory, compressed_support, Some(pending_mipmaps))?;
+                return Ok(Texture::new(view, sampler.0, [width, height]));
+            }
+            TextureImage::Decoded(image) => image,
+        };
+        let (width, height) = match image {
+            DecodedImage::Srgba8(ref img) => img.dimensions(),
+            DecodedImage::Float(width, height, _) => (width, height),
+        };
         let kind = t::Kind::D2(width as t::Size, height as t::Size, t::AaMode::Single);
-        let (_, view) = factory
-            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &[&img])
-            .unwrap_or_else(|e| {
-                panic!(
-                    "Unable to create GPU texture for {}: {:?}",
-                    path.display(),
-                    e
-                )
-            });
-        Texture::new(view, sampler.0, [width, height])
+        let view = match image {
+            DecodedImage::Srgba8(img) => factory
+                .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Allocated, &[&img])?
+                .1,
+            DecodedImage::Float(_, _, data) => factory
+                .create_texture_immutable::<gfx::format::Rgba32F>(kind, t::Mipmap::Allocated, &[&float_texels(&data)])?
+                .1,
+        };
+        pending_mipmaps.lock().unwrap().push(view.raw().clone());
+        Ok(Texture::new(view, sampler.0, [width, height]))
+    }
+
+    fn load_texture_impl(
+        path: &Path,
+        sampler: Sampler,
+        factory: &mut BackendFactory,
+        pending_mipmaps: &PendingMipmaps,
+        compressed_support: compressed::Support,
+    ) -> Result<Texture<[f32; 4]>, Error> {
+        let image = Factory::read_texture(path)?;
+        Factory::upload_texture(image, sampler, factory, pending_mipmaps, compressed_support)
     }
 
     fn load_cubemap_impl<P: AsRef<Path>>(
         paths: &CubeMapPath<P>,
         sampler: Sampler,
         factory: &mut BackendFactory,
-    ) -> CubeMap<[f32; 4]> {
+        compressed_support: compressed::Support,
+    ) -> Result<CubeMap<[f32; 4]>, Error> {
         use gfx::texture as t;
-        let images = paths
-            .as_array()
-            .iter()
-            .map(|path| {
-                let format = Factory::parse_texture_format(path.as_ref());
-                let file = fs::File::open(path).unwrap_or_else(|e| panic!("Unable to open {}: {:?}", path.as_ref().display(), e));
-                image::load(io::BufReader::new(file), format)
-                    .unwrap_or_else(|e| panic!("Unable to decode {}: {:?}", path.as_ref().display(), e))
-                    .to_rgba()
-            })
-            .collect::<Vec<_>>();
+        if compressed::is_compressed(paths.right.as_ref()) {
+            let mut image = Factory::load_compressed_impl(paths.right.as_ref())?;
+            // A single cube map file may be given for all the faces.
+            if image.faces == 1 {
+                for path in &paths.as_array()[1 ..] {
+                    let face = Factory::load_compressed_impl(path.as_ref())?;
+                    if face.faces != 1 || face.format != image.format || face.srgb != image.srgb
+                        || (face.width, face.height, face.levels) != (image.width, image.height, image.levels)
+                    {
+                        return Err(Error::Decode(format!(
+                            "{} doesn't match the other cube map faces",
+                            path.as_ref().display()
+                        )));
+                    }
+                    image.data.extend(face.data);
+                }
+                image.faces = 6;
+            }
+            let kind = t::Kind::Cube(image.width as t::Size);
+            let view = Factory::upload_compressed(image, kind, factory, compressed_support, None)?;
+            return Ok(CubeMap::new(view, sampler.0));
+        }
+        let mut images = Vec::with_capacity(6);
+        for path in paths.as_array().iter() {
+            let format = Factory::parse_texture_format(path.as_ref())?;
+            let file = fs::File::open(path)?;
+            images.push(image::load(io::BufReader::new(file), format)?.to_rgba8());
+        }
         let data: [&[u8]; 6] = [
             &images[0], &images[1], &images[2], &images[3], &images[4], &images[5]
         ];
         let size = images[0].dimensions().0;
         let kind = t::Kind::Cube(size as t::Size);
-        let (_, view) = factory
-            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &data)
-            .unwrap_or_else(|e| {
-                panic!("Unable to create GPU texture for cubemap: {:?}", e);
-            });
-        CubeMap::new(view, sampler.0)
+        let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &data)?;
+        Ok(CubeMap::new(view, sampler.0))
     }
 
     fn request_texture<P: AsRef<Path>>(
         &mut self,
         path: P,
         sampler: Sampler,
-    ) -> Texture<[f32; 4]> {
-        match self.texture_cache.entry(path.as_ref().to_owned()) {
-            Entry::Occupied(e) => e.get().clone(),
+    ) -> Result<Texture<[f32; 4]>, Error> {
+        if let Some(texture) = self.texture_cache.get(path.as_ref()) {
+            return Ok(texture.clone());
+        }
+        let image = Factory::read_texture(path.as_ref())?;
+        self.cache_texture(path.as_ref(), image, sampler)
+    }
+
+    /// Uploads a texture read from `path`, unless the cache already has it.
+    fn cache_texture(
+        &mut self,
+        path: &Path,
+        image: TextureImage,
+        sampler: Sampler,
+    ) -> Result<Texture<[f32; 4]>, Error> {
+        match self.texture_cache.entry(path.to_owned()) {
+            Entry::Occupied(e) => Ok(e.get().clone()),
             Entry::Vacant(e) => {
-                let tex = Self::load_texture_impl(path.as_ref(), sampler, &mut self.backend);
+                let tex = Self::upload_texture(image, sampler, &mut self.backend, &self.pending_mipmaps, self.compressed_support)?;
                 e.insert(tex.clone());
-                tex
+                Ok(tex)
             }
         }
     }
 
+    /// Returns the cached texture of `path`, if it was already loaded.
+    fn cached_texture(&self, path: &Path) -> Option<Texture<[f32; 4]>> {
+        self.texture_cache.get(path).cloned()
+    }
+
     fn load_obj_material(
         &mut self,
         mat: &obj::Material,
         has_normals: bool,
         has_uv: bool,
         obj_dir: Option<&Path>,
-    ) -> Material {
+        textures: &mut HashMap<PathBuf, TextureImage>,
+    ) -> Result<Material, Error> {
         let cf2u = |c: [f32; 3]| {
             c.iter()
                 .fold(0, |u, &v| (u << 8) + cmp::min((v * 255.0) as u32, 0xFF))
         };
-        match *mat {
+        Ok(match *mat {
             obj::Material {
                 kd: Some(color),
                 ns: Some(glossiness),
@@ -1125,6 +1943,7 @@ impl Factory {
                 material::Phong {
                     color: cf2u(color),
                     glossiness,
+                    opacity: mat.d.unwrap_or(1.0),
                 }.into()
             }
             obj::Material {
@@ -1134,6 +1953,7 @@ impl Factory {
                 material::Lambert {
                     color: cf2u(color),
                     flat: false,
+                    opacity: mat.d.unwrap_or(1.0),
                 }.into()
             }
             obj::Material {
@@ -1145,19 +1965,27 @@ impl Factory {
                 map: match (has_uv, map_kd) {
                     (true, &Some(ref name)) => {
                         let sampler = self.default_sampler();
-                        Some(self.request_texture(&concat_path(obj_dir, name), sampler))
+                        let path = concat_path(obj_dir, name);
+                        Some(match textures.remove(&*path) {
+                            Some(image) => self.cache_texture(&path, image, sampler)?,
+                            None => self.request_texture(&path, sampler)?,
+                        })
                     },
                     _ => None,
                 },
+                opacity: mat.d.unwrap_or(1.0),
             }.into(),
             _ => material::Basic {
                 color: 0xffffff,
                 map: None,
+                .. Default::default()
             }.into(),
-        }
+        })
     }
 
     /// Load texture from pre-loaded data.
+    ///
+    /// `pixels` holds the base level in sRGB RGBA, the other mipmaps are generated.
     pub fn load_texture_from_memory(
         &mut self,
         width: u16,
@@ -1165,73 +1993,282 @@ impl Factory {
         pixels: &[u8],
         sampler: Sampler,
     ) -> Texture<[f32; 4]> {
+        self.try_load_texture_from_memory(width, height, pixels, sampler)
+            .unwrap_or_else(|e| panic!("Unable to create GPU texture from memory: {}", e))
+    }
+
+    /// Load texture from pre-loaded data, returning an error if the GPU texture can't be created.
+    pub fn try_load_texture_from_memory(
+        &mut self,
+        width: u16,
+        height: u16,
+        pixels: &[u8],
+        sampler: Sampler,
+    ) -> Result<Texture<[f32; 4]>, Error> {
+        use gfx::texture as t;
+        let kind = t::Kind::D2(width, height, t::AaMode::Single);
+        let (_, view) = self.backend
+            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Allocated, &[pixels])?;
+        self.pending_mipmaps.lock().unwrap().push(view.raw().clone());
+        Ok(Texture::new(view, sampler.0, [width as u32, height as u32]))
+    }
+
+    /// Load a floating point texture from pre-loaded data.
+    ///
+    /// `pixels` holds the base level in linear RGBA, the other mipmaps are generated.
+    pub fn load_float_texture_from_memory(
+        &mut self,
+        width: u16,
+        height: u16,
+        pixels: &[[f32; 4]],
+        sampler: Sampler,
+    ) -> Texture<[f32; 4]> {
+        self.try_load_float_texture_from_memory(width, height, pixels, sampler)
+            .unwrap_or_else(|e| panic!("Unable to create GPU texture from memory: {}", e))
+    }
+
+    /// Load a floating point texture from pre-loaded data,
+    /// returning an error if the GPU texture can't be created.
+    pub fn try_load_float_texture_from_memory(
+        &mut self,
+        width: u16,
+        height: u16,
+        pixels: &[[f32; 4]],
+        sampler: Sampler,
+    ) -> Result<Texture<[f32; 4]>, Error> {
         use gfx::texture as t;
         let kind = t::Kind::D2(width, height, t::AaMode::Single);
         let (_, view) = self.backend
-            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &[pixels])
-            .unwrap_or_else(|e| {
-                panic!("Unable to create GPU texture from memory: {:?}", e);
-            });
-        Texture::new(view, sampler.0, [width as u32, height as u32])
+            .create_texture_immutable::<gfx::format::Rgba32F>(kind, t::Mipmap::Allocated, &[&float_texels(pixels)])?;
+        self.pending_mipmaps.lock().unwrap().push(view.raw().clone());
+        Ok(Texture::new(view, sampler.0, [width as u32, height as u32]))
     }
 
     /// Load texture from file, with default `Sampler`.
-    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR.
+    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR, EXR, DDS, KTX2.
+    /// OpenEXR requires the `exr` feature.
+    ///
+    /// Radiance HDR and OpenEXR files become floating point textures keeping their range,
+    /// and so do 16-bit images. Their values are kept linear, as they are mostly used for
+    /// data such as normal and height maps, while 8-bit images are read as sRGB colors.
+    /// DDS and KTX2 files keep their mip levels. BC1 and BC3 blocks are uploaded as they are
+    /// when the driver supports S3TC, other block formats (BC2, BC4, BC5 and ETC2)
+    /// are decompressed when loading.
+    ///
+    /// #### Panics
+    /// Panics if the file can't be loaded, see [`try_load_texture`](#method.try_load_texture)
+    /// to handle the error instead.
     pub fn load_texture<P: AsRef<Path>>(
         &mut self,
         path_str: P,
     ) -> Texture<[f32; 4]> {
+        let sampler = self.default_sampler();
+        self.load_texture_with_sampler(path_str, sampler)
+    }
+
+    /// Load texture from file, with default `Sampler`, returning an error
+    /// if the file can't be read or decoded.
+    pub fn try_load_texture<P: AsRef<Path>>(
+        &mut self,
+        path_str: P,
+    ) -> Result<Texture<[f32; 4]>, Error> {
         let sampler = self.default_sampler();
         self.request_texture(path_str, sampler)
     }
 
     /// Load texture from file, with custom `Sampler`.
-    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR.
+    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR, EXR, DDS, KTX2.
     pub fn load_texture_with_sampler<P: AsRef<Path>>(
         &mut self,
         path_str: P,
         sampler: Sampler,
     ) -> Texture<[f32; 4]> {
+        let path = path_str.as_ref();
+        self.request_texture(path, sampler)
+            .unwrap_or_else(|e| panic!("Unable to load texture {}: {}", path.display(), e))
+    }
+
+    /// Load texture from file, with custom `Sampler`, returning an error
+    /// if the file can't be read or decoded.
+    pub fn try_load_texture_with_sampler<P: AsRef<Path>>(
+        &mut self,
+        path_str: P,
+        sampler: Sampler,
+    ) -> Result<Texture<[f32; 4]>, Error> {
         self.request_texture(path_str, sampler)
     }
 
     /// Load cubemap from files.
-    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR.
+    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR, DDS, KTX2.
+    /// A DDS or KTX2 cube map can be given as the path of every face.
     pub fn load_cubemap<P: AsRef<Path>>(
         &mut self,
         paths: &CubeMapPath<P>,
     ) -> CubeMap<[f32; 4]> {
-        Factory::load_cubemap_impl(paths, self.default_sampler(), &mut self.backend)
+        self.try_load_cubemap(paths)
+            .unwrap_or_else(|e| panic!("Unable to load cubemap: {}", e))
+    }
+
+    /// Load cubemap from files, returning an error if one of them can't be read or decoded.
+    pub fn try_load_cubemap<P: AsRef<Path>>(
+        &mut self,
+        paths: &CubeMapPath<P>,
+    ) -> Result<CubeMap<[f32; 4]>, Error> {
+        Factory::load_cubemap_impl(paths, self.default_sampler(), &mut self.backend, self.compressed_support)
+    }
+
+    /// Create an [`EnvironmentMap`] lighting the `Pbr` materials from a `cubemap`.
+    ///
+    /// #### Panics
+    /// Panics if the GPU cube maps can't be created,
+    /// see [`try_environment_map`](#method.try_environment_map) to handle the error instead.
+    ///
+    /// [`EnvironmentMap`]: struct.EnvironmentMap.html
+    pub fn environment_map(
+        &mut self,
+        cubemap: &CubeMap<[f32; 4]>,
+    ) -> EnvironmentMap {
+        self.try_environment_map(cubemap)
+            .unwrap_or_else(|e| panic!("Unable to create environment map: {}", e))
+    }
+
+    /// Create an [`EnvironmentMap`] lighting the `Pbr` materials from a `cubemap`,
+    /// returning an error if the GPU cube maps can't be created.
+    ///
+    /// [`EnvironmentMap`]: struct.EnvironmentMap.html
+    pub fn try_environment_map(
+        &mut self,
+        cubemap: &CubeMap<[f32; 4]>,
+    ) -> Result<EnvironmentMap, Error> {
+        self.environment_map_impl(EnvironmentSource::Cube(cubemap.clone()))
+    }
+
+    /// Load an [`EnvironmentMap`] from an equirectangular (latitude-longitude) image.
+    /// Radiance HDR and OpenEXR files keep their full range, 16-bit images are read as linear
+    /// values and other formats as sRGB.
+    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR, EXR, DDS, KTX2.
+    ///
+    /// [`EnvironmentMap`]: struct.EnvironmentMap.html
+    pub fn load_environment_map<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> EnvironmentMap {
+        let path = path.as_ref();
+        self.try_load_environment_map(path)
+            .unwrap_or_else(|e| panic!("Unable to load environment map {}: {}", path.display(), e))
+    }
+
+    /// Load an [`EnvironmentMap`] from an equirectangular image, returning an error
+    /// if the file can't be read or decoded.
+    ///
+    /// [`EnvironmentMap`]: struct.EnvironmentMap.html
+    pub fn try_load_environment_map<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<EnvironmentMap, Error> {
+        let sampler = self.sampler(FilterMethod::Bilinear, WrapMode::Tile, WrapMode::Clamp);
+        let map = Factory::load_texture_impl(path.as_ref(), sampler, &mut self.backend, &self.pending_mipmaps, self.compressed_support)?;
+        let (cube, mut targets) = self.hdr_cube_map(ENVIRONMENT_CUBE_SIZE, 1)?;
+        let targets = targets.remove(0);
+        self.environment_map_impl(EnvironmentSource::Equirect { map, cube, targets })
+    }
+
+    fn environment_map_impl(
+        &mut self,
+        source: EnvironmentSource,
+    ) -> Result<EnvironmentMap, Error> {
+        let (irradiance, mut irradiance_targets) = self.hdr_cube_map(IRRADIANCE_SIZE, 1)?;
+        let (specular, specular_targets) = self.hdr_cube_map(SPECULAR_SIZE, SPECULAR_LEVELS)?;
+        let sampler = self.sampler(FilterMethod::Trilinear, WrapMode::Clamp, WrapMode::Clamp);
+        Ok(EnvironmentMap {
+            source,
+            irradiance,
+            irradiance_targets: irradiance_targets.remove(0),
+            specular,
+            specular_targets,
+            sampler: sampler.0,
+            pending: Arc::new(AtomicBool::new(true)),
+        })
+    }
+
+    /// Creates a floating point cube map that can be rendered into,
+    /// together with the targets of the faces of each mip level.
+    fn hdr_cube_map(
+        &mut self,
+        size: u16,
+        levels: u8,
+    ) -> Result<(
+        gfx::handle::ShaderResourceView<BackendResources, [f32; 4]>,
+        Vec<[gfx::handle::RenderTargetView<BackendResources, HdrFormat>; 6]>,
+    ), Error> {
+        use gfx::format::{ChannelTyped, Formatted};
+        use gfx::texture as t;
+
+        let channel = <<HdrFormat as Formatted>::Channel as ChannelTyped>::get_channel_type();
+        let texture = self.backend
+            .create_texture(
+                t::Kind::Cube(size),
+                levels,
+                gfx::memory::Bind::SHADER_RESOURCE | gfx::memory::Bind::RENDER_TARGET,
+                gfx::memory::Usage::Data,
+                Some(channel),
+            )?;
+        let resource = self.backend
+            .view_texture_as_shader_resource::<HdrFormat>(&texture, (0, levels - 1), gfx::format::Swizzle::new())?;
+        let targets = (0 .. levels)
+            .map(|level| {
+                let mut face = |layer| {
+                    self.backend
+                        .view_texture_as_render_target::<HdrFormat>(&texture, level, Some(layer))
+                };
+                Ok([face(0)?, face(1)?, face(2)?, face(3)?, face(4)?, face(5)?])
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok((resource, targets))
     }
 
     /// Load mesh from Wavefront Obj format.
+    ///
+    /// #### Panics
+    /// Panics if the file or its materials can't be loaded,
+    /// see [`try_load_obj`](#method.try_load_obj) to handle the error instead.
     pub fn load_obj(
         &mut self,
         path_str: &str,
     ) -> (HashMap<String, object::Group>, Vec<Mesh>) {
+        self.try_load_obj(path_str)
+            .unwrap_or_else(|e| panic!("Unable to load {}: {}", path_str, e))
+    }
+
+    /// Load mesh from Wavefront Obj format, returning an error if the file
+    /// or its materials can't be read or parsed.
+    pub fn try_load_obj(
+        &mut self,
+        path_str: &str,
+    ) -> Result<(HashMap<String, object::Group>, Vec<Mesh>), Error> {
+        let data = Factory::read_obj(path_str)?;
+        self.build_obj(data)
+    }
+
+    /// Parses an OBJ file and its materials, triangulating every group.
+    fn read_obj(path_str: &str) -> Result<ObjData, Error> {
         use genmesh::{Indexer, LruIndexer, Polygon, Triangulate, Vertices};
 
         info!("Loading {}", path_str);
         let path = Path::new(path_str);
-        let path_parent = path.parent();
-        let mut obj = obj::Obj::load(path).unwrap();
-        obj.load_mtls().unwrap();
-
-        let hub_ptr = self.hub.clone();
-        let mut hub = hub_ptr.lock().unwrap();
-        let mut groups = HashMap::new();
-        let mut meshes = Vec::new();
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
+        let mut obj = obj::Obj::load(path)?;
+        obj.load_mtls()?;
 
+        let mut objects = Vec::new();
         for object in &obj.data.objects {
-            let group = object::Group::new(&mut *hub);
+            let mut meshes = Vec::new();
             for gr in &object.groups {
                 let (mut num_normals, mut num_uvs) = (0, 0);
+                let mut vertices = Vec::new();
+                let mut indices = Vec::new();
                 {
                     // separate scope for LruIndexer
                     let f2i = |x: f32| I8Norm(cmp::min(cmp::max((x * 127.) as isize, -128), 127) as i8);
-                    vertices.clear();
                     let mut lru = LruIndexer::new(10, |_, obj::IndexTuple(ipos, iuv, inor)| {
                         let p: [f32; 3] = obj.data.position[ipos];
                         vertices.push(Vertex {
@@ -1255,7 +2292,6 @@ impl Factory {
                         });
                     });
 
-                    indices.clear();
                     indices.extend(
                         gr.polys
                             .iter()
@@ -1267,30 +2303,91 @@ impl Factory {
                     );
                 };
 
+                meshes.push(ObjMesh {
+                    name: gr.name.clone(),
+                    vertices,
+                    indices,
+                    material: match gr.material {
+                        Some(obj::ObjMaterial::Mtl(ref rc_mat)) => Some(rc_mat.clone()),
+                        _ => None,
+                    },
+                    num_normals,
+                    num_uvs,
+                });
+            }
+            objects.push((object.name.clone(), meshes));
+        }
+
+        Ok(ObjData {
+            objects,
+            dir: path.parent().map(Path::to_owned),
+            textures: HashMap::new(),
+        })
+    }
+
+    /// Decodes the textures used by the materials of `data` ahead of time.
+    fn read_obj_textures(data: &mut ObjData) -> Result<(), Error> {
+        for &(_, ref meshes) in &data.objects {
+            for mesh in meshes {
+                let name = match mesh.material {
+                    Some(ref mat) if mat.kd.is_some() && mesh.num_normals == 0 && mesh.num_uvs != 0 => match mat.map_kd {
+                        Some(ref name) => name,
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                let path = concat_path(data.dir.as_ref().map(PathBuf::as_path), name);
+                if !data.textures.contains_key(&*path) {
+                    let image = Factory::read_texture(&path)?;
+                    data.textures.insert(path.into_owned(), image);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the GPU buffers, materials and groups of a parsed OBJ file.
+    fn build_obj(
+        &mut self,
+        data: ObjData,
+    ) -> Result<(HashMap<String, object::Group>, Vec<Mesh>), Error> {
+        let ObjData { objects, dir, mut textures } = data;
+        let hub_ptr = self.hub.clone();
+        let mut hub = hub_ptr.lock().unwrap();
+        let mut groups = HashMap::new();
+        let mut meshes = Vec::new();
+
+        for (object_name, object_meshes) in objects {
+            let group = object::Group::new(&mut *hub);
+            for ObjMesh { name, vertices, indices, material, num_normals, num_uvs } in object_meshes {
                 info!(
                     "\tmaterial {} with {} normals and {} uvs",
-                    gr.name, num_normals, num_uvs
+                    name, num_normals, num_uvs
                 );
-                let material = match gr.material {
-                    Some(obj::ObjMaterial::Mtl(ref rc_mat)) => self.load_obj_material(&*rc_mat, num_normals != 0, num_uvs != 0, path_parent),
-                    _ => material::Basic {
+                let material = match material {
+                    Some(ref mat) => self.load_obj_material(mat, num_normals != 0, num_uvs != 0, dir.as_ref().map(PathBuf::as_path), &mut textures)?,
+                    None => material::Basic {
                         color: 0xFFFFFF,
                         map: None,
+                        .. Default::default()
                     }.into(),
                 };
                 info!("\t{:?}", material);
 
+                let bounds = BoundingSphere::from_points(
+                    vertices.iter().map(|v| Point3::new(v.pos[0], v.pos[1], v.pos[2])),
+                );
+                let triangles = Triangles::new(
+                    vertices.iter().map(|v| Point3::new(v.pos[0], v.pos[1], v.pos[2])).collect(),
+                    indices
+                        .chunks(3)
+                        .map(|tri| [tri[0] as u32, tri[1] as u32, tri[2] as u32])
+                        .collect(),
+                );
                 let (vertices, mut slice) = self.backend
                     .create_vertex_buffer_with_slice(&vertices, &indices[..]);
                 slice.instances = Some((1, 0));
-                let instances = self.backend
-                    .create_buffer(
-                        1,
-                        gfx::buffer::Role::Vertex,
-                        gfx::memory::Usage::Dynamic,
-                        gfx::memory::Bind::TRANSFER_DST,
-                    )
-                    .unwrap();
+                let instances = self.create_instance_buffer()?;
                 let mesh = Mesh {
                     object: hub.spawn_visual(
                         material,
@@ -1302,6 +2399,8 @@ impl Factory {
                             pending: None,
                             instance_cache_key: None,
                             displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                            bounds,
+                            triangles: Some(Arc::new(triangles)),
                         },
                         None,
                     ),
@@ -1310,28 +2409,66 @@ impl Factory {
                 meshes.push(mesh);
             }
 
-            groups.insert(object.name.clone(), group);
+            groups.insert(object_name, group);
         }
 
-        (groups, meshes)
+        Ok((groups, meshes))
     }
 
     #[cfg(feature = "audio")]
     /// Load audio from file. Supported formats are Flac, Vorbis and WAV.
+    ///
+    /// #### Panics
+    /// Panics if the file can't be loaded,
+    /// see [`try_load_audio`](#method.try_load_audio) to handle the error instead.
     pub fn load_audio<P: AsRef<Path>>(
         &self,
         path: P,
     ) -> audio::Clip {
+        let path = path.as_ref();
+        self.try_load_audio(path)
+            .unwrap_or_else(|e| panic!("Unable to load audio {}: {}", path.display(), e))
+    }
+
+    #[cfg(feature = "audio")]
+    /// Load audio from file, returning an error if it can't be read or its format isn't recognized.
+    pub fn try_load_audio<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<audio::Clip, Error> {
         let mut buffer = Vec::new();
-        let mut file = fs::File::open(&path).expect(&format!(
-            "Can't open audio file:\nFile: {}",
-            path.as_ref().display()
-        ));
-        file.read_to_end(&mut buffer).expect(&format!(
-            "Can't read audio file:\nFile: {}",
-            path.as_ref().display()
-        ));
-        audio::Clip::new(buffer)
+        let mut file = fs::File::open(&path)?;
+        file.read_to_end(&mut buffer)?;
+        audio::Clip::try_new(buffer)
+    }
+
+    #[cfg(feature = "audio")]
+    /// Open audio file for streaming. Supported formats are the same as for
+    /// [`load_audio`](#method.load_audio).
+    ///
+    /// The file is decoded from the disk while playing instead of being read
+    /// into memory, which suits long music tracks.
+    ///
+    /// #### Panics
+    /// Panics if the file can't be opened or decoded,
+    /// see [`try_load_audio_stream`](#method.try_load_audio_stream) to handle the error instead.
+    pub fn load_audio_stream<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> audio::Clip {
+        let path = path.as_ref();
+        self.try_load_audio_stream(path)
+            .unwrap_or_else(|e| panic!("Unable to load audio {}: {}", path.display(), e))
+    }
+
+    #[cfg(feature = "audio")]
+    /// Open audio file for streaming, returning an error if it can't be opened
+    /// or its format isn't recognized.
+    pub fn try_load_audio_stream<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<audio::Clip, Error> {
+        audio::Clip::try_stream(path.as_ref())
     }
 }
 
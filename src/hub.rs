@@ -3,16 +3,16 @@ use audio::{AudioData, Operation as AudioOperation};
 
 use camera::Projection;
 use color::{self, Color};
-use light::{LightOperation, ShadowMap, ShadowProjection};
+use light::{CascadedShadowMap, CubeShadowMap, LightOperation, ShadowMap, ShadowProjection};
 use material::Material;
 use mesh::DynamicMesh;
-use node::{NodeInternal, NodePointer, TransformInternal};
+use node::{NodeInternal, NodePointer};
 use object::Base;
 use render::{BackendResources, GpuData};
 use skeleton::{Bone, Skeleton};
 use text::{Operation as TextOperation, TextData};
 
-use cgmath::Transform;
+use cgmath::{InnerSpace, Matrix4, Quaternion};
 use froggy;
 use gfx;
 use mint;
@@ -28,6 +28,7 @@ pub(crate) enum SubLight {
     Directional,
     Hemisphere { ground: Color },
     Point,
+    Spot { angle: f32, penumbra: f32 },
 }
 
 #[derive(Clone, Debug)]
@@ -36,6 +37,21 @@ pub(crate) struct LightData {
     pub intensity: f32,
     pub sub_light: SubLight,
     pub shadow: Option<(ShadowMap, ShadowProjection)>,
+    pub cube_shadow: Option<(CubeShadowMap, ops::Range<f32>)>,
+    pub cascaded_shadow: Option<(CascadedShadowMap, f32)>,
+}
+
+impl LightData {
+    /// Makes the perspective shadow of a spot light cover its cone.
+    fn fit_spot_shadow(&mut self) {
+        let angle = match self.sub_light {
+            SubLight::Spot { angle, .. } => angle,
+            _ => return,
+        };
+        if let Some((_, ShadowProjection::Perspective(ref mut projection))) = self.shadow {
+            projection.fov_y = 2.0 * angle;
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -87,11 +103,19 @@ pub(crate) enum Operation {
     SetTransform(
         Option<mint::Point3<f32>>,
         Option<mint::Quaternion<f32>>,
-        Option<f32>,
+        Option<mint::Vector3<f32>>,
+    ),
+    /// Animated values, each blended with the rest transform by its weight.
+    BlendTransform(
+        Option<(mint::Point3<f32>, f32)>,
+        Option<(mint::Quaternion<f32>, f32)>,
+        Option<(mint::Vector3<f32>, f32)>,
     ),
     SetMaterial(Material),
     SetSkeleton(Skeleton),
     SetShadow(ShadowMap, ShadowProjection),
+    SetCubeShadow(CubeShadowMap, ops::Range<f32>),
+    SetCascadedShadow(CascadedShadowMap, f32),
     SetTexelRange(mint::Point2<i16>, mint::Vector2<u16>),
     SetWeights(Vec<f32>),
     SetName(String),
@@ -190,16 +214,40 @@ impl Hub {
                     self.nodes[&ptr].visible = visible;
                 }
                 Operation::SetTransform(pos, rot, scale) => {
-                    let transform = &mut self.nodes[&ptr].transform;
-                    if let Some(pos) = pos {
-
-                        transform.disp = mint::Vector3::from(pos).into();
+                    let node = &mut self.nodes[&ptr];
+                    for transform in Some(&mut node.transform).into_iter().chain(node.rest_transform.as_mut()) {
+                        if let Some(pos) = pos {
+                            transform.disp = mint::Vector3::from(pos).into();
+                        }
+                        if let Some(rot) = rot {
+                            transform.rot = rot.into();
+                        }
+                        if let Some(scale) = scale {
+                            transform.scale = scale.into();
+                        }
                     }
-                    if let Some(rot) = rot {
-                        transform.rot = rot.into();
+                }
+                Operation::BlendTransform(pos, rot, scale) => {
+                    let node = &mut self.nodes[&ptr];
+                    let rest = *node.rest_transform.get_or_insert(node.transform);
+                    let transform = &mut node.transform;
+                    if let Some((pos, weight)) = pos {
+                        let pos = mint::Vector3::from(pos).into();
+                        transform.disp = if weight < 1.0 { rest.disp.lerp(pos, weight) } else { pos };
                     }
-                    if let Some(scale) = scale {
-                        transform.scale = scale;
+                    if let Some((rot, weight)) = rot {
+                        let rot: Quaternion<f32> = rot.into();
+                        transform.rot = if weight < 1.0 {
+                            // Take the shortest path between the orientations.
+                            let rot = if rest.rot.dot(rot) < 0.0 { -rot } else { rot };
+                            rest.rot.slerp(rot, weight)
+                        } else {
+                            rot
+                        };
+                    }
+                    if let Some((scale, weight)) = scale {
+                        let scale = scale.into();
+                        transform.scale = if weight < 1.0 { rest.scale.lerp(scale, weight) } else { scale };
                     }
                 }
                 Operation::AddChild(child_ptr) => {
@@ -281,10 +329,29 @@ impl Hub {
                     match self.nodes[&ptr].sub_node {
                         SubNode::Light(ref mut data) => {
                             data.shadow = Some((map, proj));
+                            data.cascaded_shadow = None;
+                            data.fit_spot_shadow();
                         },
                     _ => unreachable!()
                     }
                 }
+                Operation::SetCubeShadow(map, range) => {
+                    match self.nodes[&ptr].sub_node {
+                        SubNode::Light(ref mut data) => {
+                            data.cube_shadow = Some((map, range));
+                        },
+                        _ => unreachable!()
+                    }
+                }
+                Operation::SetCascadedShadow(map, distance) => {
+                    match self.nodes[&ptr].sub_node {
+                        SubNode::Light(ref mut data) => {
+                            data.shadow = None;
+                            data.cascaded_shadow = Some((map, distance));
+                        },
+                        _ => unreachable!()
+                    }
+                }
                 Operation::SetTexelRange(base, size) => {
                     match self.nodes[&ptr].sub_node {
                         SubNode::Visual(Material::Sprite(ref mut params), _, _) => {
@@ -346,11 +413,13 @@ impl Hub {
         data: &mut AudioData,
     ) {
         match operation {
-            AudioOperation::Append(clip) => data.source.append(clip),
-            AudioOperation::Pause => data.source.pause(),
-            AudioOperation::Resume => data.source.resume(),
-            AudioOperation::Stop => data.source.stop(),
-            AudioOperation::SetVolume(volume) => data.source.set_volume(volume),
+            AudioOperation::Append(clip) => data.append(clip),
+            AudioOperation::Crossfade(clip, duration) => data.crossfade(clip, duration),
+            AudioOperation::Pause => data.pause(),
+            AudioOperation::Resume => data.resume(),
+            AudioOperation::Stop => data.stop(),
+            AudioOperation::SetVolume(volume) => data.set_volume(volume),
+            AudioOperation::SetBus(bus) => data.set_bus(bus),
         }
     }
 
@@ -361,6 +430,17 @@ impl Hub {
         match operation {
             LightOperation::Color(color) => data.color = color,
             LightOperation::Intensity(intensity) => data.intensity = intensity,
+            LightOperation::SpotAngle(value) => {
+                match data.sub_light {
+                    SubLight::Spot { ref mut angle, .. } => *angle = value,
+                    _ => unreachable!(),
+                }
+                data.fit_spot_shadow();
+            }
+            LightOperation::SpotPenumbra(value) => match data.sub_light {
+                SubLight::Spot { ref mut penumbra, .. } => *penumbra = value,
+                _ => unreachable!(),
+            },
         }
     }
 
@@ -422,7 +502,7 @@ pub(crate) struct WalkedNode<'a> {
     pub(crate) node_ptr: NodePointer,
     pub(crate) node: &'a NodeInternal,
     pub(crate) world_visible: bool,
-    pub(crate) world_transform: TransformInternal,
+    pub(crate) world_transform: Matrix4<f32>,
 }
 
 pub(crate) struct TreeWalker<'a> {
@@ -446,13 +526,13 @@ impl<'a> TreeWalker<'a> {
                     node_ptr: ptr.clone(),
                     node,
                     world_visible: parent.world_visible && node.visible,
-                    world_transform: parent.world_transform.concat(&node.transform),
+                    world_transform: parent.world_transform * Matrix4::from(node.transform),
                 },
                 None => WalkedNode {
                     node_ptr: ptr.clone(),
                     node,
                     world_visible: node.visible,
-                    world_transform: node.transform,
+                    world_transform: node.transform.into(),
                 },
             };
             self.stack.push(wn);
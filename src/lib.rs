@@ -242,12 +242,16 @@
 //! [`Window`]: window/struct.Window.html
 //! [`three_object`]: macro.three_object.html
 
+#[cfg(feature = "audio")]
+extern crate arc_swap;
 extern crate arrayvec;
 #[macro_use]
 extern crate bitflags;
 extern crate cgmath;
 #[macro_use]
 extern crate derivative;
+#[cfg(feature = "exr")]
+extern crate exr;
 extern crate froggy;
 extern crate genmesh;
 #[macro_use]
@@ -289,6 +293,7 @@ pub mod color;
 pub mod controls;
 pub mod custom;
 mod data;
+mod error;
 mod factory;
 mod geometry;
 mod hub;
@@ -298,6 +303,7 @@ pub mod material;
 mod mesh;
 mod node;
 pub mod object;
+pub mod raycast;
 pub mod render;
 pub mod scene;
 pub mod skeleton;
@@ -320,7 +326,10 @@ pub use controls::{AXIS_DOWN_UP, AXIS_LEFT_RIGHT, KEY_ESCAPE, KEY_SPACE, MOUSE_L
 pub use controls::{Button, MouseButton, Input, Timer};
 
 #[doc(inline)]
-pub use factory::Factory;
+pub use error::Error;
+
+#[doc(inline)]
+pub use factory::{Factory, Loading};
 
 #[doc(inline)]
 pub use geometry::{Geometry, Joints, Shape};
@@ -339,11 +348,14 @@ pub use material::Material;
 pub use mesh::{DynamicMesh, Mesh};
 
 #[doc(inline)]
-pub use node::{Node, Transform, Local, World};
+pub use node::{Node, Scale, Transform, Local, World};
 
 #[doc(inline)]
 pub use object::{Group, Object};
 
+#[doc(inline)]
+pub use raycast::{Intersection, Ray, Raycaster};
+
 #[doc(inline)]
 pub use render::Renderer;
 
@@ -357,7 +369,7 @@ pub use sprite::Sprite;
 pub use text::{Align, Font, Layout, Text};
 
 #[doc(inline)]
-pub use texture::{CubeMap, CubeMapPath, FilterMethod, Sampler, Texture, WrapMode};
+pub use texture::{CubeMap, CubeMapPath, EnvironmentMap, FilterMethod, Sampler, Texture, WrapMode};
 
 #[cfg(feature = "opengl")]
 #[doc(inline)]
@@ -4,16 +4,25 @@ use gfx;
 use object::{Base, Object, ObjectType};
 use std::ops;
 
-use camera::Orthographic;
+use camera::{Orthographic, Perspective};
 use color::Color;
 use hub::{self, Operation, SubLight, SubNode};
 use render::{BackendResources, ShadowFormat};
 use scene::SyncGuard;
+use util;
+
+/// Widest angle of a spot light cone, in degrees.
+///
+/// The perspective shadow of the cone has a field of view of twice its angle,
+/// which has to stay below 180 degrees.
+pub(crate) const MAX_SPOT_ANGLE: f32 = 89.0;
 
 #[derive(Debug)]
 pub(crate) enum LightOperation {
     Color(Color),
     Intensity(f32),
+    SpotAngle(f32),
+    SpotPenumbra(f32),
 }
 
 /// Marks light sources and implements their common methods.
@@ -41,18 +50,93 @@ impl Light for Ambient {}
 impl Light for Directional {}
 impl Light for Hemisphere {}
 impl Light for Point {}
+impl Light for Spot {}
+
+/// Bias and filtering of a shadow map, used according to the
+/// [`ShadowType`](../render/enum.ShadowType.html) of the renderer.
+#[derive(Derivative)]
+#[derivative(Clone, Copy, Debug, PartialEq, Hash, Eq)]
+pub struct ShadowFilter {
+    /// Depth offset, within the `0.0 .. 1.0` depth range of the map, avoiding
+    /// shadow acne on surfaces facing the light.
+    ///
+    /// Default: `0.0005`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub bias: f32,
+    /// Depth offset scaled by the slope of the surface relative to the light,
+    /// avoiding shadow acne on surfaces at grazing angles.
+    ///
+    /// Too large offsets detach the shadows from their casters.
+    ///
+    /// Default: `0.001`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub slope_bias: f32,
+    /// Width of the filter in texels with `Pcf` and `Vsm`, or of the widest penumbra with `Pcss`.
+    ///
+    /// Default: `3`.
+    pub kernel_size: u8,
+    /// Size of the light with `Pcss`, as a fraction of the width of the map.
+    /// Larger lights cast softer shadows.
+    ///
+    /// Default: `0.02`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub light_size: f32,
+}
+
+impl Default for ShadowFilter {
+    fn default() -> Self {
+        ShadowFilter {
+            bias: 0.0005,
+            slope_bias: 0.001,
+            kernel_size: 3,
+            light_size: 0.02,
+        }
+    }
+}
 
-/// `ShadowMap` is used to render shadows from [`PointLight`](struct.PointLight.html)
-/// and [`DirectionalLight`](struct.DirectionalLight.html).
+/// `ShadowMap` is used to render shadows from [`Directional`](struct.Directional.html)
+/// and [`Spot`](struct.Spot.html) lights.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ShadowMap {
     pub(crate) resource: gfx::handle::ShaderResourceView<BackendResources, f32>,
     pub(crate) target: gfx::handle::DepthStencilView<BackendResources, ShadowFormat>,
+    pub(crate) filter: ShadowFilter,
+}
+
+/// `CubeShadowMap` is used to render omnidirectional shadows from
+/// [`Point`](struct.Point.html) lights.
+///
+/// Each face of the cube is rendered separately, so a shadow casting point light
+/// costs six shadow passes per frame.
+///
+/// `Pcss` and `Vsm` filtering fall back to `Pcf` for cube maps.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CubeShadowMap {
+    pub(crate) resource: gfx::handle::ShaderResourceView<BackendResources, f32>,
+    /// Targets of the `+X`, `-X`, `+Y`, `-Y`, `+Z` and `-Z` faces.
+    pub(crate) targets: [gfx::handle::DepthStencilView<BackendResources, ShadowFormat>; 6],
+    pub(crate) filter: ShadowFilter,
+}
+
+/// `CascadedShadowMap` is used to render shadows from [`Directional`](struct.Directional.html)
+/// lights over large areas.
+///
+/// The camera frustum is split into cascades by distance, each with its own layer of the map,
+/// so shadows close to the camera get more texels than the distant ones.
+///
+/// `Pcss` and `Vsm` filtering fall back to `Pcf` for cascaded maps.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CascadedShadowMap {
+    pub(crate) resource: gfx::handle::ShaderResourceView<BackendResources, f32>,
+    /// Targets of the cascades, from the closest to the farthest.
+    pub(crate) targets: Vec<gfx::handle::DepthStencilView<BackendResources, ShadowFormat>>,
+    pub(crate) filter: ShadowFilter,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum ShadowProjection {
     Orthographic(Orthographic),
+    Perspective(Perspective),
 }
 
 impl ShadowMap {
@@ -63,6 +147,63 @@ impl ShadowMap {
     pub(crate) fn to_resource(&self) -> gfx::handle::ShaderResourceView<BackendResources, f32> {
         self.resource.clone()
     }
+
+    /// Returns the bias and filtering of this map.
+    pub fn filter(&self) -> ShadowFilter {
+        self.filter
+    }
+
+    /// Changes the bias and filtering of this map.
+    ///
+    /// Lights keep their own copy of the map, so call this before attaching it with `set_shadow`.
+    pub fn set_filter(
+        &mut self,
+        filter: ShadowFilter,
+    ) {
+        self.filter = filter;
+    }
+}
+
+impl CubeShadowMap {
+    pub(crate) fn to_resource(&self) -> gfx::handle::ShaderResourceView<BackendResources, f32> {
+        self.resource.clone()
+    }
+
+    /// Returns the bias and filtering of this map.
+    pub fn filter(&self) -> ShadowFilter {
+        self.filter
+    }
+
+    /// Changes the bias and filtering of this map.
+    ///
+    /// Lights keep their own copy of the map, so call this before attaching it with `Point::set_shadow`.
+    pub fn set_filter(
+        &mut self,
+        filter: ShadowFilter,
+    ) {
+        self.filter = filter;
+    }
+}
+
+impl CascadedShadowMap {
+    pub(crate) fn to_resource(&self) -> gfx::handle::ShaderResourceView<BackendResources, f32> {
+        self.resource.clone()
+    }
+
+    /// Returns the bias and filtering of this map.
+    pub fn filter(&self) -> ShadowFilter {
+        self.filter
+    }
+
+    /// Changes the bias and filtering of this map.
+    ///
+    /// Lights keep their own copy of the map, so call this before attaching it with `Directional::set_cascaded_shadow`.
+    pub fn set_filter(
+        &mut self,
+        filter: ShadowFilter,
+    ) {
+        self.filter = filter;
+    }
 }
 
 /// Omni-directional, fixed-intensity and fixed-color light source that affects
@@ -111,6 +252,9 @@ impl Directional {
     }
 
     /// Adds or updates the shadow map for this light source.
+    ///
+    /// At most two directional and spot lights cast shadows from a `ShadowMap` in
+    /// the same frame, the maps of further lights are ignored with an error logged.
     pub fn set_shadow(
         &mut self,
         map: ShadowMap,
@@ -125,6 +269,19 @@ impl Directional {
         let msg = Operation::SetShadow(map, sp);
         let _ = self.object.tx.send((self.object.node.downgrade(), msg));
     }
+
+    /// Adds or updates the cascaded shadow map for this light source,
+    /// replacing the regular shadow map if there was one.
+    ///
+    /// Shadows are cast up to `distance` away from the camera.
+    pub fn set_cascaded_shadow(
+        &mut self,
+        map: CascadedShadowMap,
+        distance: f32,
+    ) {
+        let msg = Operation::SetCascadedShadow(map, distance);
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
 }
 
 impl AsRef<Base> for Directional {
@@ -191,6 +348,21 @@ impl Point {
     pub(crate) fn new(object: Base) -> Self {
         Point { object }
     }
+
+    /// Adds or updates the omnidirectional shadow map for this light source.
+    ///
+    /// Only objects between `range.start` and `range.end` away from the light cast shadows.
+    ///
+    /// Only one point light casts shadows in a frame, the cube maps
+    /// of the other ones are ignored with an error logged.
+    pub fn set_shadow(
+        &mut self,
+        map: CubeShadowMap,
+        range: ops::Range<f32>,
+    ) {
+        let msg = Operation::SetCubeShadow(map, range);
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
 }
 
 impl AsRef<Base> for Point {
@@ -210,6 +382,78 @@ impl Object for Point {
 
 derive_DowncastObject!(Point => ObjectType::PointLight);
 
+/// Light originates from a single point, and spreads outward in a cone.
+///
+/// The cone is pointing along the local negative Z axis, use
+/// [`look_at`](../object/trait.Object.html#method.look_at) to aim it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Spot {
+    pub(crate) object: Base,
+}
+
+impl Spot {
+    pub(crate) fn new(object: Base) -> Self {
+        Spot { object }
+    }
+
+    /// Change the angle between the axis and the edge of the cone, in degrees.
+    ///
+    /// The angle is clamped to the range [0.0, 89.0].
+    pub fn set_angle(
+        &self,
+        angle: f32,
+    ) {
+        let msg = Operation::SetLight(LightOperation::SpotAngle(angle.clamp(0.0, MAX_SPOT_ANGLE)));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Change the part of the cone, in range [0.0, 1.0], where the light fades out
+    /// towards the edge.
+    pub fn set_penumbra(
+        &self,
+        penumbra: f32,
+    ) {
+        let msg = Operation::SetLight(LightOperation::SpotPenumbra(penumbra));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Adds or updates the shadow map for this light source.
+    ///
+    /// The shadow is projected in perspective and follows the cone angle.
+    /// At most two directional and spot lights cast shadows from a `ShadowMap` in
+    /// the same frame, the maps of further lights are ignored with an error logged.
+    pub fn set_shadow(
+        &mut self,
+        map: ShadowMap,
+        range: ops::Range<f32>,
+    ) {
+        let sp = ShadowProjection::Perspective(Perspective {
+            // Fitted to the cone when the hub receives the shadow.
+            fov_y: 0.0,
+            zrange: range.into(),
+        });
+        let msg = Operation::SetShadow(map, sp);
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+}
+
+impl AsRef<Base> for Spot {
+    fn as_ref(&self) -> &Base { &self.object }
+}
+
+impl Object for Spot {
+    type Data = SpotLightData;
+
+    fn resolve_data(&self, sync_guard: &SyncGuard) -> Self::Data {
+        match &sync_guard.hub[self].sub_node {
+            SubNode::Light(ref light_data) => light_data.into(),
+            sub_node @ _ => panic!("`Spot` had a bad sub node type: {:?}", sub_node),
+        }
+    }
+}
+
+derive_DowncastObject!(Spot => ObjectType::SpotLight);
+
 /// Internal data for [`Ambient`], [`Directional`], and [`Point`] lights.
 ///
 /// [`Ambient`]: ./struct.Ambient.html
@@ -261,3 +505,36 @@ impl<'a> From<&'a hub::LightData> for HemisphereLightData {
         }
     }
 }
+
+/// Internal data for [`Spot`] lights.
+///
+/// [`Spot`]: ./struct.Spot.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpotLightData {
+    /// The color of the light.
+    pub color: Color,
+
+    /// The intensity of the light.
+    pub intensity: f32,
+
+    /// The angle between the axis and the edge of the cone, in degrees.
+    pub angle: f32,
+
+    /// The part of the cone where the light fades out.
+    pub penumbra: f32,
+}
+
+impl<'a> From<&'a hub::LightData> for SpotLightData {
+    fn from(from: &'a hub::LightData) -> Self {
+        let (angle, penumbra) = match from.sub_light {
+            SubLight::Spot { angle, penumbra } => (angle, penumbra),
+            _ => panic!("Bad sub-light for `Spot`: {:?}", from.sub_light),
+        };
+        SpotLightData {
+            color: from.color,
+            intensity: from.intensity,
+            angle,
+            penumbra,
+        }
+    }
+}
@@ -17,7 +17,8 @@ pub mod basic {
     /// Parameters for a basic solid mesh material.
     ///
     /// Renders triangle meshes with a solid color or texture.
-    #[derive(Clone, Hash, Debug, PartialEq, Eq)]
+    #[derive(Derivative)]
+    #[derivative(Clone, Debug, PartialEq, Hash, Eq)]
     pub struct Basic {
         /// Solid color applied in the absence of `map`.
         ///
@@ -28,6 +29,15 @@ pub mod basic {
         ///
         /// Default: `None`.
         pub map: Option<Texture<[f32; 4]>>,
+
+        /// Opacity in the range [0.0, 1.0]. Values below 1.0 make the mesh
+        /// transparent, see [`Material::is_transparent`].
+        ///
+        /// Default: `1.0` (opaque).
+        ///
+        /// [`Material::is_transparent`]: ../enum.Material.html#method.is_transparent
+        #[derivative(Hash(hash_with = "util::hash_f32"))]
+        pub opacity: f32,
     }
 
     impl Default for Basic {
@@ -35,6 +45,7 @@ pub mod basic {
             Self {
                 color: color::WHITE,
                 map: None,
+                opacity: 1.0,
             }
         }
     }
@@ -61,7 +72,8 @@ pub mod basic {
 /// Parameters for a Lamberian diffusion reflection model.
 ///
 /// Renders triangle meshes with the Gouraud illumination model.
-#[derive(Clone, Hash, Debug, PartialEq, Eq)]
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct Lambert {
     /// Solid color applied in the absense of `map`.
     ///
@@ -72,6 +84,12 @@ pub struct Lambert {
     ///
     /// Default: `false` (lighting is interpolated across faces).
     pub flat: bool,
+
+    /// Opacity in the range [0.0, 1.0]. Values below 1.0 make the mesh transparent.
+    ///
+    /// Default: `1.0` (opaque).
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub opacity: f32,
 }
 
 impl Default for Lambert {
@@ -79,6 +97,7 @@ impl Default for Lambert {
         Self {
             color: color::WHITE,
             flat: false,
+            opacity: 1.0,
         }
     }
 }
@@ -179,6 +198,12 @@ pub struct Pbr {
     ///
     /// Default: `None`.
     pub occlusion_map: Option<Texture<[f32; 4]>>,
+
+    /// Blend the mesh using the alpha of the base color, even when
+    /// `base_color_alpha` is 1.0. Useful for base color maps with transparent texels.
+    ///
+    /// Default: `false`.
+    pub transparent: bool,
 }
 
 impl Default for Pbr {
@@ -196,6 +221,7 @@ impl Default for Pbr {
             emissive_map: None,
             metallic_roughness_map: None,
             occlusion_map: None,
+            transparent: false,
         }
     }
 }
@@ -218,6 +244,12 @@ pub struct Phong {
     /// Default: `30.0`.
     #[derivative(Hash(hash_with = "util::hash_f32"))]
     pub glossiness: f32,
+
+    /// Opacity in the range [0.0, 1.0]. Values below 1.0 make the mesh transparent.
+    ///
+    /// Default: `1.0` (opaque).
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub opacity: f32,
 }
 
 impl Default for Phong {
@@ -225,6 +257,7 @@ impl Default for Phong {
         Self {
             color: color::WHITE,
             glossiness: 30.0,
+            opacity: 1.0,
         }
     }
 }
@@ -283,6 +316,21 @@ pub enum Material {
     Wireframe(Wireframe),
 }
 
+impl Material {
+    /// Returns `true` if meshes with this material are alpha blended.
+    ///
+    /// Transparent meshes are drawn after all the opaque ones, sorted from back to front.
+    pub fn is_transparent(&self) -> bool {
+        match *self {
+            Material::Basic(ref params) => params.opacity < 1.0,
+            Material::Lambert(ref params) => params.opacity < 1.0,
+            Material::Phong(ref params) => params.opacity < 1.0,
+            Material::Pbr(ref params) => params.transparent || params.base_color_alpha < 1.0,
+            _ => false,
+        }
+    }
+}
+
 impl From<Basic> for Material {
     fn from(params: Basic) -> Self {
         Material::Basic(params)
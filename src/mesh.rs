@@ -22,7 +22,7 @@ use std::hash::{Hash, Hasher};
 ///     [ 0.5, -0.5, 0.0].into(),
 /// ];
 /// let geometry = three::Geometry::with_vertices(vertices);
-/// let red_material = three::material::Basic { color: three::color::RED, map: None };
+/// let red_material = three::material::Basic { color: three::color::RED, map: None, .. Default::default() };
 /// let mesh = factory.mesh(geometry, red_material);
 /// # let _ = mesh;
 /// ```
@@ -38,7 +38,7 @@ use std::hash::{Hash, Hasher};
 /// #     [ 0.5, -0.5, 0.0].into(),
 /// # ];
 /// # let geometry = three::Geometry::with_vertices(vertices);
-/// # let red_material = three::material::Basic { color: three::color::RED, map: None };
+/// # let red_material = three::material::Basic { color: three::color::RED, map: None, .. Default::default() };
 /// # let mesh = factory.mesh(geometry, red_material);
 /// use three::Object;
 /// let mut duplicate = factory.mesh_instance(&mesh);
@@ -57,7 +57,7 @@ use std::hash::{Hash, Hasher};
 /// #     [ 0.5, -0.5, 0.0].into(),
 /// # ];
 /// # let geometry = three::Geometry::with_vertices(vertices);
-/// # let red_material = three::material::Basic { color: three::color::RED, map: None };
+/// # let red_material = three::material::Basic { color: three::color::RED, map: None, .. Default::default() };
 /// # let mesh = factory.mesh(geometry, red_material);
 /// let yellow_material = three::material::Wireframe { color: three::color::YELLOW };
 /// # use three::Object;
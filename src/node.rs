@@ -1,4 +1,4 @@
-use cgmath;
+use cgmath::{InnerSpace, Matrix3, Matrix4, Quaternion, SquareMatrix, Vector3};
 use froggy;
 use mint;
 
@@ -9,7 +9,66 @@ use std::marker::PhantomData;
 
 /// Pointer to a Node
 pub(crate) type NodePointer = froggy::Pointer<NodeInternal>;
-pub(crate) type TransformInternal = cgmath::Decomposed<cgmath::Vector3<f32>, cgmath::Quaternion<f32>>;
+
+/// Translation, rotation and per axis scale, applied to points in reverse order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TransformInternal {
+    pub(crate) disp: Vector3<f32>,
+    pub(crate) rot: Quaternion<f32>,
+    pub(crate) scale: Vector3<f32>,
+}
+
+impl TransformInternal {
+    pub(crate) fn one() -> Self {
+        TransformInternal {
+            disp: Vector3::new(0.0, 0.0, 0.0),
+            rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl From<TransformInternal> for Matrix4<f32> {
+    fn from(tf: TransformInternal) -> Self {
+        Matrix4::from_translation(tf.disp)
+            * Matrix4::from(tf.rot)
+            * Matrix4::from_nonuniform_scale(tf.scale.x, tf.scale.y, tf.scale.z)
+    }
+}
+
+/// Recovers the closest translation, rotation and scale of an affine matrix.
+///
+/// Nodes combined with a non-uniform scale can be sheared, which TRS can't represent.
+/// The shear is dropped keeping the length of each axis and the direction of the Z axis,
+/// so the forward direction of cameras and lights is preserved.
+impl From<Matrix4<f32>> for TransformInternal {
+    fn from(mx: Matrix4<f32>) -> Self {
+        let disp = mx.w.truncate();
+        let axes = Matrix3::from_cols(mx.x.truncate(), mx.y.truncate(), mx.z.truncate());
+        let scale = Vector3::new(axes.x.magnitude(), axes.y.magnitude(), axes.z.magnitude());
+        if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            // Flattened nodes have no orientation to recover.
+            return TransformInternal {
+                disp,
+                rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                scale,
+            };
+        }
+        let z = axes.z / scale.z;
+        let y = (axes.y - z * z.dot(axes.y)).normalize();
+        let x = y.cross(z);
+        TransformInternal {
+            disp,
+            rot: Quaternion::from(Matrix3::from_cols(x, y, z)).normalize(),
+            // A mirroring matrix flips the remaining axis.
+            scale: if x.dot(axes.x) < 0.0 {
+                Vector3::new(-scale.x, scale.y, scale.z)
+            } else {
+                scale
+            },
+        }
+    }
+}
 
 // Fat node of the scene graph.
 //
@@ -29,8 +88,14 @@ pub(crate) struct NodeInternal {
     /// The transform relative to the node's parent.
     pub(crate) transform: TransformInternal,
 
-    /// The transform relative to the scene root.
-    pub(crate) world_transform: TransformInternal,
+    /// The transform set outside of animations, captured when the node is first animated.
+    ///
+    /// Animations weighing less than 1 in total are blended with it.
+    pub(crate) rest_transform: Option<TransformInternal>,
+
+    /// The transform relative to the scene root, which may be sheared by
+    /// the non-uniform scale of a parent.
+    pub(crate) world_transform: Matrix4<f32>,
 
     /// Pointer to the next sibling.
     pub(crate) next_sibling: Option<NodePointer>,
@@ -59,8 +124,9 @@ impl From<SubNode> for NodeInternal {
         NodeInternal {
             visible: true,
             name: None,
-            transform: cgmath::Transform::one(),
-            world_transform: cgmath::Transform::one(),
+            transform: TransformInternal::one(),
+            rest_transform: None,
+            world_transform: Matrix4::identity(),
             next_sibling: None,
             sub_node: sub,
         }
@@ -74,8 +140,12 @@ pub struct Transform {
     pub position: mint::Point3<f32>,
     /// Orientation.
     pub orientation: mint::Quaternion<f32>,
-    /// Scale.
-    pub scale: f32,
+    /// Scale along each axis.
+    ///
+    /// A child rotated at an angle inside a non-uniformly scaled parent is sheared when
+    /// rendered. Its world [`Node`](struct.Node.html) can't represent the shear, so it reports
+    /// the length of each stretched axis instead.
+    pub scale: mint::Vector3<f32>,
 }
 
 impl Transform {
@@ -86,7 +156,7 @@ impl Transform {
     /// * The default orientation has no rotation, meaning the coordinate axes will match the
     ///   global axes when the transform is in world space, or will match the axis of the parent
     ///   when in local space.
-    /// * The default scale is 1, meaning no change from the object's natural dimensions, or
+    /// * The default scale is 1 along every axis, meaning no change from the object's natural dimensions, or
     ///   no change relative to the parent's dimensions.
     pub fn new() -> Transform {
         Default::default()
@@ -98,7 +168,7 @@ impl Default for Transform {
         Transform {
             position: [0.0, 0.0, 0.0].into(),
             orientation: [0.0, 0.0, 0.0, 1.0].into(),
-            scale: 1.0,
+            scale: [1.0, 1.0, 1.0].into(),
         }
     }
 }
@@ -109,11 +179,43 @@ impl From<TransformInternal> for Transform {
         Transform {
             position: pos.into(),
             orientation: tf.rot.into(),
-            scale: tf.scale,
+            scale: tf.scale.into(),
         }
     }
 }
 
+/// Scale of an object, the same along every axis or per axis.
+///
+/// Converted from an `f32` for a uniform scale, or from an `[f32; 3]`, `mint::Vector3<f32>`
+/// or `cgmath::Vector3<f32>` for a scale along each axis, so both `obj.set_scale(2.0)` and
+/// `obj.set_scale([1.0, 2.0, 1.0])` work.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale(pub mint::Vector3<f32>);
+
+impl From<f32> for Scale {
+    fn from(scale: f32) -> Self {
+        Scale([scale; 3].into())
+    }
+}
+
+impl From<[f32; 3]> for Scale {
+    fn from(scale: [f32; 3]) -> Self {
+        Scale(scale.into())
+    }
+}
+
+impl From<mint::Vector3<f32>> for Scale {
+    fn from(scale: mint::Vector3<f32>) -> Self {
+        Scale(scale)
+    }
+}
+
+impl From<Vector3<f32>> for Scale {
+    fn from(scale: Vector3<f32>) -> Self {
+        Scale(scale.into())
+    }
+}
+
 /// Local space, defined relative to the parent node.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Local {}
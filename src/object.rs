@@ -13,7 +13,7 @@ use camera::Camera;
 use hub::{Hub, Message, Operation, SubLight, SubNode};
 use light;
 use mesh::Mesh;
-use node::NodePointer;
+use node::{NodePointer, Scale};
 use scene::SyncGuard;
 use skeleton::{Bone, Skeleton};
 use sprite::Sprite;
@@ -84,18 +84,20 @@ pub trait Object: AsRef<Base> {
         self.as_ref().send(Operation::SetName(name.into()));
     }
 
-    /// Set both position, orientation and scale.
-    fn set_transform<P, Q>(
+    /// Set both position, orientation and scale, see [`Scale`](../struct.Scale.html)
+    /// for the accepted scales.
+    fn set_transform<P, Q, S>(
         &self,
         pos: P,
         rot: Q,
-        scale: f32,
+        scale: S,
     ) where
         Self: Sized,
         P: Into<mint::Point3<f32>>,
         Q: Into<mint::Quaternion<f32>>,
+        S: Into<Scale>,
     {
-        self.as_ref().send(Operation::SetTransform(Some(pos.into()), Some(rot.into()), Some(scale)));
+        self.as_ref().send(Operation::SetTransform(Some(pos.into()), Some(rot.into()), Some(scale.into().0)));
     }
 
     /// Set position.
@@ -120,12 +122,19 @@ pub trait Object: AsRef<Base> {
         self.as_ref().send(Operation::SetTransform(None, Some(rot.into()), None));
     }
 
-    /// Set scale.
-    fn set_scale(
+    /// Set scale, either uniform or along each axis, see [`Scale`](../struct.Scale.html).
+    ///
+    /// Normals of scaled meshes stay perpendicular to their surfaces, see
+    /// [`Transform::scale`](../struct.Transform.html#structfield.scale) for how the scales
+    /// of nested objects combine.
+    fn set_scale<S>(
         &self,
-        scale: f32,
-    ) {
-        self.as_ref().send(Operation::SetTransform(None, None, Some(scale)));
+        scale: S,
+    ) where
+        Self: Sized,
+        S: Into<Scale>,
+    {
+        self.as_ref().send(Operation::SetTransform(None, None, Some(scale.into().0)));
     }
 
     /// Set weights.
@@ -256,6 +265,10 @@ impl Object for Base {
                     object: self.clone(),
                 }),
 
+                SubLight::Spot { .. } => ObjectType::SpotLight(light::Spot {
+                    object: self.clone(),
+                }),
+
                 SubLight::Hemisphere { .. } => ObjectType::HemisphereLight(light::Hemisphere {
                     object: self.clone(),
                 }),
@@ -289,6 +302,9 @@ pub enum ObjectType {
     /// A point light.
     PointLight(light::Point),
 
+    /// A spot light.
+    SpotLight(light::Spot),
+
     /// A mesh.
     Mesh(Mesh),
 
@@ -0,0 +1,300 @@
+//! Ray casting and object picking.
+//!
+//! A [`Raycaster`] holds a world space [`Ray`] and finds the meshes of a scene it passes
+//! through. The most common use is picking the object under the mouse cursor:
+//!
+//! ```rust,no_run
+//! # extern crate three;
+//! # fn main() {
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! let cursor = window.input.mouse_pos_ndc();
+//! let aspect_ratio = window.renderer.aspect_ratio();
+//! let guard = window.scene.sync_guard();
+//! if let Some(raycaster) = three::Raycaster::from_camera(&guard, &camera, cursor, aspect_ratio) {
+//!     if let Some(hit) = raycaster.intersect(&guard).first() {
+//!         println!("Picked {:?} at {:?}", hit.object, hit.point);
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! Only meshes created from a [`Geometry`] or loaded from a file can be hit. The test uses
+//! the original vertex positions, so skinning and morph targets are not taken into account.
+//! Sprites, lines and dynamic meshes are ignored.
+//!
+//! For this, meshes keep a copy of their vertex positions and faces in memory, 12 bytes
+//! per vertex and 12 bytes per triangle, made when they are created. The copy is shared
+//! with the instances of a mesh and freed along with the last of them.
+//!
+//! [`Raycaster`]: struct.Raycaster.html
+//! [`Ray`]: struct.Ray.html
+//! [`Geometry`]: ../struct.Geometry.html
+
+use std::cmp;
+use std::f32;
+
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Transform as Transform_, Vector3, Vector4};
+use mint;
+
+use camera::Camera;
+use hub::SubNode;
+use material::Material;
+use object::Base;
+use scene::SyncGuard;
+
+/// Triangles of a mesh kept on the CPU side for ray casting.
+#[derive(Clone, Debug)]
+pub(crate) struct Triangles {
+    positions: Vec<Point3<f32>>,
+    faces: Vec<[u32; 3]>,
+}
+
+impl Triangles {
+    /// Creates the triangle list from vertex `positions` and `faces`.
+    ///
+    /// Empty `faces` means that every three consecutive vertices form a triangle.
+    pub(crate) fn new(
+        positions: Vec<Point3<f32>>,
+        faces: Vec<[u32; 3]>,
+    ) -> Self {
+        let faces = if faces.is_empty() {
+            (0 .. positions.len() as u32 / 3)
+                .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+                .collect()
+        } else {
+            faces
+        };
+        Triangles { positions, faces }
+    }
+}
+
+/// A half-line in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    /// The point the ray starts from.
+    pub origin: mint::Point3<f32>,
+    /// Direction of the ray, expected to be normalized.
+    pub direction: mint::Vector3<f32>,
+}
+
+/// A point where a [`Ray`] hits a mesh.
+///
+/// [`Ray`]: struct.Ray.html
+#[derive(Clone, Debug)]
+pub struct Intersection {
+    /// The mesh that was hit.
+    pub object: Base,
+    /// World space distance from the ray origin.
+    pub distance: f32,
+    /// World space position of the hit.
+    pub point: mint::Point3<f32>,
+    /// Index of the triangle that was hit, as ordered in the mesh faces.
+    pub face_index: usize,
+    /// Barycentric coordinates of the hit inside the triangle.
+    ///
+    /// The point is `(1 - u - v) * a + u * b + v * c`, where `a`, `b` and `c` are the
+    /// triangle corners.
+    pub uv: mint::Point2<f32>,
+}
+
+/// Finds the meshes intersected by a ray.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Raycaster {
+    /// The ray to cast.
+    pub ray: Ray,
+    /// Hits closer than this distance are ignored.
+    ///
+    /// Default: `0.0`.
+    pub near: f32,
+    /// Hits farther than this distance are ignored.
+    ///
+    /// Default: infinity.
+    pub far: f32,
+}
+
+impl Raycaster {
+    /// Creates a raycaster for a world space `ray`.
+    pub fn new(ray: Ray) -> Self {
+        Raycaster {
+            ray,
+            near: 0.0,
+            far: f32::INFINITY,
+        }
+    }
+
+    /// Creates a raycaster going through `point_ndc` on the screen of `camera`.
+    ///
+    /// `point_ndc` is in normalized device coordinates, as returned by
+    /// [`Input::mouse_pos_ndc`], and `aspect_ratio` is usually [`Renderer::aspect_ratio`].
+    /// If the camera is not in the scene its local transform is used.
+    ///
+    /// Returns `None` if the camera can't see anything, such as when it is scaled down to nothing.
+    ///
+    /// [`Input::mouse_pos_ndc`]: ../struct.Input.html#method.mouse_pos_ndc
+    /// [`Renderer::aspect_ratio`]: ../struct.Renderer.html#method.aspect_ratio
+    pub fn from_camera<P>(
+        sync_guard: &SyncGuard,
+        camera: &Camera,
+        point_ndc: P,
+        aspect_ratio: f32,
+    ) -> Option<Self>
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let point_ndc = point_ndc.into();
+        let hub = &sync_guard.hub;
+        let node = &hub[camera];
+        let transform = hub
+            .walk_all(&sync_guard.scene.first_child)
+            .find(|w| w.node as *const _ == node as *const _)
+            .map_or(node.transform.into(), |w| w.world_transform);
+        let projection = match node.sub_node {
+            SubNode::Camera(ref projection) => projection,
+            _ => unreachable!(),
+        };
+
+        let mx_proj = Matrix4::from(projection.matrix(aspect_ratio));
+        let mx_view = transform.invert()?;
+        let mx_inv_vp = (mx_proj * mx_view).invert()?;
+        let unproject = |z: f32| {
+            let p = mx_inv_vp * Vector4::new(point_ndc.x, point_ndc.y, z, 1.0);
+            Point3::from_homogeneous(p)
+        };
+        // The far plane may be at infinity, so use the middle of the depth range instead.
+        let near = unproject(-1.0);
+        let middle = unproject(0.0);
+
+        Some(Raycaster::new(Ray {
+            origin: near.into(),
+            direction: (middle - near).normalize().into(),
+        }))
+    }
+
+    /// Returns all the intersections with visible meshes of the scene,
+    /// sorted from the closest to the farthest.
+    pub fn intersect(
+        &self,
+        sync_guard: &SyncGuard,
+    ) -> Vec<Intersection> {
+        let hub = &sync_guard.hub;
+        let mut intersections = Vec::new();
+        for w in hub.walk(&sync_guard.scene.first_child) {
+            let gpu_data = match w.node.sub_node {
+                SubNode::Visual(Material::Line(_), _, _) => continue,
+                SubNode::Visual(_, ref gpu_data, _) => gpu_data,
+                _ => continue,
+            };
+            let triangles = match gpu_data.triangles {
+                Some(ref triangles) => triangles,
+                None => continue,
+            };
+            if let Some(ref bounds) = gpu_data.bounds {
+                let sphere = bounds.transformed(&w.world_transform);
+                if !self.hits_sphere(sphere.center.into(), sphere.radius) {
+                    continue;
+                }
+            }
+            let object = hub.upgrade_ptr(w.node_ptr.clone());
+            self.intersect_triangles(triangles, &w.world_transform, &object, &mut intersections);
+        }
+        intersections.sort_by(|a, b| {
+            a.distance.partial_cmp(&b.distance).unwrap_or(cmp::Ordering::Equal)
+        });
+        intersections
+    }
+
+    /// Checks if the ray passes within `radius` of `center`.
+    fn hits_sphere(
+        &self,
+        center: Point3<f32>,
+        radius: f32,
+    ) -> bool {
+        let origin = Point3::from(self.ray.origin);
+        let direction = Vector3::from(self.ray.direction);
+        let to_center = center - origin;
+        let along = to_center.dot(direction);
+        if along + radius < self.near || along - radius > self.far {
+            return false;
+        }
+        to_center.magnitude2() - along * along <= radius * radius
+    }
+
+    fn intersect_triangles(
+        &self,
+        triangles: &Triangles,
+        transform: &Matrix4<f32>,
+        object: &Base,
+        intersections: &mut Vec<Intersection>,
+    ) {
+        let origin = Point3::from(self.ray.origin);
+        // Test in local space, then bring the hits back into the world.
+        let inverse = match transform.invert() {
+            Some(inverse) => inverse,
+            // Meshes scaled down to nothing can't be hit.
+            None => return,
+        };
+        let local_origin = inverse.transform_point(origin);
+        // Normalized so the hits are tested with the same precision at any scale.
+        let local_direction = inverse.transform_vector(Vector3::from(self.ray.direction)).normalize();
+
+        for (face_index, face) in triangles.faces.iter().enumerate() {
+            let a = triangles.positions[face[0] as usize];
+            let b = triangles.positions[face[1] as usize];
+            let c = triangles.positions[face[2] as usize];
+            let (t, u, v) = match intersect_triangle(local_origin, local_direction, a, b, c) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            let point = transform.transform_point(local_origin + local_direction * t);
+            let distance = (point - origin).magnitude();
+            if distance < self.near || distance > self.far {
+                continue;
+            }
+            intersections.push(Intersection {
+                object: object.clone(),
+                distance,
+                point: point.into(),
+                face_index,
+                uv: [u, v].into(),
+            });
+        }
+    }
+}
+
+/// Möller-Trumbore ray-triangle test, both faces are considered.
+///
+/// Returns the ray parameter and the barycentric coordinates of the hit.
+/// Rays nearly parallel to the triangle miss it, whatever the size of the triangle.
+fn intersect_triangle(
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    a: Point3<f32>,
+    b: Point3<f32>,
+    c: Point3<f32>,
+) -> Option<(f32, f32, f32)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    // `det` is the cosine between the ray and the normal, times twice the area.
+    if det.abs() <= 1.0e-6 * edge1.cross(edge2).magnitude() * direction.magnitude() {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if u < 0.0 || u > 1.0 {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    if t < 0.0 {
+        return None;
+    }
+    Some((t, u, v))
+}
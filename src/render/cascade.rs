@@ -0,0 +1,97 @@
+//! Fitting of cascaded shadow maps to the camera frustum.
+
+use cgmath;
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion, SquareMatrix, Transform as Transform_, Vector4};
+
+/// Blend between the logarithmic (`1.0`) and uniform (`0.0`) split distances.
+const SPLIT_LAMBDA: f32 = 0.5;
+
+/// A slice of the camera frustum covered by one layer of the shadow map.
+#[derive(Clone, Debug)]
+pub(crate) struct Cascade {
+    /// View space depth where the cascade ends.
+    pub far: f32,
+    pub mx_view: Matrix4<f32>,
+    pub mx_proj: Matrix4<f32>,
+}
+
+/// Splits the camera frustum up to `distance` into `count` cascades and fits
+/// an orthographic projection along `light_rotation` around each of them.
+///
+/// `mx_camera` is the world transform of the camera and `resolution` is the size
+/// of a shadow map layer, used to snap the projections to whole texels.
+pub(crate) fn fit(
+    mx_camera: Matrix4<f32>,
+    mx_proj: Matrix4<f32>,
+    light_rotation: Quaternion<f32>,
+    count: usize,
+    distance: f32,
+    resolution: u16,
+) -> Vec<Cascade> {
+    let mx_inv_proj = match mx_proj.invert() {
+        Some(matrix) => matrix,
+        None => return Vec::new(),
+    };
+    // Lines through the corners of the screen, as points on the near plane
+    // and in the middle of the depth range, which is finite for infinite projections.
+    let rays = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .map(|&(x, y)| {
+            let near = Point3::from_homogeneous(mx_inv_proj * Vector4::new(x, y, -1.0, 1.0));
+            let middle = Point3::from_homogeneous(mx_inv_proj * Vector4::new(x, y, 0.0, 1.0));
+            (near, middle)
+        })
+        .collect::<Vec<_>>();
+    let near = (-rays[0].0.z).max(0.01);
+    let far = distance.max(near);
+    let split = |i: usize| {
+        let t = i as f32 / count as f32;
+        let log = near * (far / near).powf(t);
+        let uniform = near + (far - near) * t;
+        SPLIT_LAMBDA * log + (1.0 - SPLIT_LAMBDA) * uniform
+    };
+
+    let mx_light_view = Matrix4::from(light_rotation.conjugate());
+    let mx_camera_to_light = mx_light_view * mx_camera;
+    (0 .. count)
+        .map(|i| {
+            let (start, end) = (split(i), split(i + 1));
+            let mut corners = Vec::with_capacity(8);
+            for &(a, b) in &rays {
+                for &depth in &[start, end] {
+                    let t = (depth + a.z) / (a.z - b.z);
+                    corners.push(mx_camera_to_light.transform_point(a + (b - a) * t));
+                }
+            }
+
+            // Bound the slice with a sphere, so the projection keeps its size while
+            // the camera rotates, and snap it to texels to avoid shimmering edges.
+            let sum = corners.iter().fold(Point3::new(0.0, 0.0, 0.0), |sum, p| sum + p.to_vec());
+            let mut center = sum / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|p| (p - center).magnitude())
+                .fold(0.0, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+            let texel = 2.0 * radius / resolution as f32;
+            if texel > 0.0 {
+                center.x = (center.x / texel).floor() * texel;
+                center.y = (center.y / texel).floor() * texel;
+            }
+
+            Cascade {
+                far: end,
+                mx_view: mx_light_view,
+                // Objects up to `distance` towards the light still cast shadows into the slice.
+                mx_proj: cgmath::ortho(
+                    center.x - radius,
+                    center.x + radius,
+                    center.y - radius,
+                    center.y + radius,
+                    -(center.z + radius) - distance,
+                    -(center.z - radius),
+                ),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+//! Bounding volumes and view frustum tests used for culling.
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Transform as Transform_, Vector4};
+use mint;
+
+
+/// Sphere enclosing all the vertices of a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct BoundingSphere {
+    pub center: mint::Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Computes the sphere around the bounding box of `points`.
+    ///
+    /// Returns `None` if there are no points.
+    pub(crate) fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: Iterator<Item = Point3<f32>> + Clone,
+    {
+        let mut iter = points.clone();
+        let first = iter.next()?;
+        let (min, max) = iter.fold((first, first), |(min, max), p| {
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        });
+        let center = min.midpoint(max);
+        let radius = points
+            .map(|p| (p - center).magnitude2())
+            .fold(0.0, f32::max)
+            .sqrt();
+        Some(BoundingSphere {
+            center: center.into(),
+            radius,
+        })
+    }
+
+    /// Returns a copy of the sphere grown by `amount`.
+    pub(crate) fn inflated(
+        &self,
+        amount: f32,
+    ) -> Self {
+        BoundingSphere {
+            center: self.center,
+            radius: self.radius + amount,
+        }
+    }
+
+    /// Moves the sphere from local into the space defined by `transform`.
+    pub(crate) fn transformed(
+        &self,
+        transform: &Matrix4<f32>,
+    ) -> Self {
+        let center = transform.transform_point(Point3::from(self.center));
+        // Bound the largest stretch of a possibly sheared matrix by the
+        // largest row sum of the Gram matrix of its axes.
+        let axes = [transform.x.truncate(), transform.y.truncate(), transform.z.truncate()];
+        let max_scale2 = axes
+            .iter()
+            .map(|a| axes.iter().map(|b| a.dot(*b).abs()).sum::<f32>())
+            .fold(0.0, f32::max);
+        BoundingSphere {
+            center: center.into(),
+            radius: self.radius * max_scale2.sqrt(),
+        }
+    }
+}
+
+/// Set of clipping planes of a view-projection matrix.
+#[derive(Clone, Debug)]
+pub(crate) struct Frustum {
+    /// Planes with normals pointing inside, stored as `(normal, distance)`.
+    planes: Vec<Vector4<f32>>,
+}
+
+impl Frustum {
+    /// Extracts the planes of `mx_vp` following the Gribb-Hartmann method.
+    ///
+    /// Degenerate planes, such as the far plane of an infinite projection, are skipped.
+    pub(crate) fn from_matrix(mx_vp: Matrix4<f32>) -> Self {
+        let rows = [mx_vp.row(0), mx_vp.row(1), mx_vp.row(2), mx_vp.row(3)];
+        let planes = [
+            rows[3] + rows[0],
+            rows[3] - rows[0],
+            rows[3] + rows[1],
+            rows[3] - rows[1],
+            rows[3] + rows[2],
+            rows[3] - rows[2],
+        ].iter()
+            .filter_map(|plane| {
+                let length = plane.truncate().magnitude();
+                if length > 1.0e-6 {
+                    Some(plane / length)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Frustum { planes }
+    }
+
+    /// Returns `false` if `sphere` is entirely outside of the frustum.
+    pub(crate) fn intersects(
+        &self,
+        sphere: &BoundingSphere,
+    ) -> bool {
+        let center = Vector4::new(sphere.center.x, sphere.center.y, sphere.center.z, 1.0);
+        self.planes
+            .iter()
+            .all(|plane| plane.dot(center) >= -sphere.radius)
+    }
+}
@@ -1,9 +1,10 @@
 //! The renderer.
 
-use cgmath::{Matrix as Matrix_, Matrix4, SquareMatrix, Transform as Transform_, Vector3};
+use cgmath;
+use cgmath::{EuclideanSpace, InnerSpace, Matrix as Matrix_, Matrix3, Matrix4, Point3, SquareMatrix, Transform as Transform_};
 use froggy;
 use gfx;
-use gfx::format::I8Norm;
+use gfx::format::{Formatted, I8Norm};
 use gfx::handle as h;
 use gfx::memory::Typed;
 use gfx::traits::{Factory as Factory_, FactoryExt};
@@ -13,30 +14,44 @@ use gfx_device_gl as back;
 use gfx_window_glutin;
 #[cfg(feature = "opengl")]
 use glutin;
+use image;
 use mint;
 
 pub mod source;
+mod cascade;
+mod frustum;
+mod post;
 mod pso_data;
 
+#[cfg(feature = "audio")]
+use audio;
 use color;
 
-use std::{io, str};
+use std::{cmp, io, str};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
 
 pub use self::back::CommandBuffer as BackendCommandBuffer;
 pub use self::back::Factory as BackendFactory;
 pub use self::back::Resources as BackendResources;
+pub use self::post::PostEffect;
 pub use self::source::Source;
 
+pub(crate) use self::frustum::BoundingSphere;
+
+use self::frustum::Frustum;
 use self::pso_data::{PbrFlags, PsoData};
 use camera::Camera;
 use factory::Factory;
-use hub::{SubLight, SubNode};
-use light::{ShadowMap, ShadowProjection};
+use hub::{Hub, SubLight, SubNode};
+use node::TransformInternal;
+use light::{ShadowFilter, ShadowMap, ShadowProjection};
 use material::Material;
+use raycast::Triangles;
 use scene::{Background, Scene};
 use text::Font;
-use texture::Texture;
+use texture::{CubeMap, EnvironmentMap, EnvironmentSource, Texture};
 use glutin::{ContextCurrentState, NotCurrent, Window, ContextWrapper, PossiblyCurrent};
 
 /// The format of the back buffer color requested from the windowing system.
@@ -45,10 +60,80 @@ pub type ColorFormat = gfx::format::Rgba8;
 pub type DepthFormat = gfx::format::DepthStencil;
 /// The format of the shadow buffer.
 pub type ShadowFormat = gfx::format::Depth32F;
+/// The format of the prefiltered environment maps.
+pub type HdrFormat = gfx::format::Rgba16F;
 /// The concrete type of a basic pipeline.
 pub type BasicPipelineState = gfx::PipelineState<back::Resources, basic_pipe::Meta>;
-
-pub(crate) const MAX_LIGHTS: usize = 4;
+/// The concrete type of a post effect pipeline.
+pub type PostPipelineState = gfx::PipelineState<back::Resources, post_pipe::Meta>;
+
+/// Textures created by the `Factory` whose mipmaps the `Renderer` generates
+/// before drawing the next frame.
+pub(crate) type PendingMipmaps = Arc<Mutex<Vec<h::RawShaderResourceView<back::Resources>>>>;
+
+/// Number of lights a `Renderer` can draw unless configured otherwise.
+pub(crate) const DEFAULT_MAX_LIGHTS: usize = 64;
+/// Number of `[f32; 4]` vectors taken by each light in the light buffer.
+pub(crate) const VECS_PER_LIGHT: usize = 12;
+/// Number of 2D shadow maps the shaders can sample at once.
+pub(crate) const MAX_SHADOW_MAPS: usize = 2;
+/// Value of `shadow_params[0]` for lights using the cube shadow map.
+const CUBE_SHADOW_INDEX: i32 = MAX_SHADOW_MAPS as i32;
+/// Value of `shadow_params[0]` for lights using the cascaded shadow map.
+const CASCADE_SHADOW_INDEX: i32 = CUBE_SHADOW_INDEX + 1;
+/// Values of `shadow_params[1]`, matching the defines in `shadows.glsl`.
+const SHADOW_TYPE_BASIC: i32 = 1;
+const SHADOW_TYPE_PCF: i32 = 2;
+const SHADOW_TYPE_PCSS: i32 = 3;
+const SHADOW_TYPE_VSM: i32 = 4;
+/// Maximum number of layers in a cascaded shadow map.
+pub(crate) const MAX_CASCADES: usize = 4;
+/// View direction and up vector of each cube shadow map face.
+const CUBE_FACES: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+];
+/// View direction, right and up vectors of each cube map face, as sampled by GLSL.
+const CUBE_BASES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+];
+/// Four vertices of a triangle strip covering the screen, generated by the shaders.
+const FULL_SCREEN_SLICE: gfx::Slice<back::Resources> = gfx::Slice {
+    start: 0,
+    end: 4,
+    base_vertex: 0,
+    instances: None,
+    buffer: gfx::IndexBuffer::Auto,
+};
+/// Size of the split-sum BRDF lookup table used with environment maps.
+const BRDF_LUT_SIZE: u16 = 128;
+/// Modes of the `ibl` shaders, matching the defines in `ibl_ps.glsl`.
+const IBL_MODE_EQUIRECT: f32 = 0.0;
+const IBL_MODE_IRRADIANCE: f32 = 1.0;
+const IBL_MODE_SPECULAR: f32 = 2.0;
+const IBL_MODE_BRDF: f32 = 3.0;
+/// Modes of the built-in `post` shaders, matching the defines in `post_ps.glsl`.
+const POST_MODE_BLOOM_THRESHOLD: i32 = 0;
+const POST_MODE_BLUR: i32 = 1;
+const POST_MODE_BLOOM_COMBINE: i32 = 2;
+const POST_MODE_FXAA: i32 = 3;
+const POST_MODE_VIGNETTE: i32 = 4;
+const POST_MODE_COLOR_GRADING: i32 = 5;
+/// Number of output sizes the offscreen targets are kept for, so that drawing
+/// both to the window and to render targets doesn't recreate them every frame.
+const MAX_TARGET_SIZES: usize = 4;
+/// Modes of the `ssao` shaders, matching the defines in `ssao_ps.glsl`.
+const SSAO_MODE_OCCLUSION: f32 = 0.0;
+const SSAO_MODE_BLUR: f32 = 1.0;
 pub(crate) const MAX_TARGETS: usize = 8;
 pub(crate) const VECS_PER_BONE: usize = 3;
 
@@ -136,6 +221,9 @@ gfx_defines! {
         world0: [f32; 4] = "i_World0",
         world1: [f32; 4] = "i_World1",
         world2: [f32; 4] = "i_World2",
+        normal0: [f32; 4] = "i_Normal0",
+        normal1: [f32; 4] = "i_Normal1",
+        normal2: [f32; 4] = "i_Normal2",
         color: [f32; 4] = "i_Color",
         mat_params: [f32; 4] = "i_MatParams",
         uv_range: [f32; 4] = "i_UvRange",
@@ -149,7 +237,13 @@ gfx_defines! {
         color: [f32; 4] = "color",
         color_back: [f32; 4] = "color_back",
         intensity: [f32; 4] = "intensity",
-        shadow_params: [i32; 4] = "shadow_params",
+        shadow_params: [f32; 4] = "shadow_params",
+        shadow_filter: [f32; 4] = "shadow_filter",
+    }
+
+    constant CascadeParam {
+        projection: [[f32; 4]; 4] = "projection",
+        split: [f32; 4] = "split",
     }
 
     constant Globals {
@@ -159,22 +253,66 @@ gfx_defines! {
         num_lights: u32 = "u_NumLights",
     }
 
+    // The color targets of the pipelines drawing the scene are raw, so the same
+    // pipeline states draw into the screen and into the `HdrFormat` targets.
     pipeline basic_pipe {
         vbuf: gfx::VertexBuffer<Vertex> = (),
         inst_buf: gfx::InstanceBuffer<Instance> = (),
-        cb_lights: gfx::ConstantBuffer<LightParam> = "b_Lights",
+        lights: gfx::ShaderResource<[f32; 4]> = "b_Lights",
         cb_globals: gfx::ConstantBuffer<Globals> = "b_Globals",
+        cb_cascades: gfx::ConstantBuffer<CascadeParam> = "b_Cascades",
         tex_map: gfx::TextureSampler<[f32; 4]> = "t_Map",
         shadow_map0: gfx::TextureSampler<f32> = "t_Shadow0",
         shadow_map1: gfx::TextureSampler<f32> = "t_Shadow1",
-        out_color: gfx::BlendTarget<ColorFormat> =
-            ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::REPLACE),
+        // Depth of the shadow maps, or its blurred moments with `ShadowType::Vsm`.
+        shadow_depth0: gfx::RawShaderResource = "t_ShadowDepth0",
+        shadow_depth_sampler0: gfx::Sampler = "t_ShadowDepth0",
+        shadow_depth1: gfx::RawShaderResource = "t_ShadowDepth1",
+        shadow_depth_sampler1: gfx::Sampler = "t_ShadowDepth1",
+        shadow_cube0: gfx::TextureSampler<f32> = "t_ShadowCube0",
+        shadow_cascades: gfx::TextureSampler<f32> = "t_ShadowCascades",
+        ambient_occlusion: gfx::TextureSampler<[f32; 4]> = "t_AmbientOcclusion",
+        out_color: gfx::RawRenderTarget =
+            ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), Some(gfx::preset::blend::REPLACE)),
         out_depth: gfx::DepthStencilTarget<DepthFormat> =
             (gfx::preset::depth::LESS_EQUAL_WRITE, gfx::state::Stencil {
                 front: STENCIL_SIDE, back: STENCIL_SIDE,
             }),
     }
 
+    pipeline depth_normal_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        inst_buf: gfx::InstanceBuffer<Instance> = (),
+        cb_globals: gfx::ConstantBuffer<Globals> = "b_Globals",
+        target: gfx::RenderTarget<HdrFormat> = "Target0",
+        depth: gfx::DepthTarget<DepthFormat> =
+            gfx::preset::depth::LESS_EQUAL_WRITE,
+    }
+
+    constant SsaoParams {
+        mx_proj: [[f32; 4]; 4] = "u_Proj",
+        mx_inv_proj: [[f32; 4]; 4] = "u_InverseProj",
+        params: [f32; 4] = "u_Params",
+    }
+
+    pipeline ssao_pipe {
+        params: gfx::ConstantBuffer<SsaoParams> = "b_SsaoParams",
+        input: gfx::TextureSampler<[f32; 4]> = "t_Input",
+        target: gfx::RenderTarget<HdrFormat> = "Target0",
+    }
+
+    constant VsmParams {
+        params: [f32; 4] = "u_Params",
+    }
+
+    pipeline vsm_pipe {
+        params: gfx::ConstantBuffer<VsmParams> = "b_VsmParams",
+        // Depth of the shadow map, or its moments from the previous pass.
+        input: gfx::RawShaderResource = "t_Input",
+        sampler: gfx::Sampler = "t_Input",
+        target: gfx::RenderTarget<gfx::format::Rgba32F> = "Target0",
+    }
+
     pipeline shadow_pipe {
         vbuf: gfx::VertexBuffer<Vertex> = (),
         inst_buf: gfx::InstanceBuffer<Instance> = (),
@@ -193,7 +331,7 @@ gfx_defines! {
         globals: gfx::ConstantBuffer<Globals> = "b_Globals",
         resource: gfx::RawShaderResource = "t_Input",
         sampler: gfx::Sampler = "t_Input",
-        target: gfx::RenderTarget<ColorFormat> = "Target0",
+        target: gfx::RawRenderTarget = ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), None),
         depth_target: gfx::DepthTarget<DepthFormat> =
             gfx::preset::depth::LESS_EQUAL_TEST,
     }
@@ -208,6 +346,8 @@ gfx_defines! {
         normal_scale: f32 = "u_NormalScale",
         occlusion_strength: f32 = "u_OcclusionStrength",
         pbr_flags: i32 = "u_PbrFlags",
+        _padding2: [f32; 3] = "_padding2",
+        environment: [f32; 4] = "u_Environment",
     }
 
     constant DisplacementContribution {
@@ -223,7 +363,8 @@ gfx_defines! {
 
         globals: gfx::ConstantBuffer<Globals> = "b_Globals",
         params: gfx::ConstantBuffer<PbrParams> = "b_PbrParams",
-        lights: gfx::ConstantBuffer<LightParam> = "b_Lights",
+        lights: gfx::ShaderResource<[f32; 4]> = "b_Lights",
+        cascades: gfx::ConstantBuffer<CascadeParam> = "b_Cascades",
         displacement_contributions: gfx::ConstantBuffer<DisplacementContribution> = "b_DisplacementContributions",
         joint_transforms: gfx::ShaderResource<[f32; 4]> = "b_JointTransforms",
         displacements: gfx::TextureSampler<[f32; 4]> = "u_Displacements",
@@ -237,9 +378,63 @@ gfx_defines! {
 
         occlusion_map: gfx::TextureSampler<[f32; 4]> = "u_OcclusionSampler",
 
-        color_target: gfx::RenderTarget<ColorFormat> = "Target0",
+        shadow_map0: gfx::TextureSampler<f32> = "t_Shadow0",
+        shadow_map1: gfx::TextureSampler<f32> = "t_Shadow1",
+        // Depth of the shadow maps, or its blurred moments with `ShadowType::Vsm`.
+        shadow_depth0: gfx::RawShaderResource = "t_ShadowDepth0",
+        shadow_depth_sampler0: gfx::Sampler = "t_ShadowDepth0",
+        shadow_depth1: gfx::RawShaderResource = "t_ShadowDepth1",
+        shadow_depth_sampler1: gfx::Sampler = "t_ShadowDepth1",
+        shadow_cube0: gfx::TextureSampler<f32> = "t_ShadowCube0",
+        shadow_cascades: gfx::TextureSampler<f32> = "t_ShadowCascades",
+
+        irradiance_map: gfx::TextureSampler<[f32; 4]> = "u_IrradianceSampler",
+        specular_map: gfx::TextureSampler<[f32; 4]> = "u_SpecularSampler",
+        brdf_lut: gfx::TextureSampler<[f32; 4]> = "u_BrdfLutSampler",
+        ambient_occlusion: gfx::TextureSampler<[f32; 4]> = "t_AmbientOcclusion",
+
+        color_target: gfx::RawRenderTarget =
+            ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), Some(gfx::preset::blend::REPLACE)),
         depth_target: gfx::DepthTarget<DepthFormat> = gfx::preset::depth::LESS_EQUAL_WRITE,
     }
+
+    constant IblParams {
+        forward: [f32; 4] = "u_Forward",
+        right: [f32; 4] = "u_Right",
+        up: [f32; 4] = "u_Up",
+        params: [f32; 4] = "u_Params",
+    }
+
+    constant ToneMapParams {
+        exposure: f32 = "u_Exposure",
+        operator: i32 = "u_Operator",
+    }
+
+    pipeline tonemap_pipe {
+        params: gfx::ConstantBuffer<ToneMapParams> = "b_ToneMapParams",
+        input: gfx::TextureSampler<[f32; 4]> = "t_Input",
+        target: gfx::RawRenderTarget = ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), None),
+    }
+
+    constant PostParams {
+        params: [f32; 4] = "u_Params",
+        texel_size: [f32; 2] = "u_TexelSize",
+        mode: i32 = "u_Mode",
+    }
+
+    pipeline post_pipe {
+        params: gfx::ConstantBuffer<PostParams> = "b_PostParams",
+        input: gfx::TextureSampler<[f32; 4]> = "t_Input",
+        extra: gfx::TextureSampler<[f32; 4]> = "t_Extra",
+        target: gfx::RawRenderTarget = ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), None),
+    }
+
+    pipeline ibl_pipe {
+        params: gfx::ConstantBuffer<IblParams> = "b_IblParams",
+        equirect: gfx::TextureSampler<[f32; 4]> = "t_Equirect",
+        cube: gfx::TextureSampler<[f32; 4]> = "t_Cube",
+        target: gfx::RenderTarget<HdrFormat> = "Target0",
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -251,31 +446,41 @@ pub(crate) struct InstanceCacheKey {
 impl Instance {
     #[inline]
     fn basic(
-        mx_world: mint::RowMatrix4<f32>,
+        mx_world: Matrix4<f32>,
         color: u32,
+        opacity: f32,
         uv_range: [f32; 4],
         param: f32,
     ) -> Self {
         Instance {
-            world0: mx_world.x.into(),
-            world1: mx_world.y.into(),
-            world2: mx_world.z.into(),
             color: {
-                // TODO: add alpha parameter for `to_linear_rgb`
                 let rgb = color::to_linear_rgb(color);
-                [rgb[0], rgb[1], rgb[2], 0.0]
+                [rgb[0], rgb[1], rgb[2], opacity]
             },
             mat_params: [param, 0.0, 0.0, 0.0],
             uv_range,
+            ..Instance::pbr(mx_world)
         }
     }
 
     #[inline]
-    fn pbr(mx_world: mint::RowMatrix4<f32>) -> Self {
+    fn pbr(mx_world: Matrix4<f32>) -> Self {
+        // Normals are transformed by the inverse transpose, keeping them perpendicular
+        // to the surfaces of sheared or non-uniformly scaled meshes.
+        let (a, b, c) = (mx_world.x.truncate(), mx_world.y.truncate(), mx_world.z.truncate());
+        let mx_normal = match Matrix3::from_cols(a, b, c).invert() {
+            Some(inverse) => inverse.transpose(),
+            // The cofactors still give the directions of the normals of flattened meshes.
+            None => Matrix3::from_cols(b.cross(c), c.cross(a), a.cross(b)),
+        };
+        let (rows, normal_rows) = (mx_world.transpose(), mx_normal.transpose());
         Instance {
-            world0: mx_world.x.into(),
-            world1: mx_world.y.into(),
-            world2: mx_world.z.into(),
+            world0: rows.x.into(),
+            world1: rows.y.into(),
+            world2: rows.z.into(),
+            normal0: normal_rows.x.extend(0.0).into(),
+            normal1: normal_rows.y.extend(0.0).into(),
+            normal2: normal_rows.z.extend(0.0).into(),
             color: [0.0; 4],
             mat_params: [0.0; 4],
             uv_range: [0.0; 4],
@@ -302,6 +507,26 @@ pub(crate) struct GpuData {
     pub pending: Option<DynamicData>,
     pub instance_cache_key: Option<InstanceCacheKey>,
     pub displacement_contributions: Vec<DisplacementContribution>,
+    /// Local space bounds of the vertices, `None` if they are not known.
+    pub bounds: Option<BoundingSphere>,
+    /// CPU copy of the triangles used for ray casting, `None` if the mesh can't be picked.
+    pub triangles: Option<Arc<Triangles>>,
+}
+
+impl GpuData {
+    /// Checks if the data transformed by `transform` can be seen through `frustum`.
+    fn is_in_frustum(
+        &self,
+        frustum: &Frustum,
+        transform: &Matrix4<f32>,
+        skinned: bool,
+    ) -> bool {
+        match self.bounds {
+            // Skinning moves vertices outside of the bind pose bounds.
+            Some(ref bounds) if !skinned => frustum.intersects(&bounds.transformed(transform)),
+            _ => true,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -312,13 +537,117 @@ struct InstanceData {
     list: Vec<Instance>,
 }
 
+/// Per frame inputs of the `Pbr` pipeline.
+struct PbrFrame {
+    /// World space position of the camera.
+    camera: [f32; 3],
+    irradiance: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+    specular: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+    brdf_lut: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+    /// Number of specular mip levels, zero if the scene has no environment.
+    specular_levels: usize,
+}
+
+/// A transparent mesh waiting to be drawn after the opaque ones.
+struct TransparentDraw<'a> {
+    /// View space depth of the mesh center, used for sorting.
+    depth: f32,
+    instance: Instance,
+    material: &'a Material,
+    gpu_data: &'a GpuData,
+    joint_buffer_view: h::ShaderResourceView<back::Resources, [f32; 4]>,
+    displacement_view: h::ShaderResourceView<back::Resources, [f32; 4]>,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct DynamicData {
     pub num_vertices: usize,
     pub buffer: h::Buffer<back::Resources, Vertex>,
 }
 
+/// Offscreen color and depth buffers that a scene can be rendered into.
+///
+/// Created by [`Factory::render_target`] and drawn into by [`Renderer::render_to`].
+///
+/// [`Factory::render_target`]: ../struct.Factory.html#method.render_target
+/// [`Renderer::render_to`]: struct.Renderer.html#method.render_to
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTarget {
+    pub(crate) color_view: h::RenderTargetView<back::Resources, ColorFormat>,
+    pub(crate) depth_view: h::DepthStencilView<back::Resources, DepthFormat>,
+    pub(crate) size: [u32; 2],
+}
+
+impl RenderTarget {
+    /// Returns the size of the target in pixels.
+    pub fn size(&self) -> mint::Vector2<u32> {
+        self.size.into()
+    }
+
+    /// Returns the aspect ratio of the target, i.e. width / height.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.size[0] as f32 / self.size[1] as f32
+    }
+}
+
+/// Frustum culling counters of the last [`Renderer::render`] call.
+///
+/// [`Renderer::render`]: struct.Renderer.html#method.render
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CullStats {
+    /// Number of meshes drawn in the main pass.
+    pub drawn: usize,
+    /// Number of meshes skipped in the main pass.
+    pub culled: usize,
+    /// Number of mesh draws skipped in all the shadow passes.
+    pub shadow_culled: usize,
+}
+
+/// Operator mapping the lighting of a scene to the range of the screen.
+///
+/// Anything but `None` makes the [`Renderer`] draw the scene into a floating point
+/// target first, so bright lights and emissive surfaces keep their detail instead
+/// of clipping. UI text and debug quads are drawn after tone mapping.
+///
+/// [`Renderer`]: struct.Renderer.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ToneMapping {
+    /// Draw straight into the back buffer, clamping the colors.
+    None,
+    /// Reinhard operator, `c / (1 + c)`.
+    Reinhard,
+    /// Fitted curve of the ACES filmic reference transform.
+    Aces,
+    /// Filmic curve by John Hable, as used in Uncharted 2.
+    Filmic,
+}
+
+/// Ambient occlusion algorithm, see [`Renderer::ambient_occlusion`].
+///
+/// [`Renderer::ambient_occlusion`]: struct.Renderer.html#structfield.ambient_occlusion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AmbientOcclusion {
+    /// No ambient occlusion.
+    Off,
+    /// Screen space ambient occlusion, darkening the ambient light in creases and corners.
+    ///
+    /// It costs an extra pass over the opaque meshes. Skinned meshes and meshes with
+    /// morph targets are left out of it, so they neither receive nor cast occlusion.
+    /// It darkens the ambient and hemisphere lights of the `Lambert` and `Phong`
+    /// materials, and the environment lighting of `Pbr`.
+    Ssao {
+        /// View space distance around each point searched for occluders.
+        radius: f32,
+        /// Exponent applied to the occlusion, higher values darken more.
+        power: f32,
+    },
+}
+
 /// Shadow type is used to specify shadow's rendering algorithm.
+///
+/// The bias and the size of the filters are set per map, see
+/// [`ShadowFilter`](../light/struct.ShadowFilter.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ShadowType {
     /// Force no shadows.
     Off,
@@ -326,6 +655,50 @@ pub enum ShadowType {
     Basic,
     /// Percentage-closest filter (PCF).
     Pcf,
+    /// Percentage-closer soft shadows (PCSS), sharp near the casters and softer away from them.
+    Pcss,
+    /// Variance shadow maps (VSM), blurring the depth of the maps before sampling them.
+    ///
+    /// Costs two extra full screen passes per shadow map.
+    Vsm,
+}
+
+/// Floating point color target used with tone mapping and post effects.
+struct HdrTarget {
+    resource: h::ShaderResourceView<back::Resources, [f32; 4]>,
+    view: h::RenderTargetView<back::Resources, HdrFormat>,
+}
+
+/// Render targets of the ambient occlusion passes.
+struct SsaoTargets {
+    /// View space normals and window depth of the opaque meshes.
+    depth_normal: (
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        h::RenderTargetView<back::Resources, HdrFormat>,
+    ),
+    depth: h::DepthStencilView<back::Resources, DepthFormat>,
+    occlusion: (
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        h::RenderTargetView<back::Resources, HdrFormat>,
+    ),
+    blurred: (
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        h::RenderTargetView<back::Resources, HdrFormat>,
+    ),
+}
+
+/// Blurred depth moments of a shadow map used with `ShadowType::Vsm`.
+struct VsmTarget {
+    /// The shadow map the moments are computed from.
+    source: h::ShaderResourceView<back::Resources, f32>,
+    moments: (
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        h::RenderTargetView<back::Resources, gfx::format::Rgba32F>,
+    ),
+    blurred: (
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        h::RenderTargetView<back::Resources, gfx::format::Rgba32F>,
+    ),
 }
 
 struct DebugQuad {
@@ -334,11 +707,22 @@ struct DebugQuad {
     size: [i32; 2],
 }
 
+/// Offscreen state of a renderer created by `Renderer::headless`.
+struct Headless {
+    color: h::Texture<back::Resources, <ColorFormat as Formatted>::Surface>,
+    // Note: the context has to outlive every GL object created by the renderer.
+    _context: glutin::Context<PossiblyCurrent>,
+    _event_loop: glutin::EventsLoop,
+}
+
 /// All pipeline state objects used by the `three` renderer.
 pub struct PipelineStates<R: gfx::Resources> {
     /// Corresponds to `Material::Basic`.
     mesh_basic_fill: gfx::PipelineState<R, basic_pipe::Meta>,
 
+    /// Corresponds to transparent `Material::Basic`.
+    mesh_basic_fill_blend: gfx::PipelineState<R, basic_pipe::Meta>,
+
     /// Corresponds to `Material::Line`.
     line_basic: gfx::PipelineState<R, basic_pipe::Meta>,
 
@@ -348,9 +732,15 @@ pub struct PipelineStates<R: gfx::Resources> {
     /// Corresponds to `Material::Gouraud`.
     mesh_gouraud: gfx::PipelineState<R, basic_pipe::Meta>,
 
+    /// Corresponds to transparent `Material::Gouraud`.
+    mesh_gouraud_blend: gfx::PipelineState<R, basic_pipe::Meta>,
+
     /// Corresponds to `Material::Phong`.
     mesh_phong: gfx::PipelineState<R, basic_pipe::Meta>,
 
+    /// Corresponds to transparent `Material::Phong`.
+    mesh_phong_blend: gfx::PipelineState<R, basic_pipe::Meta>,
+
     /// Corresponds to `Material::Sprite`.
     sprite: gfx::PipelineState<R, basic_pipe::Meta>,
 
@@ -363,8 +753,29 @@ pub struct PipelineStates<R: gfx::Resources> {
     /// Corresponds to `Material::Pbr`.
     pbr: gfx::PipelineState<R, pbr_pipe::Meta>,
 
+    /// Corresponds to transparent `Material::Pbr`.
+    pbr_blend: gfx::PipelineState<R, pbr_pipe::Meta>,
+
     /// Used internally for rendering `Background::Skybox`.
     skybox: gfx::PipelineState<R, quad_pipe::Meta>,
+
+    /// Used internally for prefiltering environment maps.
+    ibl: gfx::PipelineState<R, ibl_pipe::Meta>,
+
+    /// Used internally for resolving the floating point target.
+    tonemap: gfx::PipelineState<R, tonemap_pipe::Meta>,
+
+    /// Used internally for the built-in `PostEffect`s.
+    post: gfx::PipelineState<R, post_pipe::Meta>,
+
+    /// Used internally for the ambient occlusion prepass.
+    depth_normal: gfx::PipelineState<R, depth_normal_pipe::Meta>,
+
+    /// Used internally for computing and blurring ambient occlusion.
+    ssao: gfx::PipelineState<R, ssao_pipe::Meta>,
+
+    /// Used internally for blurring the depth moments of variance shadow maps.
+    vsm: gfx::PipelineState<R, vsm_pipe::Meta>,
 }
 
 impl PipelineStates<back::Resources> {
@@ -380,17 +791,32 @@ impl PipelineStates<back::Resources> {
         &'a self,
         material: &'a Material,
     ) -> &'a BasicPipelineState {
+        let transparent = material.is_transparent();
         match *material {
+            Material::Basic(_) if transparent => &self.mesh_basic_fill_blend,
             Material::Basic(_) => &self.mesh_basic_fill,
             Material::CustomBasic(ref b) => &b.pipeline,
             Material::Line(_) => &self.line_basic,
             Material::Wireframe(_) => &self.mesh_basic_wireframe,
+            Material::Lambert(_) if transparent => &self.mesh_gouraud_blend,
             Material::Lambert(_) => &self.mesh_gouraud,
+            Material::Phong(_) if transparent => &self.mesh_phong_blend,
             Material::Phong(_) => &self.mesh_phong,
             Material::Sprite(_) => &self.sprite,
             _ => unreachable!(),
         }
     }
+
+    pub(crate) fn pbr_pso<'a>(
+        &'a self,
+        material: &'a Material,
+    ) -> &'a gfx::PipelineState<back::Resources, pbr_pipe::Meta> {
+        if material.is_transparent() {
+            &self.pbr_blend
+        } else {
+            &self.pbr
+        }
+    }
 }
 
 impl<R: gfx::Resources> PipelineStates<R> {
@@ -407,6 +833,12 @@ impl<R: gfx::Resources> PipelineStates<R> {
         let quad = backend.create_shader_set(&src.quad.vs, &src.quad.ps)?;
         let pbr = backend.create_shader_set(&src.pbr.vs, &src.pbr.ps)?;
         let skybox = backend.create_shader_set(&src.skybox.vs, &src.skybox.ps)?;
+        let ibl = backend.create_shader_set(&src.ibl.vs, &src.ibl.ps)?;
+        let tonemap = backend.create_shader_set(&src.tonemap.vs, &src.tonemap.ps)?;
+        let post = backend.create_shader_set(&src.post.vs, &src.post.ps)?;
+        let depth_normal = backend.create_shader_set(&src.depth_normal.vs, &src.depth_normal.ps)?;
+        let ssao = backend.create_shader_set(&src.ssao.vs, &src.ssao.ps)?;
+        let vsm = backend.create_shader_set(&src.vsm.vs, &src.vsm.ps)?;
 
         let rast_quad = gfx::state::Rasterizer {
             samples: Some(gfx::state::MultiSample),
@@ -417,10 +849,6 @@ impl<R: gfx::Resources> PipelineStates<R> {
             method: gfx::state::RasterMethod::Line(1),
             ..rast_fill
         };
-        let rast_shadow = gfx::state::Rasterizer {
-            offset: Some(gfx::state::Offset(2, 2)),
-            ..rast_fill
-        };
 
         let pso_mesh_basic_fill = backend.create_pipeline_state(
             &basic,
@@ -434,6 +862,20 @@ impl<R: gfx::Resources> PipelineStates<R> {
             rast_fill,
             basic_pipe::new(),
         )?;
+        // Transparent meshes are blended over the opaque ones and don't occlude each other.
+        let basic_blend_init = basic_pipe::Init {
+            out_color: ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), Some(gfx::preset::blend::ALPHA)),
+            out_depth: (gfx::preset::depth::LESS_EQUAL_TEST, gfx::state::Stencil {
+                front: STENCIL_SIDE, back: STENCIL_SIDE,
+            }),
+            ..basic_pipe::new()
+        };
+        let pso_mesh_basic_fill_blend = backend.create_pipeline_state(
+            &basic,
+            gfx::Primitive::TriangleList,
+            rast_fill,
+            basic_blend_init.clone(),
+        )?;
         let pso_mesh_basic_wireframe = backend.create_pipeline_state(
             &basic,
             gfx::Primitive::TriangleList,
@@ -446,25 +888,38 @@ impl<R: gfx::Resources> PipelineStates<R> {
             rast_fill,
             basic_pipe::new(),
         )?;
+        let pso_mesh_gouraud_blend = backend.create_pipeline_state(
+            &gouraud,
+            gfx::Primitive::TriangleList,
+            rast_fill,
+            basic_blend_init.clone(),
+        )?;
         let pso_mesh_phong = backend.create_pipeline_state(
             &phong,
             gfx::Primitive::TriangleList,
             rast_fill,
             basic_pipe::new(),
         )?;
+        let pso_mesh_phong_blend = backend.create_pipeline_state(
+            &phong,
+            gfx::Primitive::TriangleList,
+            rast_fill,
+            basic_blend_init,
+        )?;
         let pso_sprite = backend.create_pipeline_state(
             &sprite,
             gfx::Primitive::TriangleStrip,
             rast_fill,
             basic_pipe::Init {
-                out_color: ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+                out_color: ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), Some(gfx::preset::blend::ALPHA)),
                 ..basic_pipe::new()
             },
         )?;
         let pso_shadow = backend.create_pipeline_state(
             &shadow,
             gfx::Primitive::TriangleList,
-            rast_shadow,
+            // Depth bias is applied when sampling, according to each map's `ShadowFilter`.
+            rast_fill,
             shadow_pipe::new(),
         )?;
         let pso_quad = backend.create_pipeline_state(
@@ -485,18 +940,75 @@ impl<R: gfx::Resources> PipelineStates<R> {
             rast_fill,
             pbr_pipe::new(),
         )?;
+        let pso_pbr_blend = backend.create_pipeline_state(
+            &pbr,
+            gfx::Primitive::TriangleList,
+            rast_fill,
+            pbr_pipe::Init {
+                color_target: ("Target0", ColorFormat::get_format(), gfx::state::ColorMask::all(), Some(gfx::preset::blend::ALPHA)),
+                depth_target: gfx::preset::depth::LESS_EQUAL_TEST,
+                ..pbr_pipe::new()
+            },
+        )?;
+
+        let pso_ibl = backend.create_pipeline_state(
+            &ibl,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            ibl_pipe::new(),
+        )?;
+        let pso_tonemap = backend.create_pipeline_state(
+            &tonemap,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            tonemap_pipe::new(),
+        )?;
+        let pso_post = backend.create_pipeline_state(
+            &post,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            post_pipe::new(),
+        )?;
+        let pso_depth_normal = backend.create_pipeline_state(
+            &depth_normal,
+            gfx::Primitive::TriangleList,
+            rast_fill,
+            depth_normal_pipe::new(),
+        )?;
+        let pso_ssao = backend.create_pipeline_state(
+            &ssao,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            ssao_pipe::new(),
+        )?;
+        let pso_vsm = backend.create_pipeline_state(
+            &vsm,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            vsm_pipe::new(),
+        )?;
 
         Ok(PipelineStates {
             mesh_basic_fill: pso_mesh_basic_fill,
+            mesh_basic_fill_blend: pso_mesh_basic_fill_blend,
             line_basic: pso_line_basic,
             mesh_basic_wireframe: pso_mesh_basic_wireframe,
             mesh_gouraud: pso_mesh_gouraud,
+            mesh_gouraud_blend: pso_mesh_gouraud_blend,
             mesh_phong: pso_mesh_phong,
+            mesh_phong_blend: pso_mesh_phong_blend,
             sprite: pso_sprite,
             shadow: pso_shadow,
             quad: pso_quad,
             pbr: pso_pbr,
+            pbr_blend: pso_pbr_blend,
             skybox: pso_skybox,
+            ibl: pso_ibl,
+            tonemap: pso_tonemap,
+            post: pso_post,
+            depth_normal: pso_depth_normal,
+            ssao: pso_ssao,
+            vsm: pso_vsm,
         })
     }
 }
@@ -516,7 +1028,19 @@ pub struct Renderer {
     const_buf: h::Buffer<back::Resources, Globals>,
     quad_buf: h::Buffer<back::Resources, QuadParams>,
     inst_buf: h::Buffer<back::Resources, Instance>,
-    light_buf: h::Buffer<back::Resources, LightParam>,
+    light_buf: h::Buffer<back::Resources, [f32; 4]>,
+    light_buf_view: h::ShaderResourceView<back::Resources, [f32; 4]>,
+    max_lights: usize,
+    cascade_buf: h::Buffer<back::Resources, CascadeParam>,
+    ibl_buf: h::Buffer<back::Resources, IblParams>,
+    tonemap_buf: h::Buffer<back::Resources, ToneMapParams>,
+    post_buf: h::Buffer<back::Resources, PostParams>,
+    ssao_buf: h::Buffer<back::Resources, SsaoParams>,
+    ssao_targets: HashMap<[u16; 2], SsaoTargets>,
+    vsm_buf: h::Buffer<back::Resources, VsmParams>,
+    vsm_targets: Vec<VsmTarget>,
+    pending_mipmaps: PendingMipmaps,
+    hdr_targets: HashMap<[u16; 2], Vec<HdrTarget>>,
     pbr_buf: h::Buffer<back::Resources, PbrParams>,
     out_color: h::RenderTargetView<back::Resources, ColorFormat>,
     out_depth: h::DepthStencilView<back::Resources, DepthFormat>,
@@ -526,13 +1050,41 @@ pub struct Renderer {
     pso: PipelineStates<back::Resources>,
     map_default: Texture<[f32; 4]>,
     shadow_default: Texture<f32>,
+    /// Sampler reading the depth of the shadow maps, or their moments, without comparison.
+    shadow_depth_sampler: h::Sampler<back::Resources>,
+    shadow_cube_default: h::ShaderResourceView<back::Resources, f32>,
+    shadow_cascades_default: h::ShaderResourceView<back::Resources, f32>,
+    environment_default: CubeMap<[f32; 4]>,
+    brdf_lut: Texture<[f32; 4]>,
     debug_quads: froggy::Storage<DebugQuad>,
     size: glutin::dpi::LogicalSize,
     dpi: f64,
     font_cache: HashMap<String, Font>,
     instance_cache: HashMap<InstanceCacheKey, InstanceData>,
+    headless: Option<Headless>,
+    cull_stats: CullStats,
     /// `ShadowType` of this `Renderer`.
     pub shadow: ShadowType,
+    /// `AmbientOcclusion` of this `Renderer`.
+    ///
+    /// Defaults to `AmbientOcclusion::Off`.
+    pub ambient_occlusion: AmbientOcclusion,
+    /// Skip meshes that are outside of the camera or shadow frustum.
+    ///
+    /// Defaults to `true`.
+    pub frustum_culling: bool,
+    /// See [`ToneMapping`](enum.ToneMapping.html).
+    ///
+    /// Defaults to `ToneMapping::None`.
+    pub tone_mapping: ToneMapping,
+    /// Multiplier of the scene colors before tone mapping,
+    /// ignored with `ToneMapping::None`.
+    ///
+    /// Defaults to `1.0`.
+    pub exposure: f32,
+    /// Full screen passes run after tone mapping, in order.
+    /// See [`PostEffect`](enum.PostEffect.html).
+    pub post_effects: Vec<PostEffect>,
 }
 
 impl Renderer {
@@ -542,11 +1094,120 @@ impl Renderer {
         context: glutin::ContextBuilder<NotCurrent>,
         event_loop: &glutin::EventsLoop,
         source: &source::Set,
+        max_lights: usize,
     ) -> (Self, glutin::WindowedContext<PossiblyCurrent>, Factory) {
+        let (windowedContext, device, gl_factory, out_color, out_depth) = gfx_window_glutin::init(builder, context, event_loop).unwrap();
+        let (size, dpi) = {
+            let window = windowedContext.window();
+            (window.get_inner_size().unwrap(), window.get_hidpi_factor())
+        };
+        let (renderer, factory) = Self::with_device(device, gl_factory, out_color, out_depth, source, max_lights, size, dpi, None);
+        (renderer, windowedContext, factory)
+    }
+
+    /// Create a new `Renderer` that draws into an offscreen color and depth target
+    /// of the given size instead of a window, together with the `Factory` for it.
+    ///
+    /// Use [`read_pixels`](#method.read_pixels) to get the rendered image back after
+    /// calling [`render`](#method.render).
+    ///
+    /// The audio sources of the factory use the null [`audio::Backend`], see
+    /// [`Factory::set_audio_backend`] to record them instead. Their sounds only play
+    /// when advanced with [`Factory::advance_audio`].
+    ///
+    /// [`audio::Backend`]: audio/enum.Backend.html
+    /// [`Factory::set_audio_backend`]: struct.Factory.html#method.set_audio_backend
+    /// [`Factory::advance_audio`]: struct.Factory.html#method.advance_audio
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # extern crate three;
+    /// # fn main() {
+    /// let (mut renderer, mut factory) = three::Renderer::headless(320, 240);
+    /// let scene = factory.scene();
+    /// let camera = factory.perspective_camera(60.0, 0.1 .. 10.0);
+    /// renderer.render(&scene, &camera);
+    /// let image = renderer.read_pixels().unwrap();
+    /// image.save("frame.png").unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if no OpenGL context can be created without a window.
+    #[cfg(feature = "opengl")]
+    pub fn headless(
+        width: u32,
+        height: u32,
+    ) -> (Self, Factory) {
+        let event_loop = glutin::EventsLoop::new();
+        let context = glutin::ContextBuilder::new()
+            .with_gl_profile(glutin::GlProfile::Core)
+            .with_gl(glutin::GlRequest::Latest)
+            .build_headless(&event_loop, glutin::dpi::PhysicalSize::new(width as f64, height as f64))
+            .expect("create headless GL context");
+        let context = unsafe { context.make_current() }
+            .map_err(|(_, err)| err)
+            .expect("make headless GL context current");
+        let (device, mut gl_factory) = back::create(|s| context.get_proc_address(s) as *const _);
+
+        let (color, _, out_color) = gl_factory
+            .create_render_target::<ColorFormat>(width as _, height as _)
+            .expect("create offscreen color target");
+        let out_depth = gl_factory
+            .create_depth_stencil_view_only::<DepthFormat>(width as _, height as _)
+            .expect("create offscreen depth target");
+        let headless = Headless {
+            color,
+            _context: context,
+            _event_loop: event_loop,
+        };
+        let size = glutin::dpi::LogicalSize::new(width as f64, height as f64);
+        #[allow(unused_mut)]
+        let (renderer, mut factory) = Self::with_device(
+            device,
+            gl_factory,
+            out_color,
+            out_depth,
+            &source::Set::default(),
+            DEFAULT_MAX_LIGHTS,
+            size,
+            1.0,
+            Some(headless),
+        );
+        #[cfg(feature = "audio")]
+        {
+            // Only the WAV backend can fail.
+            let _ = factory.set_audio_backend(audio::Backend::Null);
+        }
+        (renderer, factory)
+    }
+
+    #[cfg(feature = "opengl")]
+    fn with_device(
+        device: back::Device,
+        mut gl_factory: back::Factory,
+        out_color: h::RenderTargetView<back::Resources, ColorFormat>,
+        out_depth: h::DepthStencilView<back::Resources, DepthFormat>,
+        source: &source::Set,
+        max_lights: usize,
+        size: glutin::dpi::LogicalSize,
+        dpi: f64,
+        headless: Option<Headless>,
+    ) -> (Self, Factory) {
+        use factory::compressed;
         use gfx::texture as t;
 
-        let (windowedContext, device, mut gl_factory, out_color, out_depth) = gfx_window_glutin::init(builder, context, event_loop).unwrap();
-        let window = windowedContext.window();
+        let compressed_support = {
+            let info = device.get_info();
+            let s3tc = info.is_extension_supported("GL_EXT_texture_compression_s3tc");
+            compressed::Support {
+                s3tc,
+                s3tc_srgb: s3tc && (info.is_extension_supported("GL_EXT_texture_sRGB")
+                    || info.is_extension_supported("GL_EXT_texture_compression_s3tc_srgb")),
+            }
+        };
         let (_, srv_white) = gl_factory
             .create_texture_immutable::<gfx::format::Rgba8>(
                 t::Kind::D2(1, 1, t::AaMode::Single),
@@ -559,7 +1220,34 @@ impl Renderer {
                 t::Mipmap::Provided,
                 &[&[0x3F800000]],
             ).unwrap();
+        let (_, srv_shadow_cube) = gl_factory
+            .create_texture_immutable::<(gfx::format::R32, gfx::format::Float)>(
+                t::Kind::Cube(1),
+                t::Mipmap::Provided,
+                &[&[0x3F800000], &[0x3F800000], &[0x3F800000], &[0x3F800000], &[0x3F800000], &[0x3F800000]],
+            ).unwrap();
+        let (_, srv_shadow_cascades) = gl_factory
+            .create_texture_immutable::<(gfx::format::R32, gfx::format::Float)>(
+                t::Kind::D2Array(1, 1, 1, t::AaMode::Single),
+                t::Mipmap::Provided,
+                &[&[0x3F800000]],
+            ).unwrap();
+        let (_, srv_black_cube) = gl_factory
+            .create_texture_immutable::<gfx::format::Rgba8>(
+                t::Kind::Cube(1),
+                t::Mipmap::Provided,
+                &[&[[0; 4]], &[[0; 4]], &[[0; 4]], &[[0; 4]], &[[0; 4]], &[[0; 4]]],
+            ).unwrap();
+        let (_, srv_brdf_lut, rtv_brdf_lut) = gl_factory
+            .create_render_target::<HdrFormat>(BRDF_LUT_SIZE, BRDF_LUT_SIZE)
+            .unwrap();
         let sampler = gl_factory.create_sampler_linear();
+        let sampler_clamp = gl_factory.create_sampler(
+            t::SamplerInfo::new(t::FilterMethod::Bilinear, t::WrapMode::Clamp),
+        );
+        let sampler_shadow_depth = gl_factory.create_sampler(
+            t::SamplerInfo::new(t::FilterMethod::Bilinear, t::WrapMode::Clamp),
+        );
         let sampler_shadow = gl_factory.create_sampler(t::SamplerInfo {
             comparison: Some(gfx::state::Comparison::Less),
             border: t::PackedColor(!0), // clamp to 1.0
@@ -602,7 +1290,23 @@ impl Renderer {
         let encoder = gl_factory.create_command_buffer().into();
         let const_buf = gl_factory.create_constant_buffer(1);
         let quad_buf = gl_factory.create_constant_buffer(1);
-        let light_buf = gl_factory.create_constant_buffer(MAX_LIGHTS);
+        let light_buf = gl_factory
+            .create_buffer(
+                max_lights * VECS_PER_LIGHT,
+                gfx::buffer::Role::Constant,
+                gfx::memory::Usage::Dynamic,
+                gfx::memory::Bind::SHADER_RESOURCE | gfx::memory::Bind::TRANSFER_DST,
+            )
+            .unwrap();
+        let light_buf_view = gl_factory
+            .view_buffer_as_shader_resource(&light_buf)
+            .unwrap();
+        let cascade_buf = gl_factory.create_constant_buffer(MAX_CASCADES);
+        let ibl_buf = gl_factory.create_constant_buffer(1);
+        let tonemap_buf = gl_factory.create_constant_buffer(1);
+        let post_buf = gl_factory.create_constant_buffer(1);
+        let ssao_buf = gl_factory.create_constant_buffer(1);
+        let vsm_buf = gl_factory.create_constant_buffer(1);
         let pbr_buf = gl_factory.create_constant_buffer(1);
         let inst_buf = gl_factory
             .create_buffer(
@@ -615,13 +1319,25 @@ impl Renderer {
         let displacement_contributions_buf = gl_factory.create_constant_buffer(MAX_TARGETS);
         let pso = PipelineStates::init(source, &mut gl_factory).unwrap();
 
-        let renderer = Renderer {
+        let mut renderer = Renderer {
             device,
             factory: gl_factory.clone(),
             encoder,
             const_buf,
             quad_buf,
             light_buf,
+            light_buf_view,
+            max_lights,
+            cascade_buf,
+            ibl_buf,
+            tonemap_buf,
+            post_buf,
+            ssao_buf,
+            ssao_targets: HashMap::new(),
+            vsm_buf,
+            vsm_targets: Vec::new(),
+            pending_mipmaps: PendingMipmaps::default(),
+            hdr_targets: HashMap::new(),
             inst_buf,
             pbr_buf,
             displacement_contributions_buf,
@@ -630,17 +1346,387 @@ impl Renderer {
             pso,
             default_joint_buffer_view,
             default_displacement_buffer_view,
-            map_default: Texture::new(srv_white, sampler, [1, 1]),
+            map_default: Texture::new(srv_white, sampler.clone(), [1, 1]),
             shadow_default: Texture::new(srv_shadow, sampler_shadow, [1, 1]),
+            shadow_depth_sampler: sampler_shadow_depth,
+            shadow_cube_default: srv_shadow_cube,
+            shadow_cascades_default: srv_shadow_cascades,
+            environment_default: CubeMap::new(srv_black_cube, sampler),
+            brdf_lut: Texture::new(srv_brdf_lut, sampler_clamp, [BRDF_LUT_SIZE as u32; 2]),
             instance_cache: HashMap::new(),
             shadow: ShadowType::Basic,
+            ambient_occlusion: AmbientOcclusion::Off,
             debug_quads: froggy::Storage::new(),
             font_cache: HashMap::new(),
-            size: window.get_inner_size().unwrap(),
-            dpi: window.get_hidpi_factor(),
+            size,
+            dpi,
+            headless,
+            cull_stats: CullStats::default(),
+            frustum_culling: true,
+            tone_mapping: ToneMapping::None,
+            exposure: 1.0,
+            post_effects: Vec::new(),
         };
-        let factory = Factory::new(gl_factory);
-        (renderer, windowedContext, factory)
+        // The lookup table doesn't depend on the scene, so it's drawn once
+        // and submitted with the first frame.
+        let (equirect, cube) = (renderer.map_default.to_param(), renderer.environment_default.to_param());
+        renderer.draw_ibl(&rtv_brdf_lut, CUBE_BASES[0], [IBL_MODE_BRDF, 0.0, 0.0, 0.0], equirect, cube);
+        let factory = Factory::new(gl_factory, renderer.pending_mipmaps.clone(), compressed_support);
+        (renderer, factory)
+    }
+
+    /// Returns `count` floating point color targets of the given size,
+    /// creating them on first use.
+    fn hdr_targets(
+        &mut self,
+        width: u16,
+        height: u16,
+        count: usize,
+    ) -> Vec<(
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        h::RenderTargetView<back::Resources, HdrFormat>,
+    )> {
+        if self.hdr_targets.len() >= MAX_TARGET_SIZES && !self.hdr_targets.contains_key(&[width, height]) {
+            self.hdr_targets.clear();
+        }
+        let factory = &mut self.factory;
+        let targets = self.hdr_targets.entry([width, height]).or_insert_with(Vec::new);
+        while targets.len() < count {
+            let (_, resource, view) = factory
+                .create_render_target::<HdrFormat>(width, height)
+                .unwrap();
+            targets.push(HdrTarget { resource, view });
+        }
+        targets[.. count]
+            .iter()
+            .map(|target| (target.resource.clone(), target.view.clone()))
+            .collect()
+    }
+
+    /// Returns the ambient occlusion targets for an output of the given size,
+    /// creating them on first use.
+    fn ssao_targets(
+        &mut self,
+        width: u16,
+        height: u16,
+    ) -> &SsaoTargets {
+        if self.ssao_targets.len() >= MAX_TARGET_SIZES && !self.ssao_targets.contains_key(&[width, height]) {
+            self.ssao_targets.clear();
+        }
+        let factory = &mut self.factory;
+        self.ssao_targets.entry([width, height]).or_insert_with(|| {
+            let (_, depth_normal_srv, depth_normal_rtv) = factory
+                .create_render_target::<HdrFormat>(width, height)
+                .unwrap();
+            let depth = factory
+                .create_depth_stencil_view_only::<DepthFormat>(width, height)
+                .unwrap();
+            let (_, occlusion_srv, occlusion_rtv) = factory
+                .create_render_target::<HdrFormat>(width, height)
+                .unwrap();
+            let (_, blurred_srv, blurred_rtv) = factory
+                .create_render_target::<HdrFormat>(width, height)
+                .unwrap();
+            SsaoTargets {
+                depth_normal: (depth_normal_srv, depth_normal_rtv),
+                depth,
+                occlusion: (occlusion_srv, occlusion_rtv),
+                blurred: (blurred_srv, blurred_rtv),
+            }
+        })
+    }
+
+    /// Draws the depth and normals of the opaque meshes and computes
+    /// the blurred ambient occlusion from them.
+    fn render_ambient_occlusion(
+        &mut self,
+        hub: &Hub,
+        scene: &Scene,
+        mx_proj: Matrix4<f32>,
+        mx_view: Matrix4<f32>,
+        radius: f32,
+        power: f32,
+        size: (u16, u16),
+    ) -> h::ShaderResourceView<back::Resources, [f32; 4]> {
+        let (depth_normal, depth, occlusion, blurred) = {
+            let targets = self.ssao_targets(size.0, size.1);
+            (
+                targets.depth_normal.clone(),
+                targets.depth.clone(),
+                targets.occlusion.clone(),
+                targets.blurred.clone(),
+            )
+        };
+        self.encoder.clear(&depth_normal.1, [0.0, 0.0, 0.0, 1.0]);
+        self.encoder.clear_depth(&depth, 1.0);
+
+        let frustum = Frustum::from_matrix(mx_proj * mx_view);
+        for w in hub.walk(&scene.first_child) {
+            let (material, gpu_data) = match w.node.sub_node {
+                SubNode::Visual(ref material, ref data, None) => (material, data),
+                _ => continue,
+            };
+            match *material {
+                Material::Line(_) | Material::Sprite(_) | Material::Wireframe(_) => continue,
+                _ if material.is_transparent() || gpu_data.displacements.is_some() => continue,
+                _ => {}
+            }
+            if self.frustum_culling && !gpu_data.is_in_frustum(&frustum, &w.world_transform, false) {
+                continue;
+            }
+            self.encoder
+                .update_buffer(&gpu_data.instances, &[Instance::pbr(w.world_transform)], 0)
+                .unwrap();
+            let data = depth_normal_pipe::Data {
+                vbuf: gpu_data.vertices.clone(),
+                inst_buf: gpu_data.instances.clone(),
+                cb_globals: self.const_buf.clone(),
+                target: depth_normal.1.clone(),
+                depth: depth.clone(),
+            };
+            self.encoder.draw(&gpu_data.slice, &self.pso.depth_normal, &data);
+        }
+
+        let sampler = self.map_default.to_param().1;
+        let passes = [
+            (SSAO_MODE_OCCLUSION, depth_normal.0, occlusion.1),
+            (SSAO_MODE_BLUR, occlusion.0, blurred.1),
+        ];
+        for &(mode, ref input, ref target) in &passes {
+            self.encoder.update_constant_buffer(
+                &self.ssao_buf,
+                &SsaoParams {
+                    mx_proj: mx_proj.into(),
+                    mx_inv_proj: mx_proj.invert().unwrap().into(),
+                    params: [radius, power, mode, 0.0],
+                },
+            );
+            let data = ssao_pipe::Data {
+                params: self.ssao_buf.clone(),
+                input: (input.clone(), sampler.clone()),
+                target: target.clone(),
+            };
+            self.encoder.draw(&FULL_SCREEN_SLICE, &self.pso.ssao, &data);
+        }
+        blurred.0
+    }
+
+    /// Computes the depth moments of a shadow map for `ShadowType::Vsm`,
+    /// blurred over `kernel_size` texels.
+    fn blur_shadow_moments(
+        &mut self,
+        source: &h::ShaderResourceView<back::Resources, f32>,
+        size: (u16, u16),
+        kernel_size: u8,
+    ) -> h::ShaderResourceView<back::Resources, [f32; 4]> {
+        if !self.vsm_targets.iter().any(|target| target.source == *source) {
+            let (_, moments_srv, moments_rtv) = self.factory
+                .create_render_target::<gfx::format::Rgba32F>(size.0, size.1)
+                .unwrap();
+            let (_, blurred_srv, blurred_rtv) = self.factory
+                .create_render_target::<gfx::format::Rgba32F>(size.0, size.1)
+                .unwrap();
+            self.vsm_targets.push(VsmTarget {
+                source: source.clone(),
+                moments: (moments_srv, moments_rtv),
+                blurred: (blurred_srv, blurred_rtv),
+            });
+        }
+        let (moments, blurred) = {
+            let target = self.vsm_targets.iter().find(|target| target.source == *source).unwrap();
+            (target.moments.clone(), target.blurred.clone())
+        };
+
+        // The blur taps cover the kernel in four steps on either side.
+        let step = kernel_size.max(1) as f32 / 8.0;
+        let passes = [
+            ([step, 0.0, 1.0, 0.0], source.raw().clone(), moments.1),
+            ([0.0, step, 0.0, 0.0], moments.0.raw().clone(), blurred.1.clone()),
+        ];
+        for &(params, ref input, ref target) in &passes {
+            self.encoder.update_constant_buffer(&self.vsm_buf, &VsmParams { params });
+            let data = vsm_pipe::Data {
+                params: self.vsm_buf.clone(),
+                input: input.clone(),
+                sampler: self.shadow_depth_sampler.clone(),
+                target: target.clone(),
+            };
+            self.encoder.draw(&FULL_SCREEN_SLICE, &self.pso.vsm, &data);
+        }
+        blurred.0
+    }
+
+    /// Tone maps the scene from `targets[0]` and runs the post effects,
+    /// using the other targets in turn and writing the last pass into `final_color`.
+    fn resolve(
+        &mut self,
+        targets: &[(
+            h::ShaderResourceView<back::Resources, [f32; 4]>,
+            h::RenderTargetView<back::Resources, HdrFormat>,
+        )],
+        final_color: &h::RenderTargetView<back::Resources, ColorFormat>,
+    ) {
+        let sampler = self.map_default.to_param().1;
+        let effects = self.post_effects.clone();
+        let (width, height, _, _) = final_color.get_dimensions();
+        let texel_size = [1.0 / width as f32, 1.0 / height as f32];
+        let output = |index: usize, pass: usize| if pass + 1 == effects.len() {
+            final_color.raw().clone()
+        } else {
+            targets[index].1.raw().clone()
+        };
+
+        let (operator, exposure) = match self.tone_mapping {
+            ToneMapping::None => (0, 1.0),
+            ToneMapping::Reinhard => (1, self.exposure),
+            ToneMapping::Aces => (2, self.exposure),
+            ToneMapping::Filmic => (3, self.exposure),
+        };
+        self.encoder.update_constant_buffer(
+            &self.tonemap_buf,
+            &ToneMapParams {
+                exposure,
+                operator,
+            },
+        );
+        let params = |mode, params| PostParams { params, texel_size, mode };
+
+        // the bright areas of each bloom are taken from the scene before tone mapping
+        // compresses them, and added back at the place of the bloom in the chain
+        let mut bloom_target = 2;
+        for effect in &effects {
+            if let PostEffect::Bloom { threshold, radius, .. } = *effect {
+                let steps = [
+                    (params(POST_MODE_BLOOM_THRESHOLD, [threshold, exposure, 0.0, 0.0]), 0, bloom_target),
+                    (params(POST_MODE_BLUR, [radius, 0.0, 0.0, 0.0]), bloom_target, 1),
+                    (params(POST_MODE_BLUR, [0.0, radius, 0.0, 0.0]), 1, bloom_target),
+                ];
+                for &(ref step, from, to) in &steps {
+                    let from = (targets[from].0.clone(), sampler.clone());
+                    let to = targets[to].1.raw().clone();
+                    Self::draw_post(&mut self.encoder, &self.post_buf, &self.pso.post, step, from.clone(), from, to);
+                }
+                bloom_target += 1;
+            }
+        }
+
+        let data = tonemap_pipe::Data {
+            params: self.tonemap_buf.clone(),
+            input: (targets[0].0.clone(), sampler.clone()),
+            target: if effects.is_empty() { final_color.raw().clone() } else { targets[1].1.raw().clone() },
+        };
+        self.encoder.draw(&FULL_SCREEN_SLICE, &self.pso.tonemap, &data);
+
+        // the effects ping-pong between the first two targets
+        let mut source = 1;
+        let mut bloom_target = 2;
+        for (pass, effect) in effects.iter().enumerate() {
+            let free = 1 - source;
+            let input = (targets[source].0.clone(), sampler.clone());
+            match *effect {
+                PostEffect::Bloom { intensity, .. } => {
+                    let blurred = (targets[bloom_target].0.clone(), sampler.clone());
+                    let combine = params(POST_MODE_BLOOM_COMBINE, [intensity, 0.0, 0.0, 0.0]);
+                    Self::draw_post(&mut self.encoder, &self.post_buf, &self.pso.post, &combine, input, blurred, output(free, pass));
+                    bloom_target += 1;
+                }
+                PostEffect::Fxaa => {
+                    let fxaa = params(POST_MODE_FXAA, [0.0; 4]);
+                    Self::draw_post(&mut self.encoder, &self.post_buf, &self.pso.post, &fxaa, input.clone(), input, output(free, pass));
+                }
+                PostEffect::Vignette { offset, darkness } => {
+                    let vignette = params(POST_MODE_VIGNETTE, [offset, darkness, 0.0, 0.0]);
+                    Self::draw_post(&mut self.encoder, &self.post_buf, &self.pso.post, &vignette, input.clone(), input, output(free, pass));
+                }
+                PostEffect::ColorGrading { ref lut } => {
+                    let grading = params(POST_MODE_COLOR_GRADING, [0.0; 4]);
+                    Self::draw_post(&mut self.encoder, &self.post_buf, &self.pso.post, &grading, input, lut.to_param(), output(free, pass));
+                }
+                PostEffect::Custom { ref pipeline, params: values } => {
+                    let custom = params(0, values);
+                    Self::draw_post(&mut self.encoder, &self.post_buf, pipeline, &custom, input.clone(), input, output(free, pass));
+                }
+            }
+            source = free;
+        }
+    }
+
+    #[inline]
+    fn draw_post(
+        encoder: &mut gfx::Encoder<back::Resources, back::CommandBuffer>,
+        post_buf: &h::Buffer<back::Resources, PostParams>,
+        pso: &PostPipelineState,
+        params: &PostParams,
+        input: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+        extra: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+        target: h::RawRenderTargetView<back::Resources>,
+    ) {
+        encoder.update_constant_buffer(post_buf, params);
+        let data = post_pipe::Data {
+            params: post_buf.clone(),
+            input,
+            extra,
+            target,
+        };
+        encoder.draw(&FULL_SCREEN_SLICE, pso, &data);
+    }
+
+    /// Draws one face of an environment map in the given mode of the `ibl` shaders.
+    fn draw_ibl(
+        &mut self,
+        target: &h::RenderTargetView<back::Resources, HdrFormat>,
+        (forward, right, up): ([f32; 3], [f32; 3], [f32; 3]),
+        params: [f32; 4],
+        equirect: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+        cube: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+    ) {
+        self.encoder.update_constant_buffer(
+            &self.ibl_buf,
+            &IblParams {
+                forward: [forward[0], forward[1], forward[2], 0.0],
+                right: [right[0], right[1], right[2], 0.0],
+                up: [up[0], up[1], up[2], 0.0],
+                params,
+            },
+        );
+        let data = ibl_pipe::Data {
+            params: self.ibl_buf.clone(),
+            equirect,
+            cube,
+            target: target.clone(),
+        };
+        self.encoder.draw(&FULL_SCREEN_SLICE, &self.pso.ibl, &data);
+    }
+
+    /// Fills the irradiance and specular maps of `environment` from its source.
+    fn prefilter_environment(
+        &mut self,
+        environment: &EnvironmentMap,
+    ) {
+        let equirect_default = self.map_default.to_param();
+        let source = match environment.source {
+            EnvironmentSource::Cube(ref cubemap) => cubemap.to_param(),
+            EnvironmentSource::Equirect { ref map, ref cube, ref targets } => {
+                let cube_default = self.environment_default.to_param();
+                for (target, &basis) in targets.iter().zip(CUBE_BASES.iter()) {
+                    let params = [IBL_MODE_EQUIRECT, 0.0, 0.0, 0.0];
+                    self.draw_ibl(target, basis, params, map.to_param(), cube_default.clone());
+                }
+                (cube.clone(), environment.sampler.clone())
+            }
+        };
+        for (target, &basis) in environment.irradiance_targets.iter().zip(CUBE_BASES.iter()) {
+            let params = [IBL_MODE_IRRADIANCE, 0.0, 0.0, 0.0];
+            self.draw_ibl(target, basis, params, equirect_default.clone(), source.clone());
+        }
+        let last_level = cmp::max(environment.specular_targets.len(), 2) - 1;
+        for (level, targets) in environment.specular_targets.iter().enumerate() {
+            let roughness = level as f32 / last_level as f32;
+            for (target, &basis) in targets.iter().zip(CUBE_BASES.iter()) {
+                let params = [IBL_MODE_SPECULAR, roughness, 0.0, 0.0];
+                self.draw_ibl(target, basis, params, equirect_default.clone(), source.clone());
+            }
+        }
     }
 
     /// Reloads the shaders.
@@ -675,6 +1761,11 @@ impl Renderer {
         gfx_window_glutin::update_views(window, &mut self.out_color, &mut self.out_depth);
     }
 
+    /// Returns the culling counters of the last [`render`](#method.render) call.
+    pub fn cull_stats(&self) -> CullStats {
+        self.cull_stats
+    }
+
     /// Returns current viewport aspect ratio, i.e. width / height.
     pub fn aspect_ratio(&self) -> f32 {
         self.size.to_physical(self.dpi).width as f32 / self.size.to_physical(self.dpi).height as f32
@@ -694,25 +1785,128 @@ impl Renderer {
         }
     }
 
+    /// Reads back the last frame rendered by a [`headless`](#method.headless) renderer
+    /// as an RGBA image, with the origin at the top-left corner.
+    ///
+    /// Returns `None` if this renderer draws into a window.
+    pub fn read_pixels(&mut self) -> Option<image::RgbaImage> {
+        let texture = match self.headless {
+            Some(ref headless) => headless.color.clone(),
+            None => return None,
+        };
+        let info = texture.get_info().to_raw_image_info(ColorFormat::get_format().1, 0);
+        let (width, height) = (info.width as usize, info.height as usize);
+        let download = self.factory
+            .create_download_buffer::<[u8; 4]>(width * height)
+            .expect("create download buffer");
+        self.encoder
+            .copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0)
+            .expect("copy offscreen target into download buffer");
+        self.encoder.flush(&mut self.device);
+
+        let reader = self.factory
+            .read_mapping(&download)
+            .expect("map download buffer");
+        // GL rows go bottom-up, images go top-down.
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for row in reader.chunks(width).rev() {
+            for texel in row {
+                pixels.extend_from_slice(texel);
+            }
+        }
+        image::RgbaImage::from_raw(width as u32, height as u32, pixels)
+    }
+
     /// See [`Window::render`](struct.Window.html#method.render).
     pub fn render(
         &mut self,
         scene: &Scene,
         camera: &Camera,
+    ) {
+        let out_color = self.out_color.clone();
+        let out_depth = self.out_depth.clone();
+        let aspect_ratio = self.aspect_ratio();
+        self.render_impl(scene, camera, &out_color, &out_depth, aspect_ratio, true);
+    }
+
+    /// Renders `scene` by `camera` into an offscreen [`RenderTarget`] instead of the screen.
+    ///
+    /// The result can be sampled through the texture returned alongside the target by
+    /// [`Factory::render_target`], for example as `material::Basic::map`. UI text and
+    /// debug quads are only drawn by [`render`](#method.render).
+    ///
+    /// Note: a mesh using the target's texture must not be visible while rendering
+    /// into that same target.
+    ///
+    /// [`RenderTarget`]: render/struct.RenderTarget.html
+    /// [`Factory::render_target`]: struct.Factory.html#method.render_target
+    pub fn render_to(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        target: &RenderTarget,
+    ) {
+        let aspect_ratio = target.aspect_ratio();
+        self.render_impl(scene, camera, &target.color_view, &target.depth_view, aspect_ratio, false);
+    }
+
+    fn render_impl(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        out_color: &h::RenderTargetView<back::Resources, ColorFormat>,
+        out_depth: &h::DepthStencilView<back::Resources, DepthFormat>,
+        aspect_ratio: f32,
+        overlays: bool,
     ) {
         {
             use gfx::Device;
             self.device.cleanup();
         }
 
+        // with tone mapping or post effects the scene is drawn into a floating point
+        // target, which is resolved into `final_color` before the overlays
+        let final_color = out_color;
+        let offscreen_targets = if self.tone_mapping != ToneMapping::None || !self.post_effects.is_empty() {
+            let (width, height, _, _) = final_color.get_dimensions();
+            // the scene, a second target to ping-pong the effects, and the bright areas of each bloom
+            let blooms = self.post_effects.iter().filter(|effect| match **effect {
+                PostEffect::Bloom { .. } => true,
+                _ => false,
+            }).count();
+            let count = if self.post_effects.is_empty() {
+                1
+            } else {
+                2 + blooms
+            };
+            self.hdr_targets(width, height, count)
+        } else {
+            Vec::new()
+        };
+        let out_color = match offscreen_targets.first() {
+            Some(&(_, ref view)) => view.raw().clone(),
+            None => final_color.raw().clone(),
+        };
+
+        // generate the mipmaps of the textures loaded since the last frame
+        for view in self.pending_mipmaps.lock().unwrap().drain(..) {
+            self.encoder.generate_mipmap_raw(&view);
+        }
+
         let mut hub = scene.hub.lock().unwrap();
         hub.process_messages();
+        // spatial audio follows the listener, or the camera of the main view,
+        // so that offscreen views such as mirrors don't move the ears
+        #[cfg(feature = "audio")]
+        {
+            if overlays || scene.listener.is_some() {
+                audio::update_spatial(&mut hub, scene, camera);
+            }
+        }
         // update joint transforms of skeletons
         {
-            use node::TransformInternal;
-
             struct SkeletonTemp {
-                inverse_world_transform: TransformInternal,
+                inverse_world_transform: Matrix4<f32>,
                 cpu_buffer: Vec<[f32; 4]>,
                 gpu_buffer: gfx::handle::Buffer<BackendResources, [f32; 4]>,
             }
@@ -722,14 +1916,14 @@ impl Renderer {
                 match w.node.sub_node {
                     SubNode::Skeleton(ref skeleton) => {
                         skeletons.push(SkeletonTemp {
-                            inverse_world_transform: w.world_transform.inverse_transform().unwrap(),
+                            inverse_world_transform: w.world_transform.invert().unwrap(),
                             cpu_buffer: vec![[0.0; 4]; skeleton.bones.len() * VECS_PER_BONE],
                             gpu_buffer: skeleton.gpu_buffer.clone(),
                         });
                     }
                     SubNode::Bone { index, inverse_bind_matrix } => {
                         let skel = skeletons.last_mut().unwrap();
-                        let mx_base = Matrix4::from(skel.inverse_world_transform.concat(&w.world_transform));
+                        let mx_base = skel.inverse_world_transform * w.world_transform;
                         let mx = (mx_base * Matrix4::from(inverse_bind_matrix)).transpose();
                         let buf = &mut skel.cpu_buffer[index * VECS_PER_BONE .. (index + 1) * VECS_PER_BONE];
                         buf[0] = mx.x.into();
@@ -773,7 +1967,7 @@ impl Renderer {
                 }
                 // Note: UI text currently applies to all the scenes.
                 // We may want to make it scene-dependent at some point.
-                SubNode::UiText(ref text) => {
+                SubNode::UiText(ref text) if overlays => {
                     text.font.queue(&text.section);
                     if !self.font_cache.contains_key(&text.font.id) {
                         self.font_cache
@@ -784,16 +1978,31 @@ impl Renderer {
             }
         }
 
+        // prefilter the environment the first time it's used
+        if let Some(ref environment) = scene.environment {
+            if environment.pending.swap(false, Ordering::AcqRel) {
+                self.prefilter_environment(environment);
+            }
+        }
+
         // gather lights
         struct ShadowRequest {
             target: h::DepthStencilView<back::Resources, ShadowFormat>,
-            resource: h::ShaderResourceView<back::Resources, f32>,
             mx_view: Matrix4<f32>,
             mx_proj: Matrix4<f32>,
         }
         let mut lights = Vec::new();
         let mut shadow_requests = Vec::new();
-        let mut mx_camera_transform = hub[&camera].transform;
+        let mut shadow_maps = Vec::new();
+        let mut shadow_cube = None;
+        let mut cascaded_shadow = None;
+        let mut mx_camera_transform = Matrix4::from(hub[&camera].transform);
+        let shadow_type = match self.shadow {
+            ShadowType::Off | ShadowType::Basic => SHADOW_TYPE_BASIC,
+            ShadowType::Pcf => SHADOW_TYPE_PCF,
+            ShadowType::Pcss => SHADOW_TYPE_PCSS,
+            ShadowType::Vsm => SHADOW_TYPE_VSM,
+        };
 
         for w in hub.walk(&scene.first_child) {
             // grab the camera world space info
@@ -804,33 +2013,75 @@ impl Renderer {
                 SubNode::Light(ref light) => light,
                 _ => continue,
             };
-            if lights.len() == MAX_LIGHTS {
-                error!("Max number of lights ({}) reached", MAX_LIGHTS);
+            if lights.len() == self.max_lights {
+                error!("Max number of lights ({}) reached, see `window::Builder::max_lights`", self.max_lights);
                 break;
             }
 
-            let shadow_index = if let Some((ref map, ref projection)) = light.shadow {
-                let target = map.to_target();
-                let dim = target.get_dimensions();
-                let aspect = dim.0 as f32 / dim.1 as f32;
-                let mx_proj = match projection {
-                    &ShadowProjection::Orthographic(ref p) => p.matrix(aspect),
-                };
-                let mx_view = Matrix4::from(w.world_transform.inverse_transform().unwrap());
-                shadow_requests.push(ShadowRequest {
-                    target,
-                    resource: map.to_resource(),
-                    mx_view,
-                    mx_proj: mx_proj.into(),
-                });
-                shadow_requests.len() as i32 - 1
-            } else {
-                -1
-            };
+            // `focus` holds the cosines of the spot cone edges, followed by the cube shadow range
+            let mut focus = [-1.0, -1.0, 0.0, 0.0];
+            let mut projection = [[0.0; 4]; 4];
+            let mut shadow_index = -1;
+            let mut shadow_filter = ShadowFilter::default();
+            if self.shadow == ShadowType::Off {
+                // lights cast no shadows, leaving `shadow_index` at -1
+            } else if let Some((ref map, ref shadow_projection)) = light.shadow {
+                if shadow_maps.len() < MAX_SHADOW_MAPS {
+                    let target = map.to_target();
+                    let dim = target.get_dimensions();
+                    let aspect = dim.0 as f32 / dim.1 as f32;
+                    let mx_proj = Matrix4::from(match shadow_projection {
+                        &ShadowProjection::Orthographic(ref p) => p.matrix(aspect),
+                        &ShadowProjection::Perspective(ref p) => p.matrix(aspect),
+                    });
+                    let mx_view = w.world_transform.invert().unwrap();
+                    shadow_requests.push(ShadowRequest {
+                        target,
+                        mx_view,
+                        mx_proj,
+                    });
+                    shadow_maps.push((map.to_resource(), map.filter, (dim.0, dim.1)));
+                    projection = (mx_proj * mx_view).into();
+                    shadow_index = shadow_maps.len() as i32 - 1;
+                    shadow_filter = map.filter;
+                } else {
+                    error!("Max number of shadow maps ({}) reached", MAX_SHADOW_MAPS);
+                }
+            } else if let Some((ref map, distance)) = light.cascaded_shadow {
+                if cascaded_shadow.is_none() {
+                    // The cascades are fitted to the camera once all the lights are gathered.
+                    cascaded_shadow = Some((map, distance, TransformInternal::from(w.world_transform).rot));
+                    shadow_index = CASCADE_SHADOW_INDEX;
+                    shadow_filter = map.filter;
+                } else {
+                    error!("Only one cascaded shadow map can be used at a time");
+                }
+            } else if let Some((ref map, ref range)) = light.cube_shadow {
+                if shadow_cube.is_none() {
+                    let position = Point3::from_vec(w.world_transform.w.truncate());
+                    let mx_proj = cgmath::perspective(cgmath::Deg(90.0), 1.0, range.start, range.end);
+                    for (target, &(dir, up)) in map.targets.iter().zip(CUBE_FACES.iter()) {
+                        shadow_requests.push(ShadowRequest {
+                            target: target.clone(),
+                            mx_view: Matrix4::look_at_dir(position, dir.into(), up.into()),
+                            mx_proj,
+                        });
+                    }
+                    shadow_cube = Some(map.to_resource());
+                    // The shaders look up the cube with the direction from the light.
+                    projection = Matrix4::from_translation(-w.world_transform.w.truncate()).into();
+                    shadow_index = CUBE_SHADOW_INDEX;
+                    focus[2] = range.start;
+                    focus[3] = range.end;
+                    shadow_filter = map.filter;
+                } else {
+                    error!("Only one cube shadow map can be used at a time");
+                }
+            }
 
             let mut color_back = 0;
-            let mut p = w.world_transform.disp.extend(1.0);
-            let d = w.world_transform.rot * Vector3::unit_z();
+            let mut p = w.world_transform.w;
+            let d = w.world_transform.z.truncate().normalize();
             let intensity = match light.sub_light {
                 SubLight::Ambient => [light.intensity, 0.0, 0.0, 0.0],
                 SubLight::Directional => {
@@ -843,20 +2094,19 @@ impl Renderer {
                     [light.intensity, 0.0, 0.0, 0.0]
                 }
                 SubLight::Point => [0.0, light.intensity, 0.0, 0.0],
-            };
-            let projection = if shadow_index >= 0 {
-                let request = &shadow_requests[shadow_index as usize];
-                let matrix = request.mx_proj * request.mx_view;
-                matrix.into()
-            } else {
-                [[0.0; 4]; 4]
+                SubLight::Spot { angle, penumbra } => {
+                    let penumbra = penumbra.max(0.0).min(1.0);
+                    focus[0] = angle.to_radians().cos();
+                    focus[1] = (angle * (1.0 - penumbra)).to_radians().cos();
+                    [0.0, light.intensity, 0.0, 0.0]
+                }
             };
 
             lights.push(LightParam {
                 projection,
                 pos: p.into(),
                 dir: d.extend(0.0).into(),
-                focus: [0.0, 0.0, 0.0, 0.0],
+                focus,
                 color: {
                     let rgb = color::to_linear_rgb(light.color);
                     [rgb[0], rgb[1], rgb[2], 0.0]
@@ -866,11 +2116,59 @@ impl Renderer {
                     [rgb[0], rgb[1], rgb[2], 0.0]
                 },
                 intensity,
-                shadow_params: [shadow_index, 0, 0, 0],
+                shadow_params: [
+                    shadow_index as f32,
+                    shadow_type as f32,
+                    shadow_filter.kernel_size as f32,
+                    0.0,
+                ],
+                shadow_filter: [shadow_filter.bias, shadow_filter.slope_bias, shadow_filter.light_size, 0.0],
             });
         }
 
+        // prepare camera matrices
+        let mx_view = mx_camera_transform.invert().unwrap();
+        let projection = match hub[&camera].sub_node {
+            SubNode::Camera(ref projection) => projection.clone(),
+            _ => panic!("Camera had incorrect sub node")
+        };
+        let mx_proj = Matrix4::from(projection.matrix(aspect_ratio));
+
+        // fit cascades
+        let mut cascades = [CascadeParam {
+            projection: [[0.0; 4]; 4],
+            split: [0.0; 4],
+        }; MAX_CASCADES];
+        let shadow_cascades = match cascaded_shadow {
+            Some((map, distance, rotation)) => {
+                let resolution = map.targets[0].get_dimensions().0;
+                let fitted = cascade::fit(
+                    mx_camera_transform,
+                    mx_proj,
+                    rotation,
+                    map.targets.len(),
+                    distance,
+                    resolution,
+                );
+                for (i, (target, cascade)) in map.targets.iter().zip(fitted).enumerate() {
+                    cascades[i] = CascadeParam {
+                        projection: (cascade.mx_proj * cascade.mx_view).into(),
+                        split: [cascade.far, 0.0, 0.0, 0.0],
+                    };
+                    shadow_requests.push(ShadowRequest {
+                        target: target.clone(),
+                        mx_view: cascade.mx_view,
+                        mx_proj: cascade.mx_proj,
+                    });
+                }
+                map.to_resource()
+            }
+            None => self.shadow_cascades_default.clone(),
+        };
+        self.encoder.update_buffer(&self.cascade_buf, &cascades, 0).unwrap();
+
         // render shadow maps
+        let mut cull_stats = CullStats::default();
         for request in &shadow_requests {
             self.encoder.clear_depth(&request.target, 1.0);
             let mx_vp = request.mx_proj * request.mx_view;
@@ -884,14 +2182,18 @@ impl Renderer {
                 },
             );
 
+            let frustum = Frustum::from_matrix(mx_vp);
             for w in hub.walk(&scene.first_child) {
-                let gpu_data = match w.node.sub_node {
-                    SubNode::Visual(_, ref data, _) => data,
+                let (gpu_data, skeleton) = match w.node.sub_node {
+                    SubNode::Visual(_, ref data, ref skeleton) => (data, skeleton),
                     _ => continue,
                 };
-                let mx_world: mint::ColumnMatrix4<_> = Matrix4::from(w.world_transform).into();
+                if self.frustum_culling && !gpu_data.is_in_frustum(&frustum, &w.world_transform, skeleton.is_some()) {
+                    cull_stats.shadow_culled += 1;
+                    continue;
+                }
                 self.encoder
-                    .update_buffer(&gpu_data.instances, &[Instance::pbr(mx_world.into())], 0)
+                    .update_buffer(&gpu_data.instances, &[Instance::pbr(w.world_transform)], 0)
                     .unwrap();
                 //TODO: avoid excessive cloning
                 let data = shadow_pipe::Data {
@@ -904,13 +2206,20 @@ impl Renderer {
             }
         }
 
+        // blur the depth of the 2D shadow maps, which are sampled directly otherwise
+        let depth_default = (self.map_default.to_param().0.raw().clone(), self.shadow_depth_sampler.clone());
+        let mut shadow_depths = [depth_default.clone(), depth_default];
+        for (depth, &(ref resource, filter, size)) in shadow_depths.iter_mut().zip(&shadow_maps) {
+            depth.0 = if self.shadow == ShadowType::Vsm {
+                self.blur_shadow_moments(resource, size, filter.kernel_size).raw().clone()
+            } else {
+                resource.raw().clone()
+            };
+        }
+        self.vsm_targets
+            .retain(|target| shadow_maps.iter().any(|&(ref resource, _, _)| *resource == target.source));
+
         // prepare target and globals
-        let mx_view = Matrix4::from(mx_camera_transform.inverse_transform().unwrap());
-        let projection = match hub[&camera].sub_node {
-            SubNode::Camera(ref projection) => projection.clone(),
-            _ => panic!("Camera had incorrect sub node")
-        };
-        let mx_proj = Matrix4::from(projection.matrix(self.aspect_ratio()));
         self.encoder.update_constant_buffer(
             &self.const_buf,
             &Globals {
@@ -921,34 +2230,72 @@ impl Renderer {
             },
         );
         self.encoder
-            .update_buffer(&self.light_buf, &lights, 0)
+            .update_buffer(&self.light_buf, gfx::memory::cast_slice(&lights), 0)
             .unwrap();
 
-        self.encoder.clear_depth(&self.out_depth, 1.0);
-        self.encoder.clear_stencil(&self.out_depth, 0);
+        self.encoder.clear_depth(out_depth, 1.0);
+        self.encoder.clear_stencil(out_depth, 0);
+
+        let ambient_occlusion = match self.ambient_occlusion {
+            AmbientOcclusion::Ssao { radius, power } => {
+                let (width, height, _, _) = out_color.get_dimensions();
+                let resource = self.render_ambient_occlusion(
+                    &hub,
+                    scene,
+                    mx_proj,
+                    mx_view,
+                    radius,
+                    power,
+                    (width, height),
+                );
+                (resource, self.map_default.to_param().1)
+            }
+            AmbientOcclusion::Off => self.map_default.to_param(),
+        };
 
         if let Background::Color(color) = scene.background {
             let rgb = color::to_linear_rgb(color);
             self.encoder
-                .clear(&self.out_color, [rgb[0], rgb[1], rgb[2], 0.0]);
+                .clear_raw(&out_color, [rgb[0], rgb[1], rgb[2], 0.0].into());
         }
 
         // render everything
         let (shadow_default, shadow_sampler) = self.shadow_default.to_param();
-        let shadow0 = match shadow_requests.get(0) {
-            Some(ref request) => request.resource.clone(),
+        let shadow0 = match shadow_maps.get(0) {
+            Some(&(ref resource, _, _)) => resource.clone(),
             None => shadow_default.clone(),
         };
-        let shadow1 = match shadow_requests.get(1) {
-            Some(ref request) => request.resource.clone(),
+        let shadow1 = match shadow_maps.get(1) {
+            Some(&(ref resource, _, _)) => resource.clone(),
             None => shadow_default.clone(),
         };
+        let shadow_cube = shadow_cube.unwrap_or_else(|| self.shadow_cube_default.clone());
+        let pbr_frame = match scene.environment {
+            Some(ref environment) => PbrFrame {
+                camera: mx_camera_transform.w.truncate().into(),
+                irradiance: (environment.irradiance.clone(), environment.sampler.clone()),
+                specular: (environment.specular.clone(), environment.sampler.clone()),
+                brdf_lut: self.brdf_lut.to_param(),
+                specular_levels: environment.specular_targets.len(),
+            },
+            None => PbrFrame {
+                camera: mx_camera_transform.w.truncate().into(),
+                irradiance: self.environment_default.to_param(),
+                specular: self.environment_default.to_param(),
+                brdf_lut: self.brdf_lut.to_param(),
+                specular_levels: 0,
+            },
+        };
 
         // clear instance cache
         for instances in self.instance_cache.values_mut() {
             instances.list.clear();
         }
 
+        // transparent meshes are deferred until all the opaque ones are drawn
+        let mut transparent_draws = Vec::new();
+
+        let frustum = Frustum::from_matrix(mx_proj * mx_view);
         for w in hub.walk(&scene.first_child) {
             let (material, gpu_data, skeleton) = match w.node.sub_node {
                 SubNode::Visual(ref material, ref gpu_data, ref skeleton) => {
@@ -956,33 +2303,42 @@ impl Renderer {
                 }
                 _ => continue,
             };
+            if self.frustum_culling && !gpu_data.is_in_frustum(&frustum, &w.world_transform, skeleton.is_some()) {
+                cull_stats.culled += 1;
+                continue;
+            }
+            cull_stats.drawn += 1;
 
-            let mx_world: mint::ColumnMatrix4<_> = Matrix4::from(w.world_transform).into();
+            let mx_world = w.world_transform;
             let pso_data = material.to_pso_data();
+            let transparent = material.is_transparent();
 
             let instance = match pso_data {
-                PsoData::Basic { color, map, param0 } => {
+                PsoData::Basic { color, opacity, map, param0 } => {
                     let uv_range = match map {
                         Some(ref map) => map.uv_range(),
                         None => [0.0; 4],
                     };
-                    if let Some(ref key) = gpu_data.instance_cache_key {
-                        let data = self.instance_cache
-                            .entry(key.clone())
-                            .or_insert_with(|| InstanceData {
-                                slice: gpu_data.slice.clone(),
-                                vertices: gpu_data.vertices.clone(),
-                                material: material.clone(),
-                                list: Vec::new(),
-                            });
-                        data.list.push(Instance::basic(mx_world.into(), color, uv_range, param0));
-                        // Create a new instance and defer the draw call.
-                        continue;
+                    match gpu_data.instance_cache_key {
+                        Some(ref key) if !transparent => {
+                            let data = self.instance_cache
+                                .entry(key.clone())
+                                .or_insert_with(|| InstanceData {
+                                    slice: gpu_data.slice.clone(),
+                                    vertices: gpu_data.vertices.clone(),
+                                    material: material.clone(),
+                                    list: Vec::new(),
+                                });
+                            data.list.push(Instance::basic(mx_world, color, opacity, uv_range, param0));
+                            // Create a new instance and defer the draw call.
+                            continue;
+                        }
+                        _ => {}
                     }
-                    Instance::basic(mx_world.into(), color, uv_range, param0)
+                    Instance::basic(mx_world, color, opacity, uv_range, param0)
                 }
                 PsoData::Pbr { .. } => {
-                    Instance::pbr(mx_world.into())
+                    Instance::pbr(mx_world)
                 }
             };
             let joint_buffer_view = if let Some(ref ptr) = *skeleton {
@@ -1000,15 +2356,33 @@ impl Renderer {
                 None => self.default_displacement_buffer_view.clone(),
             };
 
+            if transparent {
+                let center = match gpu_data.bounds {
+                    Some(ref bounds) => bounds.transformed(&w.world_transform).center.into(),
+                    None => Point3::from_vec(w.world_transform.w.truncate()),
+                };
+                let depth = mx_view.transform_point(center).z;
+                transparent_draws.push(TransparentDraw {
+                    depth,
+                    instance,
+                    material,
+                    gpu_data,
+                    joint_buffer_view,
+                    displacement_view,
+                });
+                continue;
+            }
+
             Self::render_mesh(
                 &mut self.encoder,
                 self.const_buf.clone(),
                 gpu_data.instances.clone(),
-                self.light_buf.clone(),
+                self.light_buf_view.clone(),
+                self.cascade_buf.clone(),
                 self.pbr_buf.clone(),
                 self.displacement_contributions_buf.clone(),
-                self.out_color.clone(),
-                self.out_depth.clone(),
+                out_color.clone(),
+                out_depth.clone(),
                 &self.pso,
                 &self.map_default,
                 &[instance],
@@ -1018,6 +2392,11 @@ impl Renderer {
                 &shadow_sampler,
                 &shadow0,
                 &shadow1,
+                &shadow_depths,
+                &shadow_cube,
+                &shadow_cascades,
+                &ambient_occlusion,
+                &pbr_frame,
                 &gpu_data.displacement_contributions,
                 (displacement_view, self.map_default.to_param().1),
                 joint_buffer_view,
@@ -1025,28 +2404,37 @@ impl Renderer {
             );
         }
 
+        if overlays {
+            self.cull_stats = cull_stats;
+        }
+
         // render instanced meshes
         for data in self.instance_cache.values() {
             if data.list.len() > self.inst_buf.len() {
-                self.inst_buf = self.factory
-                    .create_buffer(
-                        data.list.len(),
-                        gfx::buffer::Role::Vertex,
-                        gfx::memory::Usage::Dynamic,
-                        gfx::memory::Bind::TRANSFER_DST,
-                    )
-                    // TODO: Better error handling
-                    .unwrap();
+                let buffer = self.factory.create_buffer(
+                    data.list.len(),
+                    gfx::buffer::Role::Vertex,
+                    gfx::memory::Usage::Dynamic,
+                    gfx::memory::Bind::TRANSFER_DST,
+                );
+                match buffer {
+                    Ok(buffer) => self.inst_buf = buffer,
+                    Err(e) => {
+                        error!("Unable to create a buffer for {} instances, skipping them: {:?}", data.list.len(), e);
+                        continue;
+                    }
+                }
             }
             Self::render_mesh(
                 &mut self.encoder,
                 self.const_buf.clone(),
                 self.inst_buf.clone(),
-                self.light_buf.clone(),
+                self.light_buf_view.clone(),
+                self.cascade_buf.clone(),
                 self.pbr_buf.clone(),
                 self.displacement_contributions_buf.clone(),
-                self.out_color.clone(),
-                self.out_depth.clone(),
+                out_color.clone(),
+                out_depth.clone(),
                 &self.pso,
                 &self.map_default,
                 &data.list,
@@ -1056,6 +2444,11 @@ impl Renderer {
                 &shadow_sampler,
                 &shadow0,
                 &shadow1,
+                &shadow_depths,
+                &shadow_cube,
+                &shadow_cascades,
+                &ambient_occlusion,
+                &pbr_frame,
                 &ZEROED_DISPLACEMENT_CONTRIBUTION,
                 (self.default_displacement_buffer_view.clone(), self.map_default.to_param().1),
                 self.default_joint_buffer_view.clone(),
@@ -1087,8 +2480,8 @@ impl Renderer {
                     globals: self.const_buf.clone(),
                     resource: texture.to_param().0.raw().clone(),
                     sampler: texture.to_param().1,
-                    target: self.out_color.clone(),
-                    depth_target: self.out_depth.clone(),
+                    target: out_color.clone(),
+                    depth_target: out_depth.clone(),
                 };
                 self.encoder.draw(&quad_slice, &self.pso.quad, &data);
             }
@@ -1105,17 +2498,63 @@ impl Renderer {
                     resource: cubemap.to_param().0.raw().clone(),
                     sampler: cubemap.to_param().1,
                     globals: self.const_buf.clone(),
-                    target: self.out_color.clone(),
-                    depth_target: self.out_depth.clone(),
+                    target: out_color.clone(),
+                    depth_target: out_depth.clone(),
                 };
                 self.encoder.draw(&quad_slice, &self.pso.skybox, &data);
             }
             Background::Color(_) => {}
         }
 
+        // draw transparent meshes, farthest first
+        transparent_draws.sort_by(|a, b| {
+            a.depth.partial_cmp(&b.depth).unwrap_or(cmp::Ordering::Equal)
+        });
+        for draw in transparent_draws {
+            Self::render_mesh(
+                &mut self.encoder,
+                self.const_buf.clone(),
+                draw.gpu_data.instances.clone(),
+                self.light_buf_view.clone(),
+                self.cascade_buf.clone(),
+                self.pbr_buf.clone(),
+                self.displacement_contributions_buf.clone(),
+                out_color.clone(),
+                out_depth.clone(),
+                &self.pso,
+                &self.map_default,
+                &[draw.instance],
+                draw.gpu_data.vertices.clone(),
+                draw.gpu_data.slice.clone(),
+                draw.material,
+                &shadow_sampler,
+                &shadow0,
+                &shadow1,
+                &shadow_depths,
+                &shadow_cube,
+                &shadow_cascades,
+                &ambient_occlusion,
+                &pbr_frame,
+                &draw.gpu_data.displacement_contributions,
+                (draw.displacement_view, self.map_default.to_param().1),
+                draw.joint_buffer_view,
+                draw.gpu_data.displacements.is_some(),
+            );
+        }
+
+        // resolve the floating point target
+        if !offscreen_targets.is_empty() {
+            self.resolve(&offscreen_targets, final_color);
+        }
+
+        if !overlays {
+            self.encoder.flush(&mut self.device);
+            return;
+        }
+
         // draw ui text
         for (_, font) in &self.font_cache {
-            font.draw(&mut self.encoder, &self.out_color, &self.out_depth);
+            font.draw(&mut self.encoder, final_color, out_depth);
         }
 
         // draw debug quads
@@ -1150,8 +2589,8 @@ impl Renderer {
                 globals: self.const_buf.clone(),
                 resource: quad.resource.clone(),
                 sampler: self.map_default.to_param().1,
-                target: self.out_color.clone(),
-                depth_target: self.out_depth.clone(),
+                target: final_color.raw().clone(),
+                depth_target: out_depth.clone(),
             };
             self.encoder.draw(&quad_slice, &self.pso.quad, &data);
         }
@@ -1165,10 +2604,11 @@ impl Renderer {
         encoder: &mut gfx::Encoder<back::Resources, back::CommandBuffer>,
         const_buf: h::Buffer<back::Resources, Globals>,
         inst_buf: h::Buffer<back::Resources, Instance>,
-        light_buf: h::Buffer<back::Resources, LightParam>,
+        light_buf: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        cascade_buf: h::Buffer<back::Resources, CascadeParam>,
         pbr_buf: h::Buffer<back::Resources, PbrParams>,
         displacement_contributions_buf: h::Buffer<back::Resources, DisplacementContribution>,
-        out_color: h::RenderTargetView<back::Resources, ColorFormat>,
+        out_color: h::RawRenderTargetView<back::Resources>,
         out_depth: h::DepthStencilView<back::Resources, DepthFormat>,
         pso: &PipelineStates<back::Resources>,
         map_default: &Texture<[f32; 4]>,
@@ -1179,6 +2619,11 @@ impl Renderer {
         shadow_sampler: &h::Sampler<back::Resources>,
         shadow0: &h::ShaderResourceView<back::Resources, f32>,
         shadow1: &h::ShaderResourceView<back::Resources, f32>,
+        shadow_depths: &[(h::RawShaderResourceView<back::Resources>, h::Sampler<back::Resources>); MAX_SHADOW_MAPS],
+        shadow_cube: &h::ShaderResourceView<back::Resources, f32>,
+        shadow_cascades: &h::ShaderResourceView<back::Resources, f32>,
+        ambient_occlusion: &(h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+        pbr_frame: &PbrFrame,
         displacement_contributions: &[DisplacementContribution],
         displacements: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
         joint_transform_buffer_view: h::ShaderResourceView<back::Resources, [f32; 4]>,
@@ -1203,6 +2648,11 @@ impl Renderer {
                     encoder.update_buffer(&displacement_contributions_buf, data, 0).unwrap();
                     params.pbr_flags |= PbrFlags::DISPLACEMENT_BUFFER.bits();
                 }
+                params.camera = pbr_frame.camera;
+                if pbr_frame.specular_levels > 0 {
+                    params.pbr_flags |= PbrFlags::ENVIRONMENT_MAP.bits();
+                    params.environment = [(pbr_frame.specular_levels - 1) as f32, 0.0, 0.0, 0.0];
+                }
                 encoder.update_constant_buffer(&pbr_buf, &params);
                 let map_params = maps.into_params(map_default);
                 let data = pbr_pipe::Data {
@@ -1210,30 +2660,51 @@ impl Renderer {
                     inst_buf,
                     globals: const_buf,
                     lights: light_buf,
+                    cascades: cascade_buf,
                     params: pbr_buf,
                     base_color_map: map_params.base_color,
                     normal_map: map_params.normal,
                     emissive_map: map_params.emissive,
                     metallic_roughness_map: map_params.metallic_roughness,
                     occlusion_map: map_params.occlusion,
+                    shadow_map0: (shadow0.clone(), shadow_sampler.clone()),
+                    shadow_map1: (shadow1.clone(), shadow_sampler.clone()),
+                    shadow_depth0: shadow_depths[0].0.clone(),
+                    shadow_depth_sampler0: shadow_depths[0].1.clone(),
+                    shadow_depth1: shadow_depths[1].0.clone(),
+                    shadow_depth_sampler1: shadow_depths[1].1.clone(),
+                    shadow_cube0: (shadow_cube.clone(), shadow_sampler.clone()),
+                    shadow_cascades: (shadow_cascades.clone(), shadow_sampler.clone()),
+                    irradiance_map: pbr_frame.irradiance.clone(),
+                    specular_map: pbr_frame.specular.clone(),
+                    brdf_lut: pbr_frame.brdf_lut.clone(),
+                    ambient_occlusion: ambient_occlusion.clone(),
                     color_target: out_color,
                     depth_target: out_depth,
                     displacement_contributions: displacement_contributions_buf,
                     displacements,
                     joint_transforms: joint_transform_buffer_view,
                 };
-                encoder.draw(&slice, &pso.pbr, &data);
+                encoder.draw(&slice, pso.pbr_pso(material), &data);
             }
             PsoData::Basic { map, .. } => {
                 //TODO: avoid excessive cloning
                 let data = basic_pipe::Data {
                     vbuf: vertex_buf,
                     inst_buf,
-                    cb_lights: light_buf,
+                    lights: light_buf,
                     cb_globals: const_buf.clone(),
+                    cb_cascades: cascade_buf,
                     tex_map: map.unwrap_or(map_default.clone()).to_param(),
                     shadow_map0: (shadow0.clone(), shadow_sampler.clone()),
                     shadow_map1: (shadow1.clone(), shadow_sampler.clone()),
+                    shadow_depth0: shadow_depths[0].0.clone(),
+                    shadow_depth_sampler0: shadow_depths[0].1.clone(),
+                    shadow_depth1: shadow_depths[1].0.clone(),
+                    shadow_depth_sampler1: shadow_depths[1].1.clone(),
+                    shadow_cube0: (shadow_cube.clone(), shadow_sampler.clone()),
+                    shadow_cascades: (shadow_cascades.clone(), shadow_sampler.clone()),
+                    ambient_occlusion: ambient_occlusion.clone(),
                     out_color,
                     out_depth: (out_depth, (0, 0)),
                 };
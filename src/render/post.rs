@@ -0,0 +1,58 @@
+//! Full screen effects applied after the scene is drawn.
+
+use render::PostPipelineState;
+use texture::Texture;
+
+/// A full screen pass run by the [`Renderer`] after drawing the scene.
+///
+/// Effects in [`Renderer::post_effects`] run in order on the tone mapped image, each one
+/// reading the result of the previous one, and the last one writes to the screen.
+/// UI text and debug quads are drawn afterwards.
+///
+/// The bright areas of a `Bloom` are taken from the scene before tone mapping,
+/// so lights brighter than white keep glowing, and are added at its place in the chain.
+///
+/// [`Renderer`]: struct.Renderer.html
+/// [`Renderer::post_effects`]: struct.Renderer.html#structfield.post_effects
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostEffect {
+    /// Bright areas bleed light into their surroundings.
+    Bloom {
+        /// Brightness of the scene, scaled by [`Renderer::exposure`] and before tone mapping,
+        /// above which colors contribute to the bloom.
+        ///
+        /// [`Renderer::exposure`]: struct.Renderer.html#structfield.exposure
+        threshold: f32,
+        /// Multiplier of the bloom added to the image.
+        intensity: f32,
+        /// Spread of the bloom in pixels.
+        radius: f32,
+    },
+    /// Fast approximate anti-aliasing, smoothing the jagged edges of the image.
+    Fxaa,
+    /// Darkens the borders of the screen.
+    Vignette {
+        /// Size of the darkened area, `1.0` reaching the corners.
+        offset: f32,
+        /// How dark the corners get, from `0.0` to `1.0`.
+        darkness: f32,
+    },
+    /// Remaps the colors through a lookup table.
+    ///
+    /// The table is a strip of `N` square slices of `N` by `N` texels each, laid out
+    /// horizontally by increasing blue, with red along the width and green along the
+    /// height of each slice. A typical size is `256` by `16` texels.
+    ColorGrading {
+        /// The lookup table, best loaded with a `Nearest` or `Bilinear` clamping sampler.
+        lut: Texture<[f32; 4]>,
+    },
+    /// A pass using a user shader, see [`Factory::post_effect_pipeline`].
+    ///
+    /// [`Factory::post_effect_pipeline`]: ../struct.Factory.html#method.post_effect_pipeline
+    Custom {
+        /// The pipeline to draw the pass with.
+        pipeline: PostPipelineState,
+        /// Values passed to the shader as `u_Params`.
+        params: [f32; 4],
+    },
+}
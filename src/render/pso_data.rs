@@ -2,7 +2,6 @@ use color;
 use gfx::handle as h;
 use material::Material;
 use render::{BackendResources, PbrParams};
-use std::mem;
 use texture::Texture;
 
 type MapParam = (
@@ -18,6 +17,7 @@ bitflags! {
         const EMISSIVE_MAP           = 1 << 3;
         const OCCLUSION_MAP          = 1 << 4;
         const DISPLACEMENT_BUFFER    = 1 << 5;
+        const ENVIRONMENT_MAP        = 1 << 6;
     }
 }
 
@@ -65,6 +65,7 @@ pub(crate) enum PsoData {
     },
     Basic {
         color: u32,
+        opacity: f32,
         param0: f32,
         map: Option<Texture<[f32; 4]>>,
     },
@@ -100,8 +101,10 @@ impl Material {
                     normal_scale: material.normal_scale,
                     occlusion_strength: material.occlusion_strength,
                     pbr_flags: pbr_flags.bits(),
-                    _padding0: unsafe { mem::uninitialized() },
-                    _padding1: unsafe { mem::uninitialized() },
+                    _padding0: 0.0,
+                    _padding1: 0.0,
+                    _padding2: [0.0; 3],
+                    environment: [0.0; 4],
                 };
                 PsoData::Pbr {
                     maps: PbrMaps {
@@ -116,36 +119,43 @@ impl Material {
             }
             Material::Basic(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: params.opacity,
                 map: params.map.clone(),
                 param0: 0.0,
             },
             Material::CustomBasic(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: 1.0,
                 map: params.map.clone(),
                 param0: 0.0,
             },
             Material::Line(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: 1.0,
                 map: None,
                 param0: 0.0,
             },
             Material::Wireframe(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: 1.0,
                 map: None,
                 param0: 0.0,
             },
             Material::Lambert(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: params.opacity,
                 map: None,
                 param0: if params.flat { 0.0 } else { 1.0 },
             },
             Material::Phong(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: params.opacity,
                 map: None,
                 param0: params.glossiness,
             },
             Material::Sprite(ref params) => PsoData::Basic {
                 color: !0,
+                opacity: 1.0,
                 map: Some(params.map.clone()),
                 param0: 0.0,
             },
@@ -79,8 +79,8 @@ impl Source {
 }
 
 macro_rules! decl_shaders {
-    { $(($pso:ident, $doc:ident, $ty:ident),)* } => {
-        $( decl_shaders!($pso, $doc, $ty); )*
+    { $(($pso:ident, $doc:ident, $ty:ident $(, $vs:ident)?),)* } => {
+        $( decl_shaders!($pso, $doc, $ty $(, $vs)?); )*
 
         /// The set of shaders needed by the `three` renderer.
         #[derive(Clone, Debug, Default)]
@@ -93,6 +93,10 @@ macro_rules! decl_shaders {
     };
 
     ($pso:ident, $doc:ident, $ty:ident) => {
+        decl_shaders!($pso, $doc, $ty, $pso);
+    };
+
+    ($pso:ident, $doc:ident, $ty:ident, $vs:ident) => {
         #[allow(missing_docs)]
         #[derive(Clone, Debug)]
         pub struct $ty {
@@ -107,7 +111,7 @@ macro_rules! decl_shaders {
             /// Loads user shader code.
             pub fn user<P: AsRef<Path>>(root: P) -> io::Result<Self> {
                 Ok(Self {
-                    vs: Source::user(&root, stringify!($pso), "vs")?,
+                    vs: Source::user(&root, stringify!($vs), "vs")?,
                     ps: Source::user(&root, stringify!($pso), "ps")?,
                 })
             }
@@ -116,7 +120,7 @@ macro_rules! decl_shaders {
         impl Default for $ty {
             fn default() -> Self {
                 Self {
-                    vs: Source::default(stringify!($pso), "vs").unwrap(),
+                    vs: Source::default(stringify!($vs), "vs").unwrap(),
                     ps: Source::default(stringify!($pso), "ps").unwrap(),
                 }
             }
@@ -124,13 +128,20 @@ macro_rules! decl_shaders {
     };
 }
 
+// The full screen passes share the `fullscreen_vs.glsl` vertex shader.
 decl_shaders! {
     (basic, basic, Basic),
+    (depth_normal, depth_normal, DepthNormal),
     (gouraud, Gouraud, Gouraud),
+    (ibl, ibl, Ibl),
     (pbr, PBR, Pbr),
     (phong, Phong, Phong),
+    (post, post, Post, fullscreen),
     (quad, quad, Quad),
     (shadow, shadow, Shadow),
     (skybox, skybox, Skybox),
+    (ssao, ssao, Ssao, fullscreen),
     (sprite, sprite, Sprite),
+    (tonemap, tonemap, Tonemap, fullscreen),
+    (vsm, vsm, Vsm, fullscreen),
 }
@@ -1,10 +1,12 @@
 //! `Scene` and `SyncGuard` structures.
 
+#[cfg(feature = "audio")]
+use audio;
 use node;
 use color::Color;
 use hub::{Hub, HubPtr, SubNode};
 use object::{Base, DowncastObject, Group, Object};
-use texture::{CubeMap, Texture};
+use texture::{CubeMap, EnvironmentMap, Texture};
 
 use std::mem;
 use std::marker::PhantomData;
@@ -31,6 +33,13 @@ pub struct Scene {
     pub(crate) first_child: Option<node::NodePointer>,
     /// See [`Background`](struct.Background.html).
     pub background: Background,
+    /// Image based lighting of the `Pbr` materials in the scene, see
+    /// [`EnvironmentMap`](../struct.EnvironmentMap.html).
+    pub environment: Option<EnvironmentMap>,
+    #[cfg(feature = "audio")]
+    /// Point of view of the spatial audio sources, see
+    /// [`Listener`](../audio/struct.Listener.html).
+    pub listener: Option<audio::Listener>,
 }
 
 impl Scene {
@@ -122,7 +131,7 @@ impl Scene {
 /// # use three::Object;
 /// # let mut win = three::Window::new("SyncGuard example");
 /// # let geometry = three::Geometry::default();
-/// # let material = three::material::Basic { color: three::color::RED, map: None };
+/// # let material = three::material::Basic { color: three::color::RED, map: None, .. Default::default() };
 /// # let mesh = win.factory.mesh(geometry, material);
 /// # let enemy = Enemy { mesh, is_visible: true };
 /// # win.scene.add(&enemy);
@@ -182,7 +191,7 @@ impl<'a> SyncGuard<'a> {
         node::Node {
             visible: wn.world_visible,
             name: wn.node.name.clone(),
-            transform: wn.world_transform.into(),
+            transform: node::TransformInternal::from(wn.world_transform).into(),
             material: match wn.node.sub_node {
                 SubNode::Visual(ref mat, _, _) => Some(mat.clone()),
                 _ => None,
@@ -208,6 +217,8 @@ impl<'a> SyncGuard<'a> {
     /// * [`Point`]: Returns the [`LightData`] for the light.
     /// * [`Directional`]: Returns the [`LightData`] for the light.
     /// * [`Hemisphere`]: Returns the [`HemisphereLightData`] for the light.
+    /// * [`Spot`]: Returns the [`SpotLightData`] for the light.
+    /// * [`audio::Source`]: Returns the [`Playback`] state of the source.
     ///
     /// The other object types do not have a user-facing way to represent their internal data,
     /// and so return `()`.
@@ -223,6 +234,10 @@ impl<'a> SyncGuard<'a> {
     /// [`Directional`]: ../light/struct.Directional.html
     /// [`Hemisphere`]: ../light/struct.Hemisphere.html
     /// [`HemisphereLightData`]: ../light/struct.HemisphereLightData.html
+    /// [`Spot`]: ../light/struct.Spot.html
+    /// [`SpotLightData`]: ../light/struct.SpotLightData.html
+    /// [`audio::Source`]: ../audio/struct.Source.html
+    /// [`Playback`]: ../audio/struct.Playback.html
     pub fn resolve_data<T: 'a + Object>(
         &self,
         object: &T,
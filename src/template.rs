@@ -50,7 +50,15 @@
 //! of the data on the GPU. This reduces GPU resource usage and, for any meshes that also share
 //! a material, allows three to render many objects at once.
 //!
+//! # Scenes
+//!
+//! A template can describe several scenes sharing its objects, such as the scenes of a glTF
+//! asset pack. Each [`SceneTemplate`] lists the root objects of the scene, and
+//! [`Factory::instantiate_template_scene`] creates an instance of the objects in one scene
+//! only, selected by index or by name.
+//!
 //! [`Factory::instantiate_template`]: ../struct.Factory.html#method.instantiate_template
+//! [`Factory::instantiate_template_scene`]: ../struct.Factory.html#method.instantiate_template_scene
 //! [`Factory::load_gltf`]: ../struct.Factory.html#method.load_gltf
 //! [`Factory::upload_geometry`]: ../struct.Factory.html#method.upload_geometry
 //! [`Object`]: ../trait.Object.html
@@ -60,6 +68,7 @@
 //! [`Template`]: ./struct.Template.html
 //! [`ObjectTemplate`]: ./struct.ObjectTemplate.html
 //! [`AnimationTemplate`]: ./struct.AnimationTemplate.html
+//! [`SceneTemplate`]: ./struct.SceneTemplate.html
 //! [`AnimationTemplate::tracks`]: ./struct.AnimationTemplate.html#structfield.tracks
 //! [`nodes`]: ./struct.Template.html#structfield.nodes
 //! [`cameras`]: ./struct.Template.html#structfield.cameras
@@ -68,6 +77,8 @@
 //! [`objects`]: ./struct.Template.html#structfield.objects
 //! [`InstancedGeometry`]: ./struct.InstancedGeometry.html
 
+use std::collections::HashSet;
+
 use animation::Track;
 use camera::Projection;
 use color::Color;
@@ -134,6 +145,14 @@ pub struct Template {
 
     /// Templates for animation clips that target objects instantiated from this template.
     pub animations: Vec<AnimationTemplate>,
+
+    /// The scenes described by the template.
+    ///
+    /// Templates loaded from glTF files have one scene per glTF scene. Use
+    /// [`Factory::instantiate_template_scene`] to create an instance of a single scene.
+    ///
+    /// [`Factory::instantiate_template_scene`]: ../struct.Factory.html#method.instantiate_template_scene
+    pub scenes: Vec<SceneTemplate>,
 }
 
 impl Template {
@@ -154,6 +173,111 @@ impl Template {
     ///
     /// [`Factory::group`]: ../struct.Factory.html#method.group
     pub fn new() -> Template { Default::default() }
+
+    /// Returns the index into [`scenes`] of the scene selected by index or by name.
+    ///
+    /// Returns `None` if there is no such scene.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "gltf")]
+    /// # fn main() {
+    /// # let mut window = three::Window::new("Three-rs");
+    /// let template = window.factory.load_gltf("asset-pack.gltf");
+    /// let index = template.find_scene("Forest").expect("no forest in the pack");
+    /// assert_eq!(template.find_scene(index), Some(index));
+    /// # }
+    /// # #[cfg(not(feature = "gltf"))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// [`scenes`]: #structfield.scenes
+    pub fn find_scene<'a, S: Into<SceneSelector<'a>>>(&self, scene: S) -> Option<usize> {
+        match scene.into() {
+            SceneSelector::Index(index) if index < self.scenes.len() => Some(index),
+            SceneSelector::Index(_) => None,
+            SceneSelector::Name(name) => self.scenes
+                .iter()
+                .position(|scene| scene.name.as_ref().map(String::as_str) == Some(name)),
+        }
+    }
+
+    /// Returns the indices into `objects` of the objects making up a scene: its roots, their
+    /// descendants, and the skeletons of any bones among them.
+    pub(crate) fn scene_objects(&self, scene: usize) -> HashSet<usize> {
+        let roots = &self.scenes[scene].roots;
+        let mut objects: HashSet<usize> = (0 .. self.objects.len())
+            .filter(|&index| {
+                let mut current = index;
+                loop {
+                    if roots.contains(&current) {
+                        return true;
+                    }
+                    match self.objects[current].parent {
+                        Some(parent) => current = self.groups[parent],
+                        None => return false,
+                    }
+                }
+            })
+            .collect();
+        for (index, &skeleton) in self.skeletons.iter().enumerate() {
+            let has_bones = self.bones
+                .iter()
+                .any(|bone| bone.skeleton == index && objects.contains(&bone.object));
+            if has_bones {
+                objects.insert(skeleton);
+            }
+        }
+        objects
+    }
+}
+
+/// A scene described by a [`Template`], as a subset of its objects.
+///
+/// See the [module documentation] for more information on scenes.
+///
+/// [`Template`]: ./struct.Template.html
+/// [module documentation]: ./index.html#scenes
+#[derive(Debug, Clone, Default)]
+pub struct SceneTemplate {
+    /// An optional name for the scene.
+    pub name: Option<String>,
+
+    /// The root objects of the scene, given as indices into the [`objects`] array of the
+    /// parent [`Template`].
+    ///
+    /// All descendants of the roots belong to the scene as well.
+    ///
+    /// [`Template`]: ./struct.Template.html
+    /// [`objects`]: ./struct.Template.html#structfield.objects
+    pub roots: Vec<usize>,
+}
+
+/// Selects a scene of a [`Template`], either by index into [`scenes`] or by name.
+///
+/// Both `usize` and `&str` convert into a `SceneSelector`.
+///
+/// [`Template`]: ./struct.Template.html
+/// [`scenes`]: ./struct.Template.html#structfield.scenes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneSelector<'a> {
+    /// Index into [`scenes`](./struct.Template.html#structfield.scenes).
+    Index(usize),
+    /// Name of the scene.
+    Name(&'a str),
+}
+
+impl<'a> From<usize> for SceneSelector<'a> {
+    fn from(index: usize) -> Self {
+        SceneSelector::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for SceneSelector<'a> {
+    fn from(name: &'a str) -> Self {
+        SceneSelector::Name(name)
+    }
 }
 
 /// Common data used by all object types.
@@ -9,6 +9,7 @@ use mint;
 use object;
 
 use color::Color;
+use error::Error;
 use hub::Operation as HubOperation;
 use render::{BackendCommandBuffer, BackendFactory, BackendResources, ColorFormat, DepthFormat};
 
@@ -104,6 +105,21 @@ impl Font {
         }
     }
 
+    /// Parses the font first, returning an error if the data is malformed.
+    pub(crate) fn try_new<T: Into<g::SharedBytes<'static>>>(
+        buf: T,
+        id: String,
+        factory: BackendFactory,
+    ) -> Result<Font, Error> {
+        let font = g::Font::from_bytes(buf).map_err(|e| Error::Decode(e.to_string()))?;
+        Ok(Font {
+            brush: Rc::new(RefCell::new(
+                g::GlyphBrushBuilder::using_font(font).build(factory),
+            )),
+            id: id,
+        })
+    }
+
     pub(crate) fn queue(
         &self,
         section: &g::OwnedVariedSection,
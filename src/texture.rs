@@ -1,9 +1,11 @@
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
 use gfx::handle as h;
 use mint;
 
-use render::BackendResources;
+use render::{BackendResources, HdrFormat};
 use util;
 
 pub use gfx::texture::{FilterMethod, WrapMode};
@@ -130,3 +132,37 @@ impl<T> CubeMap<T> {
         (self.view.clone(), self.sampler.clone())
     }
 }
+
+/// Surroundings of a [`Scene`] used to light the `Pbr` material.
+///
+/// Created by [`Factory::environment_map`] or [`Factory::load_environment_map`] and set
+/// with [`Scene::environment`]. The renderer prefilters it into diffuse irradiance and
+/// specular reflections by roughness the first time it is drawn.
+///
+/// [`Scene`]: struct.Scene.html
+/// [`Scene::environment`]: struct.Scene.html#structfield.environment
+/// [`Factory::environment_map`]: struct.Factory.html#method.environment_map
+/// [`Factory::load_environment_map`]: struct.Factory.html#method.load_environment_map
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    pub(crate) source: EnvironmentSource,
+    pub(crate) irradiance: h::ShaderResourceView<BackendResources, [f32; 4]>,
+    pub(crate) irradiance_targets: [h::RenderTargetView<BackendResources, HdrFormat>; 6],
+    pub(crate) specular: h::ShaderResourceView<BackendResources, [f32; 4]>,
+    /// Face targets of each mip level, from the smoothest to the roughest.
+    pub(crate) specular_targets: Vec<[h::RenderTargetView<BackendResources, HdrFormat>; 6]>,
+    pub(crate) sampler: h::Sampler<BackendResources>,
+    /// Set until the renderer has filled the irradiance and specular maps.
+    pub(crate) pending: Arc<AtomicBool>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum EnvironmentSource {
+    Cube(CubeMap<[f32; 4]>),
+    /// An equirectangular image, converted into `cube` before prefiltering.
+    Equirect {
+        map: Texture<[f32; 4]>,
+        cube: h::ShaderResourceView<BackendResources, [f32; 4]>,
+        targets: [h::RenderTargetView<BackendResources, HdrFormat>; 6],
+    },
+}
@@ -4,12 +4,17 @@ use glutin;
 use mint;
 use render;
 
+#[cfg(feature = "audio")]
+use audio;
+
 use camera::Camera;
 use factory::Factory;
 use input::Input;
 use render::Renderer;
 use scene::Scene;
 use std::path::PathBuf;
+#[cfg(feature = "audio")]
+use std::time::Duration;
 use glutin::{GlRequest, GlProfile, PossiblyCurrent};
 
 /// `Window` is the core entity of every `three-rs` application.
@@ -38,8 +43,11 @@ pub struct Window {
 /// Builder for creating new [`Window`](struct.Window.html) with desired parameters.
 #[derive(Debug, Clone)]
 pub struct Builder {
+    #[cfg(feature = "audio")]
+    audio_backend: audio::Backend,
     dimensions: glutin::dpi::LogicalSize,
     fullscreen: bool,
+    max_lights: usize,
     multisampling: u16,
     shader_directory: Option<PathBuf>,
     title: String,
@@ -47,6 +55,20 @@ pub struct Builder {
 }
 
 impl Builder {
+    /// Select where the sounds of the audio sources are played.
+    /// Defaults to the default output device of the system.
+    ///
+    /// Use `Backend::Null` or `Backend::Wav` to run without a sound card.
+    /// Errors creating the backend are logged, keeping the output device.
+    #[cfg(feature = "audio")]
+    pub fn audio_backend(
+        &mut self,
+        option: audio::Backend,
+    ) -> &mut Self {
+        self.audio_backend = option;
+        self
+    }
+
     /// Set the size of the viewport (the resolution) in logical pixels.
     /// That is the dpi setting affects the amount of pixels used but the window will
     /// take up the same amount of space regardless of dpi. Defaults to 1024x768.
@@ -68,6 +90,16 @@ impl Builder {
         self
     }
 
+    /// Sets the number of lights that can affect the scene at once. Lights beyond this
+    /// number are ignored. Defaults to `64`, values below `1` are clamped to `1`.
+    pub fn max_lights(
+        &mut self,
+        option: usize,
+    ) -> &mut Self {
+        self.max_lights = option.max(1);
+        self
+    }
+
     /// Sets the multisampling level to request. A value of `0` indicates that multisampling must
     /// not be enabled. Must be the power of 2. Defaults to `0`.
     pub fn multisampling(
@@ -147,11 +179,17 @@ impl Builder {
                     $( try_override!($name); )*
                 };
             }
-            try_override!(basic, gouraud, pbr, phong, quad, shadow, skybox, sprite,);
+            try_override!(basic, depth_normal, gouraud, ibl, pbr, phong, post, quad, shadow, skybox, ssao, sprite, tonemap, vsm,);
         }
 
-        let (renderer, windowedContext, mut factory) = Renderer::new(builder, context, &event_loop, &source_set);
+        let (renderer, windowedContext, mut factory) = Renderer::new(builder, context, &event_loop, &source_set, self.max_lights);
         let dpi = windowedContext.window().get_hidpi_factor();
+        #[cfg(feature = "audio")]
+        {
+            if let Err(e) = factory.set_audio_backend(self.audio_backend.clone()) {
+                error!("Can't use the {:?} audio backend: {}", self.audio_backend, e);
+            }
+        }
         let scene = factory.scene();
         Window {
             event_loop,
@@ -176,8 +214,11 @@ impl Window {
     /// Create new `Builder` with standard parameters.
     pub fn builder<T: Into<String>>(title: T) -> Builder {
         Builder {
+            #[cfg(feature = "audio")]
+            audio_backend: audio::Backend::Device,
             dimensions: glutin::dpi::LogicalSize::new(1024.0, 768.0),
             fullscreen: false,
+            max_lights: render::DEFAULT_MAX_LIGHTS,
             multisampling: 0,
             shader_directory: None,
             title: title.into(),
@@ -193,6 +234,11 @@ impl Window {
         if self.reset_input {
             input.reset();
         }
+        #[cfg(feature = "audio")]
+        {
+            // The virtual audio backends play along with the frames.
+            self.factory.advance_audio(Duration::from_secs_f32(input.delta_time()));
+        }
 
         let wc = &self.windowedContext;
         self.windowedContext.swap_buffers().unwrap();